@@ -0,0 +1,894 @@
+//! Long-lived handle on an archive.
+//!
+//! `Archive` is opened once and keeps the parsed `goals.yaml` in memory
+//! until the file changes on storage, so repeated reads (a search box, a
+//! launcher refreshing its list) do not re-read and re-parse it. Mutations
+//! made through the same handle are serialized by a lock.
+//!
+//! The free functions at the crate root are thin wrappers that attach a
+//! short-lived `Archive` to the given path.
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{self, ArchiveEvent, ArchiveListener};
+use crate::export::{self, ExportSink};
+use crate::ffi_types::AppError;
+use crate::types::{
+    timestamp_to_date_iso, BatchOp, BatchResult, CheckReport, DayTotal, ExportFormat,
+    FrontmatterField, Goal, GoalSort, GoalStatus, GoalTemplate, IcsImportOptions, IcsImportReport,
+    ImportMode, ImportSummary, JournalEntry, LaunchCommand, Platform, RankedGoal, ResolvedCommand,
+    RetentionPolicy, ScoredGoal, SearchHit, SearchRanking, Session, SessionKind, SessionMetadata,
+    SessionPage, SessionValidation, SnapshotInfo, TrackerImportOptions, TrackerImportReport,
+};
+use crate::{
+    batch, check, goals, ical, importer, journal, launch, manifest, notes, search, session_graph,
+    snapshot, storage_io, templates, transfer,
+};
+
+/// Options used when opening an `Archive`.
+///
+/// - `create_if_missing`: create `goals.yaml`, `graphs/` and `notes/` when
+///   the archive does not exist yet. When `false`, opening a missing
+///   archive fails with `AppError::NotFound`.
+/// - `session_validation`: rules applied by `add_session` when the call
+///   does not pass its own.
+/// - `snapshot_retention`: when set, old snapshots are pruned with this
+///   policy every time `create_snapshot` is called on the handle.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveOptions {
+    pub create_if_missing: bool,
+    pub session_validation: SessionValidation,
+    pub snapshot_retention: Option<RetentionPolicy>,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        Self {
+            create_if_missing: true,
+            session_validation: SessionValidation::default(),
+            snapshot_retention: None,
+        }
+    }
+}
+
+struct GoalsCache {
+    fingerprint: String,
+    /// Hash of the content while `goals.yaml` is recently modified, when
+    /// the fingerprint alone can miss a rewrite.
+    content_hash: Option<String>,
+    goals: Vec<Goal>,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Object))]
+pub struct Archive {
+    path: PathBuf,
+    options: ArchiveOptions,
+    goals_cache: Mutex<Option<GoalsCache>>,
+    write_lock: Mutex<()>,
+}
+
+impl Archive {
+    /// Wrap `path` without touching storage.
+    pub(crate) fn attach(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            options: ArchiveOptions::default(),
+            goals_cache: Mutex::new(None),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// `attach`, refusing an archive written with a newer schema the way
+    /// `open` does. The free functions go through here, so reads without a
+    /// handle are gated too.
+    pub(crate) fn attach_supported(path: impl Into<PathBuf>) -> Result<Self, AppError> {
+        let archive = Self::attach(path);
+        manifest::ensure_supported(&archive.path)?;
+        Ok(archive)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Current goals, read and parsed again only when the fingerprint of
+    /// `goals.yaml` changed. Until the file is old enough for its times to
+    /// be trusted, its content is compared as well.
+    fn goals(&self) -> Result<Vec<Goal>, AppError> {
+        let path = goals::goals_path(&self.path);
+        let mut cache = lock(&self.goals_cache);
+        let Some(fingerprint) = storage_io::fingerprint(&self.path, &path)? else {
+            *cache = None;
+            return Ok(vec![]);
+        };
+        let mut data = None;
+        if let Some(cached) = cache.as_mut().filter(|c| c.fingerprint == fingerprint) {
+            let Some(hash) = &cached.content_hash else {
+                return Ok(cached.goals.clone());
+            };
+            let content = storage_io::read_to_string(&self.path, &path)?.unwrap_or_default();
+            if storage_io::content_hash(&content) == *hash {
+                if !storage_io::recently_modified(&self.path, &path)? {
+                    cached.content_hash = None;
+                }
+                return Ok(cached.goals.clone());
+            }
+            data = Some(content);
+        }
+        let data = match data {
+            Some(data) => data,
+            None => storage_io::read_to_string(&self.path, &path)?.unwrap_or_default(),
+        };
+        let goals = goals::parse_goals(&data)?;
+        let content_hash = storage_io::recently_modified(&self.path, &path)?
+            .then(|| storage_io::content_hash(&data));
+        *cache = Some(GoalsCache {
+            fingerprint,
+            content_hash,
+            goals: goals.clone(),
+        });
+        Ok(goals)
+    }
+
+    /// Run a mutation under the write lock, drop the goals cache afterwards
+    /// (file timestamps are too coarse to notice quick rewrites) and notify
+    /// subscribers with the events derived from the result.
+    ///
+    /// The archive is migrated to the current schema first, and archives
+    /// with a newer schema are never written to.
+    fn mutate<T>(
+        &self,
+        f: impl FnOnce(&Path) -> Result<T, AppError>,
+        to_events: impl FnOnce(&T) -> Vec<ArchiveEvent>,
+    ) -> Result<T, AppError> {
+        let _guard = lock(&self.write_lock);
+        let result = events::record_local(&self.path, || {
+            manifest::migrate(&self.path)?;
+            f(&self.path)
+        });
+        *lock(&self.goals_cache) = None;
+        let value = result?;
+        events::emit(&self.path, &to_events(&value));
+        Ok(value)
+    }
+
+    /// `mutate`, recording the files changed by `f` in the journal as
+    /// `operation` so the change can be undone.
+    fn mutate_journaled<T>(
+        &self,
+        operation: &str,
+        f: impl FnOnce(&Path) -> Result<T, AppError>,
+        to_events: impl FnOnce(&T) -> Vec<ArchiveEvent>,
+    ) -> Result<T, AppError> {
+        self.mutate(
+            |path| journal::record(path, operation, || f(path)),
+            to_events,
+        )
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+impl Archive {
+    /// Open the archive at `path`, migrating it to the current schema.
+    ///
+    /// - `options`: optional `ArchiveOptions`, defaults to creating the
+    ///   archive if needed and the default session validation.
+    ///
+    /// Fails with `AppError::UnsupportedSchemaVersion` if the archive was
+    /// written by a newer version of the library.
+    #[cfg_attr(not(target_arch = "wasm32"), uniffi::constructor)]
+    pub fn open(path: String, options: Option<ArchiveOptions>) -> Result<Arc<Self>, AppError> {
+        let options = options.unwrap_or_default();
+        let path = PathBuf::from(path);
+        if !options.create_if_missing && !storage_io::exists(&path, &goals::goals_path(&path))? {
+            return Err(AppError::NotFound {
+                resource: "archive".into(),
+                id: path.display().to_string(),
+            });
+        }
+        // Before creating `goals.yaml`, so a new archive is not mistaken
+        // for one that predates the manifest.
+        events::record_local(&path, || manifest::migrate(&path))?;
+        storage_io::ensure_archive_structure(&path)?;
+        Ok(Arc::new(Self {
+            options,
+            ..Self::attach(path)
+        }))
+    }
+
+    /// Path of the archive directory.
+    pub fn archive_path(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    /// Register `listener` for `ArchiveEvent`s on this archive, including
+    /// changes made by other processes. Returns a subscription id for
+    /// `unsubscribe`.
+    pub fn subscribe(&self, listener: Arc<dyn ArchiveListener>) -> u64 {
+        events::subscribe(&self.path, listener)
+    }
+
+    /// Stop delivering events to the subscription `subscription_id`.
+    pub fn unsubscribe(&self, subscription_id: u64) {
+        events::unsubscribe(&self.path, subscription_id)
+    }
+
+    /// See [`crate::list_goals`].
+    pub fn list_goals(
+        &self,
+        statuses: Option<Vec<GoalStatus>>,
+        sort: Option<GoalSort>,
+    ) -> Result<Vec<Goal>, AppError> {
+        let mut goals = goals::filter_goals(self.goals()?, statuses.as_deref());
+        goals::sort_goals(&self.path, &mut goals, sort.unwrap_or_default())?;
+        Ok(goals)
+    }
+
+    /// See [`crate::list_trash`].
+    pub fn list_trash(&self) -> Result<Vec<Goal>, AppError> {
+        Ok(self.goals()?.into_iter().filter(|g| g.trashed).collect())
+    }
+
+    /// See [`crate::search_goals`].
+    pub fn search_goals(
+        &self,
+        query: String,
+        is_reward: Option<bool>,
+        statuses: Option<Vec<GoalStatus>>,
+        sort_by_recent: Option<bool>,
+    ) -> Result<Vec<Goal>, AppError> {
+        let goals = goals::filter_goals(self.goals()?, statuses.as_deref());
+        Ok(goals::rank_goals(
+            &self.path,
+            goals,
+            &query,
+            is_reward,
+            sort_by_recent.unwrap_or(true),
+        ))
+    }
+
+    /// See [`crate::search_goals_scored`].
+    pub fn search_goals_scored(
+        &self,
+        query: String,
+        is_reward: Option<bool>,
+        statuses: Option<Vec<GoalStatus>>,
+        sort_by_recent: Option<bool>,
+    ) -> Result<Vec<ScoredGoal>, AppError> {
+        let goals = goals::filter_goals(self.goals()?, statuses.as_deref());
+        Ok(goals::score_goals(
+            &self.path,
+            goals,
+            &query,
+            is_reward,
+            sort_by_recent.unwrap_or(true),
+        ))
+    }
+
+    /// See [`crate::search_goals_ranked`].
+    pub fn search_goals_ranked(
+        &self,
+        query: String,
+        is_reward: Option<bool>,
+        statuses: Option<Vec<GoalStatus>>,
+        ranking: Option<SearchRanking>,
+    ) -> Result<Vec<RankedGoal>, AppError> {
+        let goals = goals::filter_goals(self.goals()?, statuses.as_deref());
+        search::rank_goals_weighted(
+            &self.path,
+            goals,
+            &query,
+            is_reward,
+            &ranking.unwrap_or_default(),
+        )
+    }
+
+    /// See [`crate::search_archive`].
+    pub fn search_archive(&self, query: String) -> Result<Vec<SearchHit>, AppError> {
+        search::search_archive(&self.path, &self.goals()?, &query)
+    }
+
+    /// See [`crate::add_goal`].
+    pub fn add_goal(
+        &self,
+        name: String,
+        is_reward: bool,
+        commands: Vec<String>,
+        quantity_name: Option<String>,
+    ) -> Result<Goal, AppError> {
+        self.mutate_journaled(
+            "add_goal",
+            |archive| goals::add_goal(archive, &name, is_reward, commands, quantity_name),
+            |goal| vec![ArchiveEvent::GoalAdded { goal_id: goal.id }],
+        )
+    }
+
+    /// See [`crate::get_note`].
+    pub fn get_note(&self, goal_id: u64) -> Result<String, AppError> {
+        notes::get_note(&self.path, goal_id)
+    }
+
+    /// See [`crate::edit_note`].
+    pub fn edit_note(&self, goal_id: u64, content: String) -> Result<bool, AppError> {
+        self.mutate_journaled(
+            "edit_note",
+            |archive| notes::edit_note(archive, goal_id, &content),
+            |_| vec![ArchiveEvent::NoteEdited { goal_id }],
+        )?;
+        Ok(true)
+    }
+
+    /// See [`crate::get_note_frontmatter`].
+    pub fn get_note_frontmatter(&self, goal_id: u64) -> Result<Vec<FrontmatterField>, AppError> {
+        notes::get_note_frontmatter(&self.path, goal_id)
+    }
+
+    /// See [`crate::update_note_frontmatter`].
+    pub fn update_note_frontmatter(
+        &self,
+        goal_id: u64,
+        set: Vec<FrontmatterField>,
+        remove: Vec<String>,
+    ) -> Result<Vec<FrontmatterField>, AppError> {
+        self.mutate_journaled(
+            "update_note_frontmatter",
+            |archive| notes::update_note_frontmatter(archive, goal_id, set, &remove),
+            |_| vec![ArchiveEvent::NoteEdited { goal_id }],
+        )
+    }
+
+    /// See [`crate::get_note_body`].
+    pub fn get_note_body(&self, goal_id: u64) -> Result<String, AppError> {
+        notes::get_note_body(&self.path, goal_id)
+    }
+
+    /// See [`crate::set_goal_status`].
+    pub fn set_goal_status(&self, goal_id: u64, status: GoalStatus) -> Result<Goal, AppError> {
+        self.mutate_journaled(
+            "set_goal_status",
+            |archive| goals::set_goal_status(archive, goal_id, status),
+            |goal| vec![ArchiveEvent::GoalUpdated { goal_id: goal.id }],
+        )
+    }
+
+    /// See [`crate::set_pinned`].
+    pub fn set_pinned(&self, goal_id: u64, pinned: bool) -> Result<Goal, AppError> {
+        self.mutate_journaled(
+            "set_pinned",
+            |archive| goals::set_pinned(archive, goal_id, pinned),
+            |goal| vec![ArchiveEvent::GoalUpdated { goal_id: goal.id }],
+        )
+    }
+
+    /// See [`crate::set_launch_commands`].
+    pub fn set_launch_commands(
+        &self,
+        goal_id: u64,
+        commands: Vec<LaunchCommand>,
+    ) -> Result<Goal, AppError> {
+        self.mutate_journaled(
+            "set_launch_commands",
+            |archive| goals::set_launch_commands(archive, goal_id, commands),
+            |goal| vec![ArchiveEvent::GoalUpdated { goal_id: goal.id }],
+        )
+    }
+
+    /// See [`crate::resolve_launch_commands`].
+    pub fn resolve_launch_commands(
+        &self,
+        goal_id: u64,
+        platform: Option<Platform>,
+    ) -> Result<Vec<ResolvedCommand>, AppError> {
+        let platform = platform.unwrap_or_else(launch::current_platform);
+        launch::resolve_launch_commands(&self.path, goal_id, platform)
+    }
+
+    /// See [`crate::move_goal`].
+    pub fn move_goal(&self, goal_id: u64, before_id: Option<u64>) -> Result<Goal, AppError> {
+        self.mutate_journaled(
+            "move_goal",
+            |archive| goals::move_goal(archive, goal_id, before_id),
+            |goal| vec![ArchiveEvent::GoalUpdated { goal_id: goal.id }],
+        )
+    }
+
+    /// See [`crate::set_goal_trashed`].
+    pub fn set_goal_trashed(&self, goal_id: u64, trashed: bool) -> Result<Goal, AppError> {
+        self.mutate_journaled(
+            "set_goal_trashed",
+            |archive| goals::set_goal_trashed(archive, goal_id, trashed),
+            |goal| {
+                vec![ArchiveEvent::GoalTrashed {
+                    goal_id: goal.id,
+                    trashed: goal.trashed,
+                }]
+            },
+        )
+    }
+
+    /// See [`crate::add_session`]. Falls back to the archive's
+    /// `session_validation` when `validation` is `None`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_session(
+        &self,
+        goal_id: u64,
+        goal_name: String,
+        start_ts_secs: i64,
+        duration_secs: u32,
+        is_reward: bool,
+        quantity: Option<u32>,
+        validation: Option<SessionValidation>,
+        metadata: Option<SessionMetadata>,
+    ) -> Result<Session, AppError> {
+        let start_at = parse_start_ts(start_ts_secs)?;
+        let validation = validation.unwrap_or_else(|| self.options.session_validation.clone());
+
+        self.mutate_journaled(
+            "add_session",
+            |archive| {
+                session_graph::add_session(
+                    archive,
+                    goal_id,
+                    &goal_name,
+                    start_at,
+                    duration_secs,
+                    is_reward,
+                    quantity,
+                    &validation,
+                    &metadata.unwrap_or_default(),
+                )
+            },
+            |session| {
+                let mut events = vec![ArchiveEvent::SessionAdded {
+                    date_iso: timestamp_to_date_iso(session.start_at),
+                    session_id: session.id.clone(),
+                }];
+                if !is_reward {
+                    events.push(ArchiveEvent::GoalUpdated { goal_id });
+                }
+                events
+            },
+        )
+    }
+
+    /// See [`crate::apply_batch`].
+    pub fn apply_batch(
+        &self,
+        ops: Vec<BatchOp>,
+        validation: Option<SessionValidation>,
+    ) -> Result<Vec<BatchResult>, AppError> {
+        let validation = validation.unwrap_or_else(|| self.options.session_validation.clone());
+        self.mutate_journaled(
+            "apply_batch",
+            |archive| batch::apply_batch(archive, &ops, &validation),
+            |results| batch_events(&ops, results),
+        )
+    }
+
+    /// See [`crate::update_session`].
+    pub fn update_session(
+        &self,
+        date_iso: String,
+        session_id: String,
+        metadata: SessionMetadata,
+    ) -> Result<Session, AppError> {
+        let date = parse_date_iso(&date_iso)?;
+        self.mutate_journaled(
+            "update_session",
+            |archive| session_graph::update_session(archive, date, &session_id, &metadata),
+            |session| {
+                vec![ArchiveEvent::SessionUpdated {
+                    date_iso: date_iso.clone(),
+                    session_id: session.id.clone(),
+                }]
+            },
+        )
+    }
+
+    /// See [`crate::get_session_note`].
+    pub fn get_session_note(
+        &self,
+        date_iso: String,
+        session_id: String,
+    ) -> Result<String, AppError> {
+        let date = parse_date_iso(&date_iso)?;
+        notes::get_session_note(&self.path, date, &session_id)
+    }
+
+    /// See [`crate::query_sessions`].
+    pub fn query_sessions(
+        &self,
+        start_date_iso: Option<String>,
+        end_date_iso: Option<String>,
+        tags: Option<Vec<String>>,
+        min_rating: Option<u8>,
+    ) -> Result<Vec<Session>, AppError> {
+        session_graph::query_sessions(
+            &self.path,
+            start_date_iso.as_deref(),
+            end_date_iso.as_deref(),
+            tags.as_deref().unwrap_or_default(),
+            min_rating,
+        )
+    }
+
+    /// See [`crate::list_day_sessions`].
+    pub fn list_day_sessions(&self, date_iso: String) -> Result<Vec<Session>, AppError> {
+        let date = parse_date_iso(&date_iso)?;
+        session_graph::list_day_sessions(&self.path, date)
+    }
+
+    /// See [`crate::list_sessions_between_dates`].
+    pub fn list_sessions_between_dates(
+        &self,
+        start_date_iso: Option<String>,
+        end_date_iso: Option<String>,
+    ) -> Result<Vec<Session>, AppError> {
+        session_graph::list_sessions_between_dates(
+            &self.path,
+            start_date_iso.as_deref(),
+            end_date_iso.as_deref(),
+        )
+    }
+
+    /// See [`crate::list_day_totals`].
+    pub fn list_day_totals(
+        &self,
+        start_date_iso: Option<String>,
+        end_date_iso: Option<String>,
+    ) -> Result<Vec<DayTotal>, AppError> {
+        let end = match end_date_iso {
+            Some(iso) => parse_date_iso(&iso)?,
+            None => Local::now().date_naive(),
+        };
+        let start = match start_date_iso {
+            Some(iso) => parse_date_iso(&iso)?,
+            None => end - Duration::days(7),
+        };
+        session_graph::list_day_totals(&self.path, start, end)
+    }
+
+    /// See [`crate::list_goal_sessions`].
+    pub fn list_goal_sessions(
+        &self,
+        goal_id: u64,
+        start_date_iso: Option<String>,
+        end_date_iso: Option<String>,
+        kind: Option<SessionKind>,
+        limit: Option<u32>,
+        cursor: Option<String>,
+    ) -> Result<SessionPage, AppError> {
+        let start = start_date_iso.as_deref().map(parse_date_iso).transpose()?;
+        let end = end_date_iso.as_deref().map(parse_date_iso).transpose()?;
+        session_graph::list_goal_sessions(
+            &self.path,
+            goal_id,
+            start,
+            end,
+            kind,
+            limit.unwrap_or(50) as usize,
+            cursor.as_deref(),
+        )
+    }
+
+    /// See [`crate::migrate_archive`].
+    pub fn migrate(&self) -> Result<u32, AppError> {
+        let _guard = lock(&self.write_lock);
+        let version = events::record_local(&self.path, || manifest::migrate(&self.path))?;
+        *lock(&self.goals_cache) = None;
+        Ok(version)
+    }
+
+    /// See [`crate::archive_schema_version`].
+    pub fn schema_version(&self) -> Result<u32, AppError> {
+        manifest::schema_version(&self.path)
+    }
+
+    /// See [`crate::check_archive`].
+    pub fn check_archive(&self, repair: bool) -> Result<CheckReport, AppError> {
+        if !repair {
+            return check::check_archive(&self.path, false);
+        }
+        self.mutate(
+            |path| check::check_archive(path, true),
+            check::repair_events,
+        )
+    }
+
+    /// See [`crate::undo`].
+    pub fn undo(&self) -> Result<Option<JournalEntry>, AppError> {
+        let step = self.mutate(journal::undo, step_events)?;
+        Ok(step.map(|(entry, _)| entry))
+    }
+
+    /// See [`crate::redo`].
+    pub fn redo(&self) -> Result<Option<JournalEntry>, AppError> {
+        let step = self.mutate(journal::redo, step_events)?;
+        Ok(step.map(|(entry, _)| entry))
+    }
+
+    /// See [`crate::history`].
+    pub fn history(&self, limit: Option<u32>) -> Result<Vec<JournalEntry>, AppError> {
+        journal::history(&self.path, limit)
+    }
+
+    /// See [`crate::create_snapshot`]. Applies the handle's
+    /// `snapshot_retention` afterwards, if any.
+    pub fn create_snapshot(&self, label: Option<String>) -> Result<SnapshotInfo, AppError> {
+        self.mutate(
+            |path| {
+                let info = snapshot::create(path, label)?;
+                if let Some(policy) = &self.options.snapshot_retention {
+                    snapshot::prune(path, policy)?;
+                }
+                Ok(info)
+            },
+            |_| vec![],
+        )
+    }
+
+    /// See [`crate::list_snapshots`].
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>, AppError> {
+        snapshot::list(&self.path)
+    }
+
+    /// See [`crate::restore_snapshot`].
+    pub fn restore_snapshot(&self, snapshot_id: String) -> Result<SnapshotInfo, AppError> {
+        self.mutate(
+            |path| snapshot::restore(path, &snapshot_id),
+            |(_, events)| events.clone(),
+        )
+        .map(|(safety, _)| safety)
+    }
+
+    /// See [`crate::prune_snapshots`].
+    pub fn prune_snapshots(&self, policy: RetentionPolicy) -> Result<Vec<String>, AppError> {
+        self.mutate(|path| snapshot::prune(path, &policy), |_| vec![])
+    }
+
+    /// See [`crate::list_templates`].
+    pub fn list_templates(&self) -> Result<Vec<GoalTemplate>, AppError> {
+        templates::list_templates(&self.path)
+    }
+
+    /// See [`crate::save_template`].
+    pub fn save_template(&self, template: GoalTemplate) -> Result<(), AppError> {
+        self.mutate_journaled(
+            "save_template",
+            |archive| templates::save_template(archive, &template),
+            |_| vec![],
+        )
+    }
+
+    /// See [`crate::delete_template`].
+    pub fn delete_template(&self, id: String) -> Result<(), AppError> {
+        self.mutate_journaled(
+            "delete_template",
+            |archive| templates::delete_template(archive, &id),
+            |_| vec![],
+        )
+    }
+
+    /// See [`crate::apply_template`].
+    pub fn apply_template(&self, template: GoalTemplate) -> Result<Vec<u64>, AppError> {
+        self.mutate_journaled(
+            "apply_template",
+            |archive| templates::apply_template(archive, &template),
+            |ids| {
+                let added = ids
+                    .iter()
+                    .map(|&goal_id| ArchiveEvent::GoalAdded { goal_id });
+                let notes = ids
+                    .iter()
+                    .zip(&template.goals)
+                    .filter(|(_, goal)| goal.note.is_some())
+                    .map(|(&goal_id, _)| ArchiveEvent::NoteEdited { goal_id });
+                added.chain(notes).collect()
+            },
+        )
+    }
+
+    /// See [`crate::export_archive`].
+    pub fn export_archive(&self) -> Result<String, AppError> {
+        transfer::export_archive(&self.path)
+    }
+
+    /// See [`crate::import_archive`].
+    pub fn import_archive(
+        &self,
+        bundle: String,
+        mode: ImportMode,
+    ) -> Result<ImportSummary, AppError> {
+        self.mutate(
+            |path| {
+                let validation = &self.options.session_validation;
+                transfer::import_archive(path, &bundle, mode, validation)
+            },
+            |(_, events)| events.clone(),
+        )
+        .map(|(summary, _)| summary)
+    }
+
+    /// See [`crate::export_sessions`].
+    pub fn export_sessions(
+        &self,
+        start_date_iso: Option<String>,
+        end_date_iso: Option<String>,
+        format: ExportFormat,
+        sink: Arc<dyn ExportSink>,
+    ) -> Result<u64, AppError> {
+        let start = start_date_iso.as_deref().map(parse_date_iso).transpose()?;
+        let end = end_date_iso.as_deref().map(parse_date_iso).transpose()?;
+        export::export_sessions(&self.path, start, end, format, sink.as_ref())
+    }
+
+    /// See [`crate::export_goals`].
+    pub fn export_goals(
+        &self,
+        format: ExportFormat,
+        sink: Arc<dyn ExportSink>,
+    ) -> Result<u64, AppError> {
+        export::export_goals(&self.path, format, sink.as_ref())
+    }
+
+    /// See [`crate::export_ics`].
+    pub fn export_ics(
+        &self,
+        start_date_iso: Option<String>,
+        end_date_iso: Option<String>,
+        sink: Arc<dyn ExportSink>,
+    ) -> Result<u64, AppError> {
+        let start = start_date_iso.as_deref().map(parse_date_iso).transpose()?;
+        let end = end_date_iso.as_deref().map(parse_date_iso).transpose()?;
+        ical::export_ics(&self.path, start, end, sink.as_ref())
+    }
+
+    /// See [`crate::import_ics`].
+    pub fn import_ics(
+        &self,
+        ics: String,
+        options: Option<IcsImportOptions>,
+    ) -> Result<IcsImportReport, AppError> {
+        let options = options.unwrap_or_default();
+        let validation = options
+            .validation
+            .clone()
+            .unwrap_or_else(|| self.options.session_validation.clone());
+        self.mutate_journaled(
+            "import_ics",
+            |path| ical::import_ics(path, &ics, &options, &validation),
+            |report| session_events(&report.imported),
+        )
+    }
+
+    /// See [`crate::import_tracker_csv`].
+    pub fn import_tracker_csv(
+        &self,
+        csv: String,
+        options: TrackerImportOptions,
+    ) -> Result<TrackerImportReport, AppError> {
+        let validation = options
+            .validation
+            .clone()
+            .unwrap_or_else(|| self.options.session_validation.clone());
+        if options.dry_run {
+            return importer::import_csv(&self.path, &csv, &options, &validation);
+        }
+        self.mutate_journaled(
+            "import_tracker_csv",
+            |path| importer::import_csv(path, &csv, &options, &validation),
+            |report| {
+                let mut events: Vec<ArchiveEvent> = report
+                    .goals_created
+                    .iter()
+                    .map(|goal| ArchiveEvent::GoalAdded { goal_id: goal.id })
+                    .collect();
+                events.extend(session_events(&report.sessions));
+                events
+            },
+        )
+    }
+
+    /// See [`crate::list_recorded_days`].
+    pub fn list_recorded_days(&self) -> Result<Vec<String>, AppError> {
+        let days = session_graph::list_recorded_days(&self.path)?;
+        Ok(days.into_iter().map(format_date_iso).collect())
+    }
+
+    /// See [`crate::first_recorded_day`].
+    pub fn first_recorded_day(&self) -> Result<Option<String>, AppError> {
+        let days = session_graph::list_recorded_days(&self.path)?;
+        Ok(days.first().copied().map(format_date_iso))
+    }
+
+    /// See [`crate::last_recorded_day`].
+    pub fn last_recorded_day(&self) -> Result<Option<String>, AppError> {
+        let days = session_graph::list_recorded_days(&self.path)?;
+        Ok(days.last().copied().map(format_date_iso))
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while holding the lock cannot leave the cache half-written in a
+    // way that matters: it is re-validated on every read.
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn parse_date_iso(date_iso: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(date_iso, "%Y-%m-%d").map_err(|e| AppError::InvalidInput {
+        detail: format!("date_iso must be YYYY-MM-DD: {e}"),
+    })
+}
+
+pub(crate) fn parse_start_ts(start_ts_secs: i64) -> Result<DateTime<Utc>, AppError> {
+    Utc.timestamp_opt(start_ts_secs, 0)
+        .single()
+        .ok_or_else(|| AppError::InvalidInput {
+            detail: format!("invalid start_ts_secs: {start_ts_secs}"),
+        })
+}
+
+fn step_events(step: &Option<(JournalEntry, Vec<ArchiveEvent>)>) -> Vec<ArchiveEvent> {
+    step.as_ref()
+        .map(|(_, events)| events.clone())
+        .unwrap_or_default()
+}
+
+/// Events for the operations of a batch, in order.
+fn batch_events(ops: &[BatchOp], results: &[BatchResult]) -> Vec<ArchiveEvent> {
+    let mut events = Vec::new();
+    for (op, result) in ops.iter().zip(results) {
+        match (op, result) {
+            (BatchOp::AddGoal { .. }, BatchResult::Goal { goal }) => {
+                events.push(ArchiveEvent::GoalAdded { goal_id: goal.id })
+            }
+            (BatchOp::SetGoalTrashed { trashed, .. }, BatchResult::Goal { goal }) => {
+                events.push(ArchiveEvent::GoalTrashed {
+                    goal_id: goal.id,
+                    trashed: *trashed,
+                })
+            }
+            (_, BatchResult::Goal { goal }) => {
+                events.push(ArchiveEvent::GoalUpdated { goal_id: goal.id })
+            }
+            (BatchOp::AddSession { .. }, BatchResult::Session { session }) => {
+                events.extend(session_events(std::slice::from_ref(session)))
+            }
+            (_, BatchResult::Session { session }) => events.push(ArchiveEvent::SessionUpdated {
+                date_iso: timestamp_to_date_iso(session.start_at),
+                session_id: session.id.clone(),
+            }),
+            (_, BatchResult::Note { goal_id }) => {
+                events.push(ArchiveEvent::NoteEdited { goal_id: *goal_id })
+            }
+        }
+    }
+    events
+}
+
+/// Events for sessions recorded in bulk: one `SessionAdded` each, plus a
+/// `GoalUpdated` per goal whose status `add_session` may have changed.
+fn session_events(sessions: &[Session]) -> Vec<ArchiveEvent> {
+    let mut events = Vec::new();
+    for session in sessions {
+        events.push(ArchiveEvent::SessionAdded {
+            date_iso: timestamp_to_date_iso(session.start_at),
+            session_id: session.id.clone(),
+        });
+        let updated = ArchiveEvent::GoalUpdated {
+            goal_id: session.goal_id,
+        };
+        if session.kind == SessionKind::Goal && !events.contains(&updated) {
+            events.push(updated);
+        }
+    }
+    events
+}
+
+fn format_date_iso(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
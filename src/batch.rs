@@ -0,0 +1,101 @@
+//! Batches of goal, session and note changes applied all at once.
+//!
+//! Operations run one after another through the usual functions, inside
+//! `storage_io::staged`: their writes are held in memory and stored together
+//! at the end. `goals.yaml` and each day graph are therefore written once
+//! however many operations touch them, and an operation that fails leaves
+//! the archive exactly as it was before the batch.
+use std::path::Path;
+
+use crate::archive::{parse_date_iso, parse_start_ts};
+use crate::ffi_types::AppError;
+use crate::goals;
+use crate::notes;
+use crate::session_graph;
+use crate::storage_io;
+use crate::types::{BatchOp, BatchResult, SessionValidation};
+
+/// Apply `ops` in order; `validation` is used by every `AddSession`.
+/// Returns one result per operation, or the error of the first operation
+/// that failed, in which case nothing was written.
+pub fn apply_batch(
+    archive: &Path,
+    ops: &[BatchOp],
+    validation: &SessionValidation,
+) -> Result<Vec<BatchResult>, AppError> {
+    storage_io::staged(|| {
+        ops.iter()
+            .map(|op| apply(archive, op, validation))
+            .collect()
+    })
+}
+
+fn apply(
+    archive: &Path,
+    op: &BatchOp,
+    validation: &SessionValidation,
+) -> Result<BatchResult, AppError> {
+    let result = match op {
+        BatchOp::AddGoal {
+            name,
+            is_reward,
+            commands,
+            quantity_name,
+        } => BatchResult::Goal {
+            goal: goals::add_goal(
+                archive,
+                name,
+                *is_reward,
+                commands.clone(),
+                quantity_name.clone(),
+            )?,
+        },
+        BatchOp::SetGoalStatus { goal_id, status } => BatchResult::Goal {
+            goal: goals::set_goal_status(archive, *goal_id, *status)?,
+        },
+        BatchOp::SetGoalTrashed { goal_id, trashed } => BatchResult::Goal {
+            goal: goals::set_goal_trashed(archive, *goal_id, *trashed)?,
+        },
+        BatchOp::SetPinned { goal_id, pinned } => BatchResult::Goal {
+            goal: goals::set_pinned(archive, *goal_id, *pinned)?,
+        },
+        BatchOp::AddSession {
+            goal_id,
+            goal_name,
+            start_ts_secs,
+            duration_secs,
+            is_reward,
+            quantity,
+            metadata,
+        } => BatchResult::Session {
+            session: session_graph::add_session(
+                archive,
+                *goal_id,
+                goal_name,
+                parse_start_ts(*start_ts_secs)?,
+                *duration_secs,
+                *is_reward,
+                *quantity,
+                validation,
+                &metadata.clone().unwrap_or_default(),
+            )?,
+        },
+        BatchOp::UpdateSession {
+            date_iso,
+            session_id,
+            metadata,
+        } => BatchResult::Session {
+            session: session_graph::update_session(
+                archive,
+                parse_date_iso(date_iso)?,
+                session_id,
+                metadata,
+            )?,
+        },
+        BatchOp::EditNote { goal_id, content } => {
+            notes::edit_note(archive, *goal_id, content)?;
+            BatchResult::Note { goal_id: *goal_id }
+        }
+    };
+    Ok(result)
+}
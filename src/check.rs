@@ -0,0 +1,350 @@
+//! Archive integrity checker.
+//!
+//! Hand edits and concurrent writers can leave an archive in a state the
+//! rest of the library quietly tolerates: sessions that fall out of the
+//! `-->` chain are skipped, a repeated session id hides one of the two
+//! sessions, and so on. `check_archive` looks for these problems and, in
+//! repair mode, applies the fixes that cannot lose data:
+//!
+//! - duplicate session ids: the later session gets a fresh id;
+//! - broken chains: the day graph is rewritten in start order;
+//! - unknown goal ids: re-linked when exactly one goal has the same name
+//!   and kind;
+//! - graph names such as `2024-3-1.mmd`: renamed to `2024-03-01.mmd`.
+//!
+//! Everything else (unparsable files, duplicate goal ids, orphan notes,
+//! sessions without a time range) is only reported. Days containing nodes that cannot be parsed are
+//! never rewritten, since rewriting would drop those nodes.
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::events::ArchiveEvent;
+use crate::ffi_types::AppError;
+use crate::goals;
+use crate::session_graph::{
+    day_mermaid_path, graphs_dir, list_day_sessions, next_session_id, parse_day_file_name,
+    parse_day_nodes, save_day_sessions,
+};
+use crate::storage_io;
+use crate::types::{ArchiveIssue, CheckReport, Goal, IssueKind, Session, SessionKind};
+
+/// Formats accepted for the date in a misnamed graph file.
+const LOOSE_DAY_FORMATS: [&str; 4] = ["%Y-%m-%d", "%Y_%m_%d", "%Y%m%d", "%Y.%m.%d"];
+
+struct Checker<'a> {
+    archive: &'a Path,
+    repair: bool,
+    report: CheckReport,
+}
+
+impl Checker<'_> {
+    fn issue(
+        &mut self,
+        kind: IssueKind,
+        path: &str,
+        goal_id: Option<u64>,
+        detail: String,
+        repaired: bool,
+    ) {
+        self.report.issues.push(ArchiveIssue {
+            kind,
+            path: path.to_string(),
+            goal_id,
+            detail,
+            repaired,
+        });
+    }
+}
+
+pub fn check_archive(archive: &Path, repair: bool) -> Result<CheckReport, AppError> {
+    let mut checker = Checker {
+        archive,
+        repair,
+        report: CheckReport::default(),
+    };
+    let goals = check_goals(&mut checker)?;
+    let days = check_graph_names(&mut checker)?;
+    let mut sessions_by_day = HashMap::new();
+    for day in days {
+        let sessions = check_day(&mut checker, day, goals.as_deref())?;
+        sessions_by_day.insert(day, sessions);
+    }
+    check_notes(&mut checker, goals.as_deref(), &sessions_by_day)?;
+    Ok(checker.report)
+}
+
+/// Check `goals.yaml` and return the goals, or `None` if it is unreadable.
+fn check_goals(checker: &mut Checker) -> Result<Option<Vec<Goal>>, AppError> {
+    let goals = match goals::read_goals(checker.archive) {
+        Ok(goals) => goals,
+        Err(AppError::Parse { detail }) => {
+            checker.issue(IssueKind::UnreadableFile, "goals.yaml", None, detail, false);
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    checker.report.goals_checked = goals.len() as u32;
+
+    // Sessions and notes refer to goals by id only, so there is no telling
+    // which of the two goals they belong to; renumbering either would move
+    // data between them.
+    let mut seen: HashMap<u64, String> = HashMap::new();
+    for goal in &goals {
+        let Some(first) = seen.get(&goal.id) else {
+            seen.insert(goal.id, goal.name.clone());
+            continue;
+        };
+        let detail = format!(
+            "goals \"{first}\" and \"{}\" share id {}; their sessions and notes cannot be told apart",
+            goal.name, goal.id
+        );
+        checker.issue(
+            IssueKind::DuplicateGoalId,
+            "goals.yaml",
+            Some(goal.id),
+            detail,
+            false,
+        );
+    }
+    Ok(Some(goals))
+}
+
+/// Check the file names in `graphs/` and return the days with a graph.
+fn check_graph_names(checker: &mut Checker) -> Result<Vec<NaiveDate>, AppError> {
+    let archive = checker.archive;
+    let mut days = Vec::new();
+    for name in storage_io::list_dir(archive, &graphs_dir(archive))? {
+        let path = format!("graphs/{name}");
+        let loose = name.strip_suffix(".mmd").and_then(|stem| {
+            LOOSE_DAY_FORMATS
+                .iter()
+                .find_map(|f| NaiveDate::parse_from_str(stem, f).ok())
+        });
+        let Some(day) = loose else {
+            checker.issue(
+                IssueKind::MalformedGraphName,
+                &path,
+                None,
+                "not a YYYY-MM-DD.mmd day graph; ignored".into(),
+                false,
+            );
+            continue;
+        };
+        let canonical = day_mermaid_path(archive, day);
+        if archive.join(&path) == canonical {
+            days.push(day);
+            continue;
+        }
+
+        let target = format!("graphs/{}.mmd", day.format("%Y-%m-%d"));
+        if !checker.repair {
+            checker.issue(
+                IssueKind::MalformedGraphName,
+                &path,
+                None,
+                format!("should be named {target}"),
+                false,
+            );
+        } else if storage_io::exists(archive, &canonical)? {
+            checker.issue(
+                IssueKind::MalformedGraphName,
+                &path,
+                None,
+                format!("should be named {target}, which already exists"),
+                false,
+            );
+        } else {
+            let content = storage_io::read_to_string(archive, &archive.join(&path))?;
+            storage_io::write_string(archive, &canonical, &content.unwrap_or_default())?;
+            storage_io::delete(archive, &archive.join(&path))?;
+            checker.issue(
+                IssueKind::MalformedGraphName,
+                &target,
+                None,
+                format!("renamed from {path}"),
+                true,
+            );
+            days.push(day);
+        }
+    }
+    days.sort();
+    days.dedup();
+    Ok(days)
+}
+
+/// Check one day graph and return its sessions after any repairs.
+fn check_day(
+    checker: &mut Checker,
+    day: NaiveDate,
+    goals: Option<&[Goal]>,
+) -> Result<Vec<Session>, AppError> {
+    let archive = checker.archive;
+    checker.report.days_checked += 1;
+    let graph_path = day_mermaid_path(archive, day);
+    let path = format!("graphs/{}.mmd", day.format("%Y-%m-%d"));
+    let content = storage_io::read_to_string(archive, &graph_path)?.unwrap_or_default();
+    let nodes = parse_day_nodes(&content, day);
+
+    let mut malformed = false;
+    for (id, session) in &nodes {
+        if session.is_none() {
+            malformed = true;
+            checker.issue(
+                IssueKind::MalformedSession,
+                &path,
+                None,
+                format!("{id} has no valid time range"),
+                false,
+            );
+        }
+    }
+    let repair = checker.repair && !malformed;
+    let mut sessions: Vec<Session> = nodes.into_iter().filter_map(|(_, s)| s).collect();
+    let mut changed = false;
+
+    let original_ids: HashSet<String> = sessions.iter().map(|s| s.id.clone()).collect();
+
+    // Repeated ids: later occurrences get a fresh id.
+    let mut seen = HashSet::new();
+    for i in 0..sessions.len() {
+        if seen.insert(sessions[i].id.clone()) {
+            continue;
+        }
+        let mut detail = format!("{} is used by more than one session", sessions[i].id);
+        if repair {
+            let fresh = next_session_id(&sessions, sessions[i].kind);
+            detail.push_str(&format!(
+                "; the one starting at {} is now {fresh}",
+                sessions[i].start_at
+            ));
+            sessions[i].id = fresh.clone();
+            seen.insert(fresh);
+            changed = true;
+        }
+        checker.issue(IssueKind::DuplicateSessionId, &path, None, detail, repair);
+    }
+
+    // Sessions the `-->` chain does not reach.
+    let chained: HashSet<String> = list_day_sessions(archive, day)?
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    let mut unreachable: Vec<&str> = original_ids
+        .iter()
+        .filter(|id| !chained.contains(*id))
+        .map(String::as_str)
+        .collect();
+    if !unreachable.is_empty() {
+        unreachable.sort();
+        let detail = format!("not reachable from the chain: {}", unreachable.join(", "));
+        checker.issue(IssueKind::BrokenChain, &path, None, detail, repair);
+        changed |= repair;
+    }
+
+    // Goal ids missing from goals.yaml, re-linked by name when unambiguous.
+    if let Some(goals) = goals {
+        for session in &mut sessions {
+            if goals.iter().any(|g| g.id == session.goal_id) {
+                continue;
+            }
+            let unknown = session.goal_id;
+            let is_reward = session.kind == SessionKind::Reward;
+            let matches: Vec<&Goal> = goals
+                .iter()
+                .filter(|g| g.name == session.name && g.is_reward == is_reward)
+                .collect();
+            let mut detail = format!("{} refers to unknown goal {unknown}", session.id);
+            let relink = repair && matches.len() == 1;
+            if relink {
+                let goal = matches[0];
+                detail.push_str(&format!("; linked to \"{}\" ({})", goal.name, goal.id));
+                session.goal_id = goal.id;
+                changed = true;
+            }
+            checker.issue(IssueKind::UnknownGoal, &path, Some(unknown), detail, relink);
+        }
+    }
+
+    if changed {
+        save_day_sessions(archive, &sessions, day)?;
+    }
+    Ok(sessions)
+}
+
+/// Report goal notes and session notes whose owner does not exist.
+fn check_notes(
+    checker: &mut Checker,
+    goals: Option<&[Goal]>,
+    sessions_by_day: &HashMap<NaiveDate, Vec<Session>>,
+) -> Result<(), AppError> {
+    let archive = checker.archive;
+    let notes_dir = archive.join("notes");
+    for name in storage_io::list_files(archive, &notes_dir)? {
+        checker.report.notes_checked += 1;
+        let path = format!("notes/{name}");
+        if let Some(rest) = name.strip_prefix("sessions/") {
+            let Some((day, id)) = rest
+                .strip_suffix(".md")
+                .and_then(|r| r.split_once('/'))
+                .and_then(|(d, id)| Some((NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()?, id)))
+            else {
+                continue;
+            };
+            let exists = sessions_by_day
+                .get(&day)
+                .is_some_and(|sessions| sessions.iter().any(|s| s.id == id));
+            if !exists {
+                checker.issue(
+                    IssueKind::OrphanNote,
+                    &path,
+                    None,
+                    format!("no session {id} on {}", day.format("%Y-%m-%d")),
+                    false,
+                );
+            }
+            continue;
+        }
+        let Some(goals) = goals else {
+            continue;
+        };
+        let Some(goal_id) = name
+            .strip_prefix("goal_")
+            .and_then(|r| r.strip_suffix(".md"))
+            .and_then(|id| id.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        if !goals.iter().any(|g| g.id == goal_id) {
+            checker.issue(
+                IssueKind::OrphanNote,
+                &path,
+                Some(goal_id),
+                format!("no goal with id {goal_id}"),
+                false,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Events describing what a repair changed.
+pub fn repair_events(report: &CheckReport) -> Vec<ArchiveEvent> {
+    let mut events = Vec::new();
+    for issue in report.issues.iter().filter(|i| i.repaired) {
+        let event = issue
+            .path
+            .strip_prefix("graphs/")
+            .and_then(parse_day_file_name)
+            .map(|day| ArchiveEvent::SessionsChanged {
+                date_iso: day.format("%Y-%m-%d").to_string(),
+            });
+        if let Some(event) = event {
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+    }
+    events
+}
@@ -0,0 +1,500 @@
+//! Change notifications for archives.
+//!
+//! Listeners subscribe to an archive path through `Archive::subscribe`.
+//! Mutations made through this library in the same process are reported
+//! right away. Changes made by other processes are picked up by a polling
+//! watcher on native targets and by the `storage` event (or the IndexedDB
+//! backend's tab channel) on wasm.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use serde::Serialize;
+
+use crate::types::Goal;
+
+/// A change to an archive.
+///
+/// Events caused by other processes only know which day graph changed, so
+/// they are reported as `SessionsChanged` rather than per session.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum ArchiveEvent {
+    GoalAdded {
+        goal_id: u64,
+    },
+    GoalUpdated {
+        goal_id: u64,
+    },
+    GoalTrashed {
+        goal_id: u64,
+        trashed: bool,
+    },
+    SessionAdded {
+        date_iso: String,
+        session_id: String,
+    },
+    SessionUpdated {
+        date_iso: String,
+        session_id: String,
+    },
+    SessionsChanged {
+        date_iso: String,
+    },
+    NoteEdited {
+        goal_id: u64,
+    },
+}
+
+/// Receives `ArchiveEvent`s; implemented by the host application.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export(with_foreign))]
+pub trait ArchiveListener: Send + Sync {
+    fn on_event(&self, event: ArchiveEvent);
+}
+
+/// Subscribers of one archive and, on native targets, its watcher.
+struct Watch {
+    subscribers: Mutex<Vec<(u64, Arc<dyn ArchiveListener>)>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    watcher: watcher::Watcher,
+}
+
+impl Watch {
+    fn listeners(&self) -> Vec<Arc<dyn ArchiveListener>> {
+        lock(&self.subscribers)
+            .iter()
+            .map(|(_, l)| l.clone())
+            .collect()
+    }
+}
+
+/// Watches by archive. The registry is only locked to look a watch up;
+/// mutations and snapshots lock the state of their own archive.
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    watches: HashMap<PathBuf, Arc<Watch>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    lock(REGISTRY.get_or_init(Default::default))
+}
+
+fn watch_for(archive: &Path) -> Option<Arc<Watch>> {
+    registry().watches.get(archive).cloned()
+}
+
+fn listeners_for(archive: &Path) -> Vec<Arc<dyn ArchiveListener>> {
+    watch_for(archive)
+        .map(|watch| watch.listeners())
+        .unwrap_or_default()
+}
+
+fn deliver(listeners: &[Arc<dyn ArchiveListener>], events: &[ArchiveEvent]) {
+    for event in events {
+        for listener in listeners {
+            listener.on_event(event.clone());
+        }
+    }
+}
+
+/// Register `listener` for changes to `archive` and return its
+/// subscription id.
+pub fn subscribe(archive: &Path, listener: Arc<dyn ArchiveListener>) -> u64 {
+    let (id, watch) = {
+        let mut registry = registry();
+        registry.next_id += 1;
+        let id = registry.next_id;
+        let watch = registry
+            .watches
+            .entry(archive.to_path_buf())
+            .or_insert_with(|| {
+                Arc::new(Watch {
+                    subscribers: Mutex::new(Vec::new()),
+                    #[cfg(not(target_arch = "wasm32"))]
+                    watcher: Default::default(),
+                })
+            })
+            .clone();
+        lock(&watch.subscribers).push((id, listener));
+        (id, watch)
+    };
+    start_watching(archive, &watch);
+    id
+}
+
+/// Remove the subscription `id`. The watcher for an archive stops once its
+/// last subscriber is gone.
+pub fn unsubscribe(archive: &Path, id: u64) {
+    let watch = {
+        let mut registry = registry();
+        let Some(watch) = registry.watches.get(archive).cloned() else {
+            return;
+        };
+        let mut subscribers = lock(&watch.subscribers);
+        subscribers.retain(|(sub_id, _)| *sub_id != id);
+        if !subscribers.is_empty() {
+            return;
+        }
+        drop(subscribers);
+        registry.watches.remove(archive);
+        watch
+    };
+    stop_watching(&watch);
+}
+
+/// Run a mutation of `archive` made by this process.
+///
+/// When the archive is watched, mutations of it run one at a time and the
+/// watcher snapshot entries of the files they wrote are refreshed
+/// afterwards, so the watcher does not report our own writes a second time
+/// as external changes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn record_local<T>(archive: &Path, f: impl FnOnce() -> T) -> T {
+    match watch_for(archive) {
+        Some(watch) => watch.watcher.record_local(archive, f),
+        None => f(),
+    }
+}
+
+/// Run a mutation of `archive` made by this process. Other tabs are the
+/// only source of external changes on wasm, so there is nothing to track.
+#[cfg(target_arch = "wasm32")]
+pub fn record_local<T>(_archive: &Path, f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+/// Notify the subscribers of `archive` about `events`.
+pub fn emit(archive: &Path, events: &[ArchiveEvent]) {
+    if events.is_empty() {
+        return;
+    }
+    deliver(&listeners_for(archive), events);
+}
+
+/// Events describing how the goal list changed from `old` to `new`.
+fn diff_goals(old: &[Goal], new: &[Goal]) -> Vec<ArchiveEvent> {
+    let mut events = Vec::new();
+    for goal in new {
+        match old.iter().find(|g| g.id == goal.id) {
+            None => events.push(ArchiveEvent::GoalAdded { goal_id: goal.id }),
+            Some(prev) if prev.trashed != goal.trashed => events.push(ArchiveEvent::GoalTrashed {
+                goal_id: goal.id,
+                trashed: goal.trashed,
+            }),
+            Some(prev) if prev != goal => {
+                events.push(ArchiveEvent::GoalUpdated { goal_id: goal.id })
+            }
+            Some(_) => {}
+        }
+    }
+    events
+}
+
+/// Events for a change of the archive-relative file `relative` from `old`
+/// to `new` content (`None` when the file did not or no longer exists).
+pub fn file_events(relative: &str, old: Option<&str>, new: Option<&str>) -> Vec<ArchiveEvent> {
+    if relative == "goals.yaml" {
+        let parse = |v: Option<&str>| -> Vec<Goal> {
+            v.and_then(|s| serde_yaml::from_str(s).ok())
+                .unwrap_or_default()
+        };
+        return diff_goals(&parse(old), &parse(new));
+    }
+    event_for_file(relative).into_iter().collect()
+}
+
+/// Event for a change to the archive-relative file `relative`, other than
+/// `goals.yaml`.
+fn event_for_file(relative: &str) -> Option<ArchiveEvent> {
+    if let Some(name) = relative.strip_prefix("graphs/") {
+        let date_iso = name.strip_suffix(".mmd")?;
+        return Some(ArchiveEvent::SessionsChanged {
+            date_iso: date_iso.to_string(),
+        });
+    }
+    if let Some(name) = relative.strip_prefix("notes/sessions/") {
+        let (date_iso, file) = name.split_once('/')?;
+        let session_id = file.strip_suffix(".md")?;
+        return Some(ArchiveEvent::SessionUpdated {
+            date_iso: date_iso.to_string(),
+            session_id: session_id.to_string(),
+        });
+    }
+    let goal_id = relative
+        .strip_prefix("notes/goal_")?
+        .strip_suffix(".md")?
+        .parse()
+        .ok()?;
+    Some(ArchiveEvent::NoteEdited { goal_id })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn start_watching(archive: &Path, watch: &Arc<Watch>) {
+    watch.watcher.start(archive, watch);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn stop_watching(watch: &Watch) {
+    watch.watcher.stop();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod watcher {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    use super::{deliver, diff_goals, event_for_file, lock, Goal, Watch};
+    use crate::{goals, storage_io};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Fingerprints of the watched files, keyed by archive-relative path.
+    #[derive(Default)]
+    pub struct Snapshot {
+        files: HashMap<String, String>,
+        goals: Vec<Goal>,
+    }
+
+    impl Snapshot {
+        pub fn take(archive: &Path) -> Self {
+            let mut files = HashMap::new();
+            let graphs = storage_io::list_dir(archive, &archive.join("graphs")).unwrap_or_default();
+            // Session notes live in per-day folders below `notes/sessions/`.
+            let notes = storage_io::list_files(archive, &archive.join("notes")).unwrap_or_default();
+            let relative = graphs
+                .into_iter()
+                .map(|name| format!("graphs/{name}"))
+                .chain(notes.into_iter().map(|name| format!("notes/{name}")));
+            for relative in relative {
+                if let Ok(Some(fp)) = storage_io::fingerprint(archive, &archive.join(&relative)) {
+                    files.insert(relative, fp);
+                }
+            }
+            if let Ok(Some(fp)) = storage_io::fingerprint(archive, &goals::goals_path(archive)) {
+                files.insert("goals.yaml".into(), fp);
+            }
+            Snapshot {
+                files,
+                goals: goals::read_goals(archive).unwrap_or_default(),
+            }
+        }
+
+        /// Update the entries of the files at `paths`, leaving the others.
+        pub fn refresh(&mut self, archive: &Path, paths: &[PathBuf]) {
+            for path in paths {
+                let Some(relative) = path.strip_prefix(archive).ok().and_then(|p| p.to_str())
+                else {
+                    continue;
+                };
+                let relative = relative.replace('\\', "/");
+                if !is_watched(&relative) {
+                    continue;
+                }
+                match storage_io::fingerprint(archive, path) {
+                    Ok(Some(fp)) => {
+                        self.files.insert(relative.clone(), fp);
+                    }
+                    _ => {
+                        self.files.remove(&relative);
+                    }
+                }
+                if relative == "goals.yaml" {
+                    self.goals = goals::read_goals(archive).unwrap_or_default();
+                }
+            }
+        }
+
+        fn diff(&self, newer: &Snapshot) -> Vec<super::ArchiveEvent> {
+            let mut events = Vec::new();
+            let mut changed: Vec<&String> = newer
+                .files
+                .iter()
+                .filter(|(path, fp)| self.files.get(*path) != Some(fp))
+                .map(|(path, _)| path)
+                .collect();
+            changed.extend(self.files.keys().filter(|p| !newer.files.contains_key(*p)));
+            changed.sort();
+            for path in changed {
+                if path == "goals.yaml" {
+                    events.extend(diff_goals(&self.goals, &newer.goals));
+                } else if let Some(event) = event_for_file(path) {
+                    events.push(event);
+                }
+            }
+            events
+        }
+    }
+
+    /// Whether `take` fingerprints the archive-relative file `relative`.
+    fn is_watched(relative: &str) -> bool {
+        relative == "goals.yaml"
+            || relative
+                .strip_prefix("graphs/")
+                .is_some_and(|name| !name.contains('/'))
+            || relative.starts_with("notes/")
+    }
+
+    #[derive(Default)]
+    struct State {
+        snapshot: Snapshot,
+        /// Bumped around every local mutation, so a snapshot the polling
+        /// thread took meanwhile is thrown away instead of reported.
+        generation: u64,
+        writing: bool,
+        thread: Option<JoinHandle<()>>,
+        stopped: bool,
+    }
+
+    /// The polling thread of one archive. Snapshots are taken without
+    /// holding `state`, which is only locked to compare and swap them.
+    #[derive(Default)]
+    pub struct Watcher {
+        state: Mutex<State>,
+        /// Runs local mutations of the archive one at a time.
+        local: Mutex<()>,
+    }
+
+    impl Watcher {
+        /// Take the first snapshot and spawn the polling thread, unless it
+        /// runs already or the watch was stopped meanwhile.
+        pub fn start(&self, archive: &Path, watch: &Arc<Watch>) {
+            let mut state = lock(&self.state);
+            if state.thread.is_some() || state.stopped {
+                return;
+            }
+            state.snapshot = Snapshot::take(archive);
+            let (archive, watch) = (archive.to_path_buf(), watch.clone());
+            state.thread = Some(std::thread::spawn(move || poll(archive, watch)));
+        }
+
+        /// Stop the polling thread and wait for it to finish.
+        pub fn stop(&self) {
+            let thread = {
+                let mut state = lock(&self.state);
+                state.stopped = true;
+                state.thread.take()
+            };
+            let Some(thread) = thread else {
+                return;
+            };
+            // A listener may unsubscribe from within the polling thread.
+            if thread.thread().id() != std::thread::current().id() {
+                thread.thread().unpark();
+                let _ = thread.join();
+            }
+        }
+
+        pub fn record_local<T>(&self, archive: &Path, f: impl FnOnce() -> T) -> T {
+            let _local = lock(&self.local);
+            {
+                let mut state = lock(&self.state);
+                state.writing = true;
+                state.generation += 1;
+            }
+            let (result, written) = storage_io::track_writes(archive, f);
+            // Only the files written need a new fingerprint.
+            let mut snapshot = std::mem::take(&mut lock(&self.state).snapshot);
+            snapshot.refresh(archive, &written);
+            let mut state = lock(&self.state);
+            state.snapshot = snapshot;
+            state.writing = false;
+            state.generation += 1;
+            result
+        }
+    }
+
+    fn poll(archive: PathBuf, watch: Arc<Watch>) {
+        let state = &watch.watcher.state;
+        loop {
+            std::thread::park_timeout(POLL_INTERVAL);
+            let generation = {
+                let state = lock(state);
+                if state.stopped {
+                    return;
+                }
+                if state.writing {
+                    continue;
+                }
+                state.generation
+            };
+            let newer = Snapshot::take(&archive);
+            let events = {
+                let mut state = lock(state);
+                if state.stopped {
+                    return;
+                }
+                if state.generation != generation {
+                    continue;
+                }
+                let events = state.snapshot.diff(&newer);
+                state.snapshot = newer;
+                events
+            };
+            deliver(&watch.listeners(), &events);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn stop_watching(_watch: &Watch) {}
+
+#[cfg(target_arch = "wasm32")]
+fn start_watching(archive: &Path, _watch: &Arc<Watch>) {
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+
+    use crate::storage_idb;
+
+    thread_local! {
+        // Handlers are never removed, so register at most one per archive.
+        static LISTENING: RefCell<HashSet<PathBuf>> = RefCell::new(HashSet::new());
+    }
+    if !LISTENING.with(|l| l.borrow_mut().insert(archive.to_path_buf())) {
+        return;
+    }
+    // Both the `storage` event and the IndexedDB backend only report writes
+    // made by other tabs, so there is nothing of our own to filter out here.
+    let on_change = {
+        let archive = archive.to_path_buf();
+        Rc::new(move |key: &str, old: Option<String>, new: Option<String>| {
+            report_external_change(&archive, key, old, new)
+        })
+    };
+    storage_idb::add_change_hook(on_change.clone());
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let handler =
+        Closure::<dyn FnMut(web_sys::StorageEvent)>::new(move |event: web_sys::StorageEvent| {
+            if let Some(key) = event.key() {
+                on_change(&key, event.old_value(), event.new_value());
+            }
+        });
+    let _ = window.add_event_listener_with_callback("storage", handler.as_ref().unchecked_ref());
+    handler.forget();
+}
+
+/// Notify the subscribers of `archive` that another tab changed the value
+/// stored under `key` from `old` to `new`.
+#[cfg(target_arch = "wasm32")]
+fn report_external_change(archive: &Path, key: &str, old: Option<String>, new: Option<String>) {
+    let Some(relative) = crate::storage_io::relative_path_for_key(archive, key) else {
+        return;
+    };
+    let events = file_events(&relative, old.as_deref(), new.as_deref());
+    deliver(&listeners_for(archive), &events);
+}
@@ -0,0 +1,235 @@
+//! Tabular exports of sessions and goals, for spreadsheets and scripts.
+//!
+//! Rows are handed to an `ExportSink` one day at a time, so exporting years
+//! of history never holds more than a day's rows in memory. CSV output
+//! starts with a header line and quotes fields as RFC 4180 describes, and
+//! fields a spreadsheet would run as a formula get a leading `'`. JSON Lines
+//! output has one object per line with the same keys.
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::Serialize;
+
+use crate::ffi_types::AppError;
+use crate::goals;
+use crate::session_graph::list_day_sessions;
+use crate::session_index;
+use crate::types::{ExportFormat, Goal, GoalStatus, Session, SessionKind};
+
+/// Receives exported text in order; implemented by the host application,
+/// e.g. by appending to a file.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export(with_foreign))]
+pub trait ExportSink: Send + Sync {
+    fn write(&self, chunk: String);
+}
+
+/// One exported record: its CSV columns and, through `Serialize`, its JSON
+/// object with the same keys.
+trait Row: Serialize {
+    const COLUMNS: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+#[derive(Serialize)]
+struct SessionRow<'a> {
+    date: String,
+    start: String,
+    end: String,
+    duration_secs: i64,
+    goal_id: u64,
+    goal_name: &'a str,
+    kind: SessionKind,
+    quantity: Option<u32>,
+    quantity_unit: Option<&'a str>,
+}
+
+impl Row for SessionRow<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "date",
+        "start",
+        "end",
+        "duration_secs",
+        "goal_id",
+        "goal_name",
+        "kind",
+        "quantity",
+        "quantity_unit",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.date.clone(),
+            self.start.clone(),
+            self.end.clone(),
+            self.duration_secs.to_string(),
+            self.goal_id.to_string(),
+            self.goal_name.to_string(),
+            format!("{:?}", self.kind),
+            self.quantity.map(|q| q.to_string()).unwrap_or_default(),
+            self.quantity_unit.unwrap_or_default().to_string(),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+struct GoalRow<'a> {
+    id: u64,
+    name: &'a str,
+    is_reward: bool,
+    status: GoalStatus,
+    trashed: bool,
+    quantity_unit: Option<&'a str>,
+    commands: &'a [String],
+}
+
+impl Row for GoalRow<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "is_reward",
+        "status",
+        "trashed",
+        "quantity_unit",
+        "commands",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.to_string(),
+            self.is_reward.to_string(),
+            format!("{:?}", self.status),
+            self.trashed.to_string(),
+            self.quantity_unit.unwrap_or_default().to_string(),
+            // One command per line within the cell.
+            self.commands.join("\n"),
+        ]
+    }
+}
+
+/// Spreadsheets read a cell starting with one of these as a formula.
+const FORMULA_PREFIXES: [char; 4] = ['=', '+', '-', '@'];
+
+fn csv_line<S: AsRef<str>>(fields: &[S]) -> Result<String, AppError> {
+    let io_error = |detail: String| AppError::Io { detail };
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(vec![]);
+    writer
+        .write_record(fields.iter().map(|field| {
+            let field = field.as_ref();
+            if field.starts_with(FORMULA_PREFIXES) {
+                format!("'{field}")
+            } else {
+                field.to_string()
+            }
+        }))
+        .map_err(|e| io_error(e.to_string()))?;
+    let bytes = writer.into_inner().map_err(|e| io_error(e.to_string()))?;
+    String::from_utf8(bytes).map_err(|e| io_error(e.to_string()))
+}
+
+fn push_row<R: Row>(out: &mut String, format: ExportFormat, row: &R) -> Result<(), AppError> {
+    match format {
+        ExportFormat::Csv => out.push_str(&csv_line(&row.fields())?),
+        ExportFormat::JsonLines => {
+            out.push_str(&serde_json::to_string(row)?);
+            out.push('\n');
+        }
+    }
+    Ok(())
+}
+
+fn write_header<R: Row>(sink: &dyn ExportSink, format: ExportFormat) -> Result<(), AppError> {
+    if format == ExportFormat::Csv {
+        sink.write(csv_line(R::COLUMNS)?);
+    }
+    Ok(())
+}
+
+/// Local date and time in a form spreadsheets recognise.
+fn local_time(ts: i64) -> String {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+fn session_row<'a>(day: NaiveDate, session: &'a Session, goal: Option<&'a Goal>) -> SessionRow<'a> {
+    SessionRow {
+        date: day.format("%Y-%m-%d").to_string(),
+        start: local_time(session.start_at),
+        end: local_time(session.end_at),
+        duration_secs: session.end_at - session.start_at,
+        goal_id: session.goal_id,
+        goal_name: goal.map_or(session.name.as_str(), |g| g.name.as_str()),
+        kind: session.kind,
+        quantity: session.quantity,
+        quantity_unit: goal.and_then(|g| g.quantity_name.as_deref()),
+    }
+}
+
+/// Write the sessions recorded between `start` and `end` (inclusive, both
+/// optional) to `sink`, oldest first, and return how many were written.
+pub fn export_sessions(
+    archive: &Path,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    format: ExportFormat,
+    sink: &dyn ExportSink,
+) -> Result<u64, AppError> {
+    let goals: HashMap<u64, Goal> = goals::read_goals(archive)?
+        .into_iter()
+        .map(|g| (g.id, g))
+        .collect();
+    let days = session_index::load_index(archive)?
+        .recorded_days()
+        .into_iter()
+        .filter(|day| start.is_none_or(|s| *day >= s) && end.is_none_or(|e| *day <= e));
+
+    write_header::<SessionRow>(sink, format)?;
+    let mut count = 0;
+    for day in days {
+        let mut chunk = String::new();
+        for session in list_day_sessions(archive, day)? {
+            let row = session_row(day, &session, goals.get(&session.goal_id));
+            push_row(&mut chunk, format, &row)?;
+            count += 1;
+        }
+        if !chunk.is_empty() {
+            sink.write(chunk);
+        }
+    }
+    Ok(count)
+}
+
+/// Write every goal, trashed ones included, to `sink` and return how many
+/// were written.
+pub fn export_goals(
+    archive: &Path,
+    format: ExportFormat,
+    sink: &dyn ExportSink,
+) -> Result<u64, AppError> {
+    let goals = goals::read_goals(archive)?;
+    write_header::<GoalRow>(sink, format)?;
+    let mut chunk = String::new();
+    for goal in &goals {
+        let row = GoalRow {
+            id: goal.id,
+            name: &goal.name,
+            is_reward: goal.is_reward,
+            status: goal.status,
+            trashed: goal.trashed,
+            quantity_unit: goal.quantity_name.as_deref(),
+            commands: &goal.commands,
+        };
+        push_row(&mut chunk, format, &row)?;
+    }
+    if !chunk.is_empty() {
+        sink.write(chunk);
+    }
+    Ok(goals.len() as u64)
+}
@@ -1,7 +1,10 @@
+use serde::Serialize;
+
 use crate::storage_io::StorageIoError;
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Error))]
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind")]
 pub enum AppError {
     #[error("I/O error: {detail}")]
     Io { detail: String },
@@ -20,6 +23,37 @@ pub enum AppError {
 
     #[error("Parse error: {detail}")]
     Parse { detail: String },
+
+    #[error("Session overlaps existing sessions: {}", session_ids.join(", "))]
+    SessionOverlap { session_ids: Vec<String> },
+
+    #[error("Session starts in the future: {start_at}")]
+    SessionInFuture { start_at: i64 },
+
+    #[error("Invalid session duration: {duration_secs}s (allowed {min_secs}s..={max_secs}s)")]
+    InvalidDuration {
+        duration_secs: u32,
+        min_secs: u32,
+        max_secs: u32,
+    },
+
+    #[error("Archive schema version {found} is newer than the supported version {supported}")]
+    UnsupportedSchemaVersion { found: u32, supported: u32 },
+}
+
+impl AppError {
+    /// Whether the error rejects a session on its own merits (validation,
+    /// overlap) rather than reporting a storage or parse failure. Bulk
+    /// imports skip such sessions and carry on.
+    pub fn rejects_session(&self) -> bool {
+        matches!(
+            self,
+            AppError::InvalidInput { .. }
+                | AppError::SessionOverlap { .. }
+                | AppError::SessionInFuture { .. }
+                | AppError::InvalidDuration { .. }
+        )
+    }
 }
 
 impl From<StorageIoError> for AppError {
@@ -32,6 +66,9 @@ impl From<StorageIoError> for AppError {
             StorageIoError::Io(io_err) => AppError::Io {
                 detail: io_err.to_string(),
             },
+            StorageIoError::PartialCommit { .. } => AppError::Io {
+                detail: e.to_string(),
+            },
         }
     }
 }
@@ -43,3 +80,11 @@ impl From<serde_yaml::Error> for AppError {
         }
     }
 }
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Parse {
+            detail: e.to_string(),
+        }
+    }
+}
@@ -1,37 +1,45 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::path::Path;
 
+use chrono::{Duration, Local};
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 
 use crate::ffi_types::AppError;
-use crate::session_graph::list_sessions_between_dates;
+use crate::launch;
+use crate::session_index;
 use crate::storage_io;
-use crate::types::{Goal, GoalStatus};
+use crate::types::{Goal, GoalSort, GoalStatus, LaunchCommand, ScoredGoal};
 
 const DEFAULT_VISIBLE_STATUSES: [GoalStatus; 2] = [GoalStatus::TODO, GoalStatus::DOING];
 
-fn goals_path(archive: &Path) -> std::path::PathBuf {
+pub fn goals_path(archive: &Path) -> std::path::PathBuf {
     archive.join("goals.yaml")
 }
 
-fn read_goals(archive: &Path) -> Result<Vec<Goal>, AppError> {
+pub fn read_goals(archive: &Path) -> Result<Vec<Goal>, AppError> {
     let path = goals_path(archive);
     let Some(data) = storage_io::read_to_string(archive, &path)? else {
         return Ok(vec![]);
     };
-    let goals: Vec<Goal> = serde_yaml::from_str(&data)?;
+    parse_goals(&data)
+}
+
+/// Parse the content of `goals.yaml`.
+pub fn parse_goals(data: &str) -> Result<Vec<Goal>, AppError> {
+    let goals: Vec<Goal> = serde_yaml::from_str(data)?;
     Ok(goals)
 }
 
-fn write_goals(archive: &Path, goals: &[Goal]) -> Result<(), AppError> {
+pub fn write_goals(archive: &Path, goals: &[Goal]) -> Result<(), AppError> {
     let path = goals_path(archive);
     let data = serde_yaml::to_string(goals)?;
     storage_io::write_string(archive, &path, &data)?;
     Ok(())
 }
 
-fn filter_goals(goals: Vec<Goal>, statuses: Option<&[GoalStatus]>) -> Vec<Goal> {
+pub fn filter_goals(goals: Vec<Goal>, statuses: Option<&[GoalStatus]>) -> Vec<Goal> {
     let statuses = statuses.unwrap_or(&DEFAULT_VISIBLE_STATUSES);
     goals
         .into_iter()
@@ -71,6 +79,9 @@ pub fn add_goal(
         status: GoalStatus::TODO,
         trashed: false,
         quantity_name,
+        pinned: false,
+        sort_order: next_sort_order(&goals),
+        launch_commands: vec![],
     };
     goals.push(goal.clone());
 
@@ -125,6 +136,132 @@ pub fn set_goal_trashed(archive: &Path, goal_id: u64, trashed: bool) -> Result<G
     Ok(goal)
 }
 
+pub fn set_pinned(archive: &Path, goal_id: u64, pinned: bool) -> Result<Goal, AppError> {
+    let mut goals = read_goals(archive)?;
+    let goal = goals
+        .iter_mut()
+        .find(|g| g.id == goal_id)
+        .ok_or_else(|| goal_not_found(goal_id))?;
+    goal.pinned = pinned;
+    let goal = goal.clone();
+    write_goals(archive, &goals)?;
+
+    Ok(goal)
+}
+
+/// Replace the typed launch commands of `goal_id`; its legacy `commands`
+/// are left alone.
+pub fn set_launch_commands(
+    archive: &Path,
+    goal_id: u64,
+    commands: Vec<LaunchCommand>,
+) -> Result<Goal, AppError> {
+    launch::validate_commands(&commands)?;
+    let mut goals = read_goals(archive)?;
+    let goal = goals
+        .iter_mut()
+        .find(|g| g.id == goal_id)
+        .ok_or_else(|| goal_not_found(goal_id))?;
+    goal.launch_commands = commands;
+    let goal = goal.clone();
+    write_goals(archive, &goals)?;
+
+    Ok(goal)
+}
+
+/// Move `goal_id` right before `before_id` in the manual order, or to its
+/// end when `before_id` is `None`.
+///
+/// Only the moved goal gets a new `sort_order`, so moves made by two apps
+/// at once both survive. Keys that are missing, malformed or duplicated are
+/// renumbered first, keeping the current order.
+pub fn move_goal(archive: &Path, goal_id: u64, before_id: Option<u64>) -> Result<Goal, AppError> {
+    if before_id == Some(goal_id) {
+        return Err(AppError::InvalidInput {
+            detail: format!("goal {goal_id} cannot be moved before itself"),
+        });
+    }
+    let mut goals = read_goals(archive)?;
+    if !goals.iter().any(|g| g.id == goal_id) {
+        return Err(goal_not_found(goal_id));
+    }
+
+    let mut order: Vec<usize> = (0..goals.len()).collect();
+    order.sort_by(|&a, &b| goals[a].sort_order.cmp(&goals[b].sort_order));
+    let keyed = order.iter().all(|&i| is_order_key(&goals[i].sort_order))
+        && order
+            .windows(2)
+            .all(|w| goals[w[0]].sort_order < goals[w[1]].sort_order);
+    if !keyed {
+        for (&i, key) in order.iter().zip(spread_order_keys(order.len())) {
+            goals[i].sort_order = key;
+        }
+    }
+
+    order.retain(|&i| goals[i].id != goal_id);
+    let key_at = |pos: usize| goals[order[pos]].sort_order.clone();
+    let (after, before) = match before_id {
+        Some(before_id) => {
+            let pos = order
+                .iter()
+                .position(|&i| goals[i].id == before_id)
+                .ok_or_else(|| goal_not_found(before_id))?;
+            let after = pos.checked_sub(1).map(key_at).unwrap_or_default();
+            (after, Some(key_at(pos)))
+        }
+        None => (
+            order.len().checked_sub(1).map(key_at).unwrap_or_default(),
+            None,
+        ),
+    };
+
+    let goal = goals
+        .iter_mut()
+        .find(|g| g.id == goal_id)
+        .expect("checked above");
+    goal.sort_order = order_key_between(&after, before.as_deref());
+    let goal = goal.clone();
+    write_goals(archive, &goals)?;
+
+    Ok(goal)
+}
+
+/// Order `goals`, read in `goals.yaml` order, by `sort`.
+pub fn sort_goals(archive: &Path, goals: &mut [Goal], sort: GoalSort) -> Result<(), AppError> {
+    match sort {
+        GoalSort::Archive => {}
+        GoalSort::Manual => goals.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then_with(|| a.sort_order.cmp(&b.sort_order))
+        }),
+        GoalSort::Recent => {
+            let last_active = session_index::load_index(archive)?.last_activity(None);
+            goals.sort_by_key(|g| Reverse(last_active.get(&g.id).copied()));
+        }
+        GoalSort::Name => goals.sort_by_cached_key(|g| g.name.to_lowercase()),
+    }
+    Ok(())
+}
+
+/// A `sort_order` key after the key of every goal in `goals`.
+pub fn next_sort_order(goals: &[Goal]) -> String {
+    let last = goals
+        .iter()
+        .map(|g| g.sort_order.as_str())
+        .filter(|key| is_order_key(key))
+        .max()
+        .unwrap_or("");
+    order_key_between(last, None)
+}
+
+fn goal_not_found(goal_id: u64) -> AppError {
+    AppError::NotFound {
+        resource: "goal".into(),
+        id: goal_id.to_string(),
+    }
+}
+
 pub fn get_goal(archive: &Path, goal_id: u64) -> Result<Goal, AppError> {
     let goals = read_goals(archive)?;
     goals.into_iter().find(|g| g.id == goal_id).ok_or_else(|| {
@@ -143,44 +280,73 @@ pub fn search_goals(
     sort_by_recent: bool,
 ) -> Result<Vec<Goal>, AppError> {
     let goals = list_goals(archive, statuses)?;
+    Ok(rank_goals(archive, goals, query, is_reward, sort_by_recent))
+}
+
+/// Filter `goals` by `query` and `is_reward`, then order them by recent
+/// activity (when `sort_by_recent`) and fuzzy score.
+pub fn rank_goals(
+    archive: &Path,
+    goals: Vec<Goal>,
+    query: &str,
+    is_reward: Option<bool>,
+    sort_by_recent: bool,
+) -> Vec<Goal> {
+    score_goals(archive, goals, query, is_reward, sort_by_recent)
+        .into_iter()
+        .map(|s| s.goal)
+        .collect()
+}
+
+/// Same as [`rank_goals`], keeping the fuzzy score, the matched character
+/// indices and whether recent activity moved each goal up.
+pub fn score_goals(
+    archive: &Path,
+    goals: Vec<Goal>,
+    query: &str,
+    is_reward: Option<bool>,
+    sort_by_recent: bool,
+) -> Vec<ScoredGoal> {
     let matcher = SkimMatcherV2::default();
     let trimmed = query.trim();
 
-    let recent_sessions = if sort_by_recent {
-        list_sessions_between_dates(archive, None, None).unwrap_or_default()
+    // Recency only considers the last week, matching the default window of
+    // `list_sessions_between_dates`.
+    let last_active = if sort_by_recent {
+        let today = Local::now().date_naive();
+        let window = Some((today - Duration::days(7), today));
+        session_index::load_index_within(archive, window)
+            .map(|index| index.last_activity(window))
+            .unwrap_or_default()
     } else {
-        vec![]
+        HashMap::new()
     };
 
-    let mut last_active = HashMap::new();
-    if sort_by_recent {
-        for session in recent_sessions {
-            let entry = last_active.entry(session.goal_id).or_insert(0);
-            if session.start_at > *entry {
-                *entry = session.start_at;
-            }
-        }
-    }
-
-    let mut scored: Vec<(i64, Goal)> = goals
+    let mut scored: Vec<ScoredGoal> = goals
         .into_iter()
         .filter(|g| match is_reward {
             Some(flag) => g.is_reward == flag,
             None => true,
         })
         .filter_map(|g| {
-            if trimmed.is_empty() {
-                Some((0, g))
+            let (score, indices) = if trimmed.is_empty() {
+                (0, vec![])
             } else {
-                matcher.fuzzy_match(&g.name, trimmed).map(|s| (s, g))
-            }
+                matcher.fuzzy_indices(&g.name, trimmed)?
+            };
+            Some(ScoredGoal {
+                recency_boosted: last_active.contains_key(&g.id),
+                goal: g,
+                score,
+                indices: indices.into_iter().map(|i| i as u32).collect(),
+            })
         })
         .collect();
 
-    scored.sort_by(|(score_a, goal_a), (score_b, goal_b)| {
+    scored.sort_by(|a, b| {
         if sort_by_recent {
-            let active_a = last_active.get(&goal_a.id);
-            let active_b = last_active.get(&goal_b.id);
+            let active_a = last_active.get(&a.goal.id);
+            let active_b = last_active.get(&b.goal.id);
             match (active_a, active_b) {
                 (Some(ts_a), Some(ts_b)) => {
                     let cmp = ts_b.cmp(ts_a);
@@ -193,7 +359,78 @@ pub fn search_goals(
                 _ => {}
             }
         }
-        score_b.cmp(score_a)
+        b.score.cmp(&a.score)
     });
-    Ok(scored.into_iter().map(|(_, g)| g).collect())
+    scored
+}
+
+/// Digits of `sort_order` keys, in ASCII order so that keys compare as
+/// strings. A key is a base-62 fraction; it never ends in `0`, so there is
+/// always room before it.
+const ORDER_DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+fn is_order_key(key: &str) -> bool {
+    !key.is_empty() && !key.ends_with('0') && key.bytes().all(|b| ORDER_DIGITS.contains(&b))
+}
+
+/// A key strictly between `after` (`""` for the start) and `before` (`None`
+/// for the end). Both must be valid keys with `after < before`.
+fn order_key_between(after: &str, before: Option<&str>) -> String {
+    let key = order_midpoint(after.as_bytes(), before.map(str::as_bytes));
+    String::from_utf8(key).expect("order digits are ASCII")
+}
+
+fn order_midpoint(after: &[u8], before: Option<&[u8]>) -> Vec<u8> {
+    if let Some(before) = before {
+        // Skip the common prefix, reading `after` as padded with zeros.
+        let common = before
+            .iter()
+            .enumerate()
+            .take_while(|(i, d)| after.get(*i).unwrap_or(&b'0') == *d)
+            .count();
+        if common > 0 {
+            let mut key = before[..common].to_vec();
+            let rest = after.get(common..).unwrap_or_default();
+            key.extend(order_midpoint(rest, Some(&before[common..])));
+            return key;
+        }
+    }
+    let digit = |d: u8| ORDER_DIGITS.iter().position(|&x| x == d).unwrap_or(0);
+    let low = after.first().map_or(0, |&d| digit(d));
+    let high = before.map_or(ORDER_DIGITS.len(), |b| digit(b[0]));
+    if high - low > 1 {
+        return vec![ORDER_DIGITS[(low + high).div_ceil(2)]];
+    }
+    match before {
+        Some(before) if before.len() > 1 => vec![before[0]],
+        _ => {
+            let mut key = vec![ORDER_DIGITS[low]];
+            key.extend(order_midpoint(after.get(1..).unwrap_or_default(), None));
+            key
+        }
+    }
+}
+
+/// `count` increasing keys of equal width, spread evenly over the key space.
+fn spread_order_keys(count: usize) -> Vec<String> {
+    let base = ORDER_DIGITS.len() as u128;
+    let (mut width, mut span) = (1, base);
+    while span <= count as u128 {
+        width += 1;
+        span *= base;
+    }
+    (1..=count as u128)
+        .map(|i| {
+            let mut value = i * span / (count as u128 + 1);
+            let mut key = vec![b'0'; width];
+            for slot in key.iter_mut().rev() {
+                *slot = ORDER_DIGITS[(value % base) as usize];
+                value /= base;
+            }
+            while key.last() == Some(&b'0') {
+                key.pop();
+            }
+            String::from_utf8(key).expect("order digits are ASCII")
+        })
+        .collect()
 }
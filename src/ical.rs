@@ -0,0 +1,388 @@
+//! iCalendar (RFC 5545) export and import of sessions.
+//!
+//! The export writes one `VEVENT` per session, titled with the goal name
+//! and with the kind and quantity in its description, so a calendar app can
+//! show focus history next to the user's appointments. Like the tabular
+//! export it streams to an `ExportSink` a day at a time.
+//!
+//! The import reads `VEVENT`s with a start and an end (or duration) and
+//! records each through `session_graph::add_session`, so imported sessions
+//! pass the same validation as ones recorded in the app. All-day events are
+//! skipped. Times with a `TZID` are read as local time, since resolving
+//! time zone names would need a time zone database.
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::export::ExportSink;
+use crate::ffi_types::AppError;
+use crate::goals;
+use crate::session_graph::{self, list_day_sessions};
+use crate::session_index;
+use crate::types::{
+    Goal, IcsImportOptions, IcsImportReport, Session, SessionMetadata, SessionValidation,
+    SkippedEvent,
+};
+
+const PRODID: &str = "-//Success//successlib//EN";
+
+/// Content lines longer than this many bytes are folded.
+const MAX_LINE_BYTES: usize = 75;
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Append `line` to `out`, folded so no physical line exceeds
+/// `MAX_LINE_BYTES`.
+fn push_line(out: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_BYTES {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn utc_stamp(ts: i64) -> String {
+    Utc.timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_default()
+}
+
+/// Write the sessions recorded between `start` and `end` (inclusive, both
+/// optional) to `sink` as an iCalendar file and return how many were
+/// written.
+pub fn export_ics(
+    archive: &Path,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    sink: &dyn ExportSink,
+) -> Result<u64, AppError> {
+    let goals: HashMap<u64, Goal> = goals::read_goals(archive)?
+        .into_iter()
+        .map(|g| (g.id, g))
+        .collect();
+    let days = session_index::load_index(archive)?
+        .recorded_days()
+        .into_iter()
+        .filter(|day| start.is_none_or(|s| *day >= s) && end.is_none_or(|e| *day <= e));
+    let stamp = utc_stamp(Utc::now().timestamp());
+
+    let mut header = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        &format!("PRODID:{PRODID}"),
+    ] {
+        push_line(&mut header, line);
+    }
+    sink.write(header);
+
+    let mut count = 0;
+    for day in days {
+        let mut chunk = String::new();
+        for session in list_day_sessions(archive, day)? {
+            let goal = goals.get(&session.goal_id);
+            let name = goal.map_or(session.name.as_str(), |g| g.name.as_str());
+            let mut description = format!("Kind: {:?}", session.kind);
+            if let Some(quantity) = session.quantity {
+                description.push_str(&format!("\nQuantity: {quantity}"));
+                if let Some(unit) = goal.and_then(|g| g.quantity_name.as_deref()) {
+                    description.push_str(&format!(" {unit}"));
+                }
+            }
+            let uid = format!("{}-{}@success", day.format("%Y-%m-%d"), session.id);
+            for line in [
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}", escape_text(&uid)),
+                format!("DTSTAMP:{stamp}"),
+                format!("DTSTART:{}", utc_stamp(session.start_at)),
+                format!("DTEND:{}", utc_stamp(session.end_at)),
+                format!("SUMMARY:{}", escape_text(name)),
+                format!("DESCRIPTION:{}", escape_text(&description)),
+                "END:VEVENT".to_string(),
+            ] {
+                push_line(&mut chunk, &line);
+            }
+            count += 1;
+        }
+        if !chunk.is_empty() {
+            sink.write(chunk);
+        }
+    }
+
+    let mut footer = String::new();
+    push_line(&mut footer, "END:VCALENDAR");
+    sink.write(footer);
+    Ok(count)
+}
+
+/// A property of a `VEVENT`: its parameters (upper-cased) and raw value.
+struct Property {
+    params: Vec<String>,
+    value: String,
+}
+
+type Event = HashMap<String, Property>;
+
+/// Split a content line into name, parameters and value. The value starts
+/// after the first `:` outside a quoted parameter value.
+fn parse_line(line: &str) -> Option<(String, Property)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some(i),
+            _ => {}
+        }
+        None
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts.map(|p| p.trim().to_ascii_uppercase()).collect();
+    Some((
+        name,
+        Property {
+            params,
+            value: value.to_string(),
+        },
+    ))
+}
+
+/// The `VEVENT`s of an iCalendar file, with folded lines joined.
+fn parse_events(data: &str) -> Vec<Event> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in data.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    // Nested components such as VALARM have properties of their own.
+    let mut depth = 0;
+    for line in &lines {
+        let Some((name, property)) = parse_line(line) else {
+            continue;
+        };
+        let component = property.value.trim().to_ascii_uppercase();
+        match name.as_str() {
+            "BEGIN" if component == "VEVENT" => {
+                current = Some(HashMap::new());
+                depth = 0;
+            }
+            "END" if component == "VEVENT" => events.extend(current.take()),
+            "BEGIN" => depth += 1,
+            "END" => depth -= 1,
+            _ => {
+                if let Some(event) = current.as_mut().filter(|_| depth == 0) {
+                    event.entry(name).or_insert(property);
+                }
+            }
+        }
+    }
+    events
+}
+
+/// Read a `DATE-TIME` value: UTC when it ends in `Z`, local time otherwise.
+fn parse_date_time(property: &Property) -> Result<DateTime<Utc>, String> {
+    let value = property.value.trim();
+    if property.params.iter().any(|p| p == "VALUE=DATE") || value.len() == 8 {
+        return Err("all-day events are not sessions".into());
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map(|t| t.and_utc())
+            .map_err(|e| format!("invalid time {value}: {e}"));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map_err(|e| format!("invalid time {value}: {e}"))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .ok_or_else(|| format!("{value} does not exist in the local time zone"))
+}
+
+/// Read a `DURATION` value such as `PT1H30M` or `P1DT2H`, in seconds.
+fn parse_duration(value: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid duration {value}");
+    let value = value.trim();
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+    let mut secs: i64 = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        if c == 'T' {
+            in_time = true;
+            continue;
+        }
+        let n: i64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+        let unit = match (c, in_time) {
+            ('W', false) => 7 * 24 * 3600,
+            ('D', false) => 24 * 3600,
+            ('H', true) => 3600,
+            ('M', true) => 60,
+            ('S', true) => 1,
+            _ => return Err(invalid()),
+        };
+        secs = n
+            .checked_mul(unit)
+            .and_then(|part| secs.checked_add(part))
+            .ok_or_else(|| format!("duration {value} is too long"))?;
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(sign * secs)
+}
+
+/// `Quantity: <n>` from a description written by `export_ics`.
+fn parse_quantity(description: &str) -> Option<u32> {
+    description.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Quantity:")?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    })
+}
+
+fn find_goal<'a>(goals: &'a [Goal], summary: &str, options: &IcsImportOptions) -> Option<&'a Goal> {
+    if let Some(id) = options.goal_mapping.get(summary) {
+        return goals.iter().find(|g| g.id == *id);
+    }
+    if !options.match_by_name {
+        return None;
+    }
+    let summary = summary.trim();
+    goals
+        .iter()
+        .find(|g| !g.trashed && g.name.trim().eq_ignore_ascii_case(summary))
+}
+
+/// Record the events of the iCalendar file `data` as sessions. Events that
+/// match no goal or fail validation are reported as skipped; storage errors
+/// abort the import.
+pub fn import_ics(
+    archive: &Path,
+    data: &str,
+    options: &IcsImportOptions,
+    validation: &SessionValidation,
+) -> Result<IcsImportReport, AppError> {
+    let goals = goals::read_goals(archive)?;
+    let mut report = IcsImportReport::default();
+    for event in parse_events(data) {
+        match import_event(archive, &goals, &event, options, validation)? {
+            Ok(session) => report.imported.push(session),
+            Err(reason) => report.skipped.push(SkippedEvent {
+                uid: event.get("UID").map(|p| unescape_text(&p.value)),
+                summary: summary(&event),
+                start_at: event
+                    .get("DTSTART")
+                    .and_then(|p| parse_date_time(p).ok())
+                    .map(|t| t.timestamp()),
+                reason,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+fn summary(event: &Event) -> String {
+    event
+        .get("SUMMARY")
+        .map(|p| unescape_text(&p.value))
+        .unwrap_or_default()
+}
+
+/// Record one event. The inner error is the reason it was skipped.
+fn import_event(
+    archive: &Path,
+    goals: &[Goal],
+    event: &Event,
+    options: &IcsImportOptions,
+    validation: &SessionValidation,
+) -> Result<Result<Session, String>, AppError> {
+    let timing = || -> Result<(DateTime<Utc>, u32), String> {
+        let start = event.get("DTSTART").ok_or("no start time")?;
+        let start_at = parse_date_time(start)?;
+        let secs = match (event.get("DTEND"), event.get("DURATION")) {
+            (Some(end), _) => parse_date_time(end)?.timestamp() - start_at.timestamp(),
+            (None, Some(duration)) => parse_duration(&duration.value)?,
+            (None, None) => return Err("no end time or duration".into()),
+        };
+        let secs = u32::try_from(secs).map_err(|_| format!("invalid duration of {secs}s"))?;
+        Ok((start_at, secs))
+    };
+    let (start_at, duration_secs) = match timing() {
+        Ok(timing) => timing,
+        Err(reason) => return Ok(Err(reason)),
+    };
+    let summary = summary(event);
+    let Some(goal) = find_goal(goals, &summary, options) else {
+        return Ok(Err(format!("no goal matches \"{summary}\"")));
+    };
+    let quantity = goal.quantity_name.as_ref().and_then(|_| {
+        let description = event.get("DESCRIPTION")?;
+        parse_quantity(&unescape_text(&description.value))
+    });
+
+    let result = session_graph::add_session(
+        archive,
+        goal.id,
+        &goal.name,
+        start_at,
+        duration_secs,
+        goal.is_reward,
+        quantity,
+        validation,
+        &SessionMetadata::default(),
+    );
+    match result {
+        Ok(session) => Ok(Ok(session)),
+        Err(err) if err.rejects_session() => Ok(Err(err.to_string())),
+        Err(err) => Err(err),
+    }
+}
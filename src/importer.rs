@@ -0,0 +1,460 @@
+//! Import of CSV exports from other time trackers.
+//!
+//! Each row becomes a session of the goal it names, recorded through
+//! `session_graph::add_session` so it is validated like any other session
+//! and the day graphs stay canonical. Rows that repeat a session the archive
+//! already has (same goal, start and end) are counted as duplicates, which
+//! makes it safe to import overlapping exports one after another.
+//!
+//! A dry run goes through the same steps without writing: it checks each
+//! row against the sessions already recorded plus the rows accepted before
+//! it, and reports the goals it would create with provisional ids.
+use std::collections::hash_map::{Entry, HashMap};
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::ffi_types::AppError;
+use crate::goals::{self, next_goal_id};
+use crate::session_graph::{
+    self, list_day_sessions, merge_sessions, next_session_id, overlapping_sessions, validate_timing,
+};
+use crate::types::{
+    CsvColumns, Goal, GoalStatus, OverlapPolicy, Session, SessionKind, SessionMetadata,
+    SessionValidation, SkippedRow, TrackerFormat, TrackerImportOptions, TrackerImportReport,
+};
+
+/// Local date and time formats tried in order.
+const DATE_TIME_FORMATS: [&str; 12] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %I:%M %p",
+    "%d.%m.%Y %H:%M:%S",
+    "%d.%m.%Y %H:%M",
+];
+
+/// Formats with a UTC offset, tried after RFC 3339.
+const ZONED_FORMATS: [&str; 3] = [
+    "%Y-%m-%d %H:%M:%S %z",
+    "%a %b %d %H:%M:%S GMT%z %Y",
+    "%a %b %d %Y %H:%M:%S GMT%z",
+];
+
+fn names(columns: &[&str]) -> Vec<String> {
+    columns.iter().map(|c| c.to_string()).collect()
+}
+
+/// Built-in column mapping of `format`.
+fn preset(format: TrackerFormat) -> Option<CsvColumns> {
+    match format {
+        TrackerFormat::Toggl => Some(CsvColumns {
+            goal: names(&["Project", "Description"]),
+            start: "Start time".into(),
+            start_date: Some("Start date".into()),
+            end: Some("End time".into()),
+            end_date: Some("End date".into()),
+            duration: Some("Duration".into()),
+            ..Default::default()
+        }),
+        TrackerFormat::Clockify => Some(CsvColumns {
+            goal: names(&["Project", "Description"]),
+            start: "Start Time".into(),
+            start_date: Some("Start Date".into()),
+            end: Some("End Time".into()),
+            end_date: Some("End Date".into()),
+            duration: Some("Duration (h)".into()),
+            ..Default::default()
+        }),
+        TrackerFormat::Forest => Some(CsvColumns {
+            goal: names(&["Tag", "Note"]),
+            start: "Start Time".into(),
+            end: Some("End Time".into()),
+            success: Some("Is Success".into()),
+            ..Default::default()
+        }),
+        TrackerFormat::Generic => None,
+    }
+}
+
+/// `CsvColumns` resolved to column indices.
+struct Layout {
+    goal: Vec<usize>,
+    start: usize,
+    start_date: Option<usize>,
+    end: Option<usize>,
+    end_date: Option<usize>,
+    duration: Option<usize>,
+    quantity: Option<usize>,
+    success: Option<usize>,
+}
+
+impl Layout {
+    fn resolve(columns: &CsvColumns, headers: &csv::StringRecord) -> Result<Self, AppError> {
+        let find = |name: &str| -> Result<usize, AppError> {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| AppError::InvalidInput {
+                    detail: format!("the CSV has no column named \"{name}\""),
+                })
+        };
+        let optional = |name: &Option<String>| name.as_deref().map(find).transpose();
+        if columns.goal.is_empty() {
+            return Err(AppError::InvalidInput {
+                detail: "the column mapping names no goal column".into(),
+            });
+        }
+        let layout = Layout {
+            goal: columns
+                .goal
+                .iter()
+                .map(|name| find(name))
+                .collect::<Result<_, _>>()?,
+            start: find(&columns.start)?,
+            start_date: optional(&columns.start_date)?,
+            end: optional(&columns.end)?,
+            end_date: optional(&columns.end_date)?,
+            duration: optional(&columns.duration)?,
+            quantity: optional(&columns.quantity)?,
+            success: optional(&columns.success)?,
+        };
+        if layout.end.is_none() && layout.duration.is_none() {
+            return Err(AppError::InvalidInput {
+                detail: "the column mapping needs an end or a duration column".into(),
+            });
+        }
+        Ok(layout)
+    }
+}
+
+fn parse_date_time(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    for format in ZONED_FORMATS {
+        if let Ok(t) = DateTime::parse_from_str(value, format) {
+            return Ok(t.with_timezone(&Utc));
+        }
+    }
+    let naive = DATE_TIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+        .ok_or_else(|| format!("unrecognised date and time \"{value}\""))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .ok_or_else(|| format!("{value} does not exist in the local time zone"))
+}
+
+/// `H:MM:SS`, `H:MM` or decimal hours, in seconds.
+fn parse_duration(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    let invalid = || format!("unrecognised duration \"{value}\"");
+    if !value.contains(':') {
+        let hours: f64 = value.parse().map_err(|_| invalid())?;
+        let secs = (hours * 3600.0).round();
+        if !secs.is_finite() || secs.abs() > i64::MAX as f64 {
+            return Err(format!("duration \"{value}\" is too long"));
+        }
+        return Ok(secs as i64);
+    }
+    let parts: Vec<i64> = value
+        .split(':')
+        .map(|p| p.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let (h, m, s) = match parts[..] {
+        [h, m] => (h, m, 0),
+        [h, m, s] => (h, m, s),
+        _ => return Err(invalid()),
+    };
+    h.checked_mul(3600)
+        .and_then(|secs| secs.checked_add(m.checked_mul(60)?))
+        .and_then(|secs| secs.checked_add(s))
+        .ok_or_else(|| format!("duration \"{value}\" is too long"))
+}
+
+/// A row turned into what `add_session` needs.
+struct Row {
+    goal_name: String,
+    start_at: DateTime<Utc>,
+    duration_secs: u32,
+    quantity: Option<u32>,
+}
+
+/// Read one row; the error is the reason it is skipped.
+fn read_row(layout: &Layout, record: &csv::StringRecord) -> Result<Option<Row>, String> {
+    let field = |i: usize| record.get(i).unwrap_or("").trim();
+    let with_date = |date: Option<usize>, time: usize| match date {
+        Some(date) => format!("{} {}", field(date), field(time)),
+        None => field(time).to_string(),
+    };
+
+    if let Some(success) = layout.success {
+        if matches!(
+            field(success).to_ascii_lowercase().as_str(),
+            "false" | "no" | "0"
+        ) {
+            return Ok(None);
+        }
+    }
+    let goal_name = layout
+        .goal
+        .iter()
+        .map(|&i| field(i))
+        .find(|name| !name.is_empty())
+        .ok_or("no goal name")?
+        .to_string();
+    let start_at = parse_date_time(&with_date(layout.start_date, layout.start))?;
+    let end = layout.end.filter(|&i| !field(i).is_empty());
+    let secs = match (end, layout.duration) {
+        (Some(end), _) => {
+            let end_date = layout.end_date.or(layout.start_date);
+            let mut end_at = parse_date_time(&with_date(end_date, end))?;
+            // An end time that borrows the start date but is earlier than
+            // the start is on the next day.
+            if layout.end_date.is_none() && layout.start_date.is_some() && end_at < start_at {
+                end_at += Duration::days(1);
+            }
+            end_at.timestamp() - start_at.timestamp()
+        }
+        (None, Some(duration)) => parse_duration(field(duration))?,
+        (None, None) => return Err("no end time or duration".into()),
+    };
+    let duration_secs = u32::try_from(secs).map_err(|_| format!("invalid duration of {secs}s"))?;
+    let quantity = match layout.quantity.map(field).filter(|q| !q.is_empty()) {
+        Some(q) => Some(
+            q.parse()
+                .map_err(|_| format!("quantity \"{q}\" is not a whole number"))?,
+        ),
+        None => None,
+    };
+    Ok(Some(Row {
+        goal_name,
+        start_at,
+        duration_secs,
+        quantity,
+    }))
+}
+
+/// Check a session the way `add_session` would, against `nodes`, and add
+/// it there; for dry runs.
+fn plan_session(
+    nodes: &mut Vec<Session>,
+    goal: &Goal,
+    row: &Row,
+    quantity: Option<u32>,
+    validation: &SessionValidation,
+) -> Result<Session, AppError> {
+    validate_timing(row.start_at, row.duration_secs, validation)?;
+    let kind = if goal.is_reward {
+        SessionKind::Reward
+    } else {
+        SessionKind::Goal
+    };
+    let start_at = row.start_at.timestamp();
+    let end_at = start_at + i64::from(row.duration_secs);
+    let mut session = Session {
+        id: next_session_id(nodes, kind),
+        name: goal.name.clone(),
+        goal_id: goal.id,
+        kind,
+        quantity,
+        start_at,
+        end_at,
+        rating: None,
+        tags: vec![],
+        note_path: None,
+    };
+    let overlapping = overlapping_sessions(nodes, start_at, end_at);
+    if !overlapping.is_empty() {
+        let conflicting_ids: Vec<String> = overlapping.iter().map(|n| n.id.clone()).collect();
+        let rejected = match validation.overlap {
+            OverlapPolicy::Allow => false,
+            OverlapPolicy::Reject => true,
+            OverlapPolicy::Merge => overlapping
+                .iter()
+                .any(|n| n.goal_id != goal.id || n.kind != kind),
+        };
+        if rejected {
+            return Err(AppError::SessionOverlap {
+                session_ids: conflicting_ids,
+            });
+        }
+        if validation.overlap == OverlapPolicy::Merge {
+            // Fold into the sessions it overlaps, as `add_session` would.
+            session = merge_sessions(session, &overlapping);
+            nodes.retain(|n| !conflicting_ids.contains(&n.id));
+        }
+    }
+    nodes.push(session.clone());
+    Ok(session)
+}
+
+/// Import the rows of the CSV file `data`.
+pub fn import_csv(
+    archive: &Path,
+    data: &str,
+    options: &TrackerImportOptions,
+    validation: &SessionValidation,
+) -> Result<TrackerImportReport, AppError> {
+    let columns = options
+        .columns
+        .clone()
+        .or_else(|| preset(options.format))
+        .ok_or_else(|| AppError::InvalidInput {
+            detail: "a generic CSV import needs a column mapping".into(),
+        })?;
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(data.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let layout = Layout::resolve(&columns, &headers)?;
+
+    let mut report = TrackerImportReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+    let mut goals = goals::read_goals(archive)?;
+    // Sessions per day: as recorded, plus the rows accepted in a dry run.
+    let mut days: HashMap<NaiveDate, Vec<Session>> = HashMap::new();
+
+    for record in reader.records() {
+        report.rows_read += 1;
+        let (line, row) = match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line() as u32);
+                (line, read_row(&layout, &record))
+            }
+            Err(err) => {
+                let line = err.position().map_or(0, |p| p.line() as u32);
+                (line, Err(err.to_string()))
+            }
+        };
+        let row = match row {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                report.skipped.push(SkippedRow {
+                    line,
+                    reason: "marked as unsuccessful".into(),
+                });
+                continue;
+            }
+            Err(reason) => {
+                report.skipped.push(SkippedRow { line, reason });
+                continue;
+            }
+        };
+
+        let existing = goals
+            .iter()
+            .find(|g| !g.trashed && g.name.eq_ignore_ascii_case(&row.goal_name));
+        // A missing goal starts out provisional and is only created once its
+        // first session has passed validation.
+        let (mut goal, created) = match existing {
+            Some(goal) => (goal.clone(), false),
+            None if options.create_missing_goals => {
+                let goal = Goal {
+                    id: next_goal_id(&goals),
+                    name: row.goal_name.clone(),
+                    is_reward: false,
+                    commands: vec![],
+                    status: GoalStatus::TODO,
+                    trashed: false,
+                    quantity_name: None,
+                    pinned: false,
+                    sort_order: goals::next_sort_order(&goals),
+                    launch_commands: vec![],
+                };
+                (goal, true)
+            }
+            None => {
+                report.skipped.push(SkippedRow {
+                    line,
+                    reason: format!("no goal named \"{}\"", row.goal_name),
+                });
+                continue;
+            }
+        };
+        let quantity = row.quantity.filter(|_| goal.quantity_name.is_some());
+
+        let day = row.start_at.with_timezone(&Local).date_naive();
+        let nodes = match days.entry(day) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(list_day_sessions(archive, day)?),
+        };
+        let start_at = row.start_at.timestamp();
+        let end_at = start_at + i64::from(row.duration_secs);
+        let duplicate = nodes
+            .iter()
+            .any(|s| s.goal_id == goal.id && s.start_at == start_at && s.end_at == end_at);
+        if duplicate {
+            report.duplicates += 1;
+            continue;
+        }
+
+        let result = if options.dry_run {
+            plan_session(nodes, &goal, &row, quantity, validation)
+        } else {
+            // A new goal is checked against a copy of the day first, so a
+            // rejected row leaves no goal behind.
+            let checked = if created {
+                plan_session(&mut nodes.clone(), &goal, &row, quantity, validation).map(drop)
+            } else {
+                Ok(())
+            };
+            checked
+                .and_then(|()| {
+                    if created {
+                        goal = goals::add_goal(archive, &row.goal_name, false, vec![], None)?;
+                    }
+                    session_graph::add_session(
+                        archive,
+                        goal.id,
+                        &goal.name,
+                        row.start_at,
+                        row.duration_secs,
+                        goal.is_reward,
+                        quantity,
+                        validation,
+                        &SessionMetadata::default(),
+                    )
+                })
+                .and_then(|session| {
+                    // Re-read the day, since the session may have been merged.
+                    *nodes = list_day_sessions(archive, day)?;
+                    Ok(session)
+                })
+        };
+        if created && result.is_ok() {
+            goals.push(goal.clone());
+            report.goals_created.push(goal);
+        }
+        match result {
+            Ok(session) => report.sessions.push(session),
+            Err(err) if err.rejects_session() => {
+                report.skipped.push(SkippedRow {
+                    line,
+                    reason: err.to_string(),
+                });
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(report)
+}
+
+fn csv_error(err: csv::Error) -> AppError {
+    AppError::Parse {
+        detail: err.to_string(),
+    }
+}
@@ -0,0 +1,329 @@
+//! Journal of the changes made to an archive, for undo and redo.
+//!
+//! Every journaled mutation appends a line to `journal/log.jsonl` naming
+//! the operation and the archive files it changed. The content of those
+//! files before and after the change is kept in `journal/{id}.json` for the
+//! last `UNDO_DEPTH` entries only, and only as long as they fit in
+//! `UNDO_BYTES` together, so the log stays a complete record of what
+//! happened (for sync and audit) while the journal fits in browser storage.
+//! `journal/index.json` tracks which changes are kept.
+//!
+//! Undo and redo are entries of the journal too: they put back the content
+//! of an earlier entry, and which entries are undone follows from replaying
+//! the log. Neither overwrites a file that changed since the entry was
+//! written, e.g. by an import of a whole archive or a snapshot restore,
+//! which are not journaled.
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{self, ArchiveEvent};
+use crate::ffi_types::AppError;
+use crate::snapshot;
+use crate::storage_io;
+use crate::types::JournalEntry;
+
+/// Number of most recent entries that can still be undone.
+const UNDO_DEPTH: usize = 100;
+
+/// Total size of the changes kept for undo; localStorage only holds about
+/// 5 MB per origin.
+const UNDO_BYTES: usize = 1 << 20;
+
+const UNDO: &str = "undo";
+const REDO: &str = "redo";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LogLine {
+    id: u64,
+    at: i64,
+    operation: String,
+    #[serde(default)]
+    target: Option<u64>,
+    files: Vec<String>,
+}
+
+/// Content of an archive-relative file before and after an entry; `None`
+/// when the file did not or no longer exists.
+#[derive(Debug, Serialize, Deserialize)]
+struct FileChange {
+    path: String,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+/// The id of the last entry, and the ids and sizes of the entries whose
+/// changes are kept, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    last_id: u64,
+    kept: Vec<(u64, usize)>,
+}
+
+fn journal_dir(archive: &Path) -> PathBuf {
+    archive.join("journal")
+}
+
+fn log_path(archive: &Path) -> PathBuf {
+    journal_dir(archive).join("log.jsonl")
+}
+
+fn changes_path(archive: &Path, id: u64) -> PathBuf {
+    journal_dir(archive).join(format!("{id}.json"))
+}
+
+fn index_path(archive: &Path) -> PathBuf {
+    journal_dir(archive).join("index.json")
+}
+
+/// Read the index, or rebuild it from the log and the change files of a
+/// journal written before there was one.
+fn read_index(archive: &Path) -> Result<Index, AppError> {
+    if let Some(data) = storage_io::read_to_string(archive, &index_path(archive))? {
+        return serde_json::from_str(&data).map_err(|e| AppError::Parse {
+            detail: format!("journal/index.json: {e}"),
+        });
+    }
+    let log = read_log(archive)?;
+    let mut index = Index {
+        last_id: log.last().map_or(0, |line| line.id),
+        kept: Vec::new(),
+    };
+    for line in log.iter().rev().take(UNDO_DEPTH).rev() {
+        if let Some(data) = storage_io::read_to_string(archive, &changes_path(archive, line.id))? {
+            index.kept.push((line.id, data.len()));
+        }
+    }
+    Ok(index)
+}
+
+fn read_log(archive: &Path) -> Result<Vec<LogLine>, AppError> {
+    let Some(data) = storage_io::read_to_string(archive, &log_path(archive))? else {
+        return Ok(vec![]);
+    };
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| AppError::Parse {
+                detail: format!("journal/log.jsonl: {e}"),
+            })
+        })
+        .collect()
+}
+
+/// Append an entry to the log and store its changes, dropping the oldest
+/// changes that no longer fit in the undo depth or size. Changes larger
+/// than `UNDO_BYTES` on their own are not kept at all.
+fn append(
+    archive: &Path,
+    operation: &str,
+    target: Option<u64>,
+    changes: &[FileChange],
+) -> Result<LogLine, AppError> {
+    let mut index = read_index(archive)?;
+    let id = index.last_id + 1;
+    index.last_id = id;
+    let data = serde_json::to_string(changes)?;
+    if data.len() <= UNDO_BYTES {
+        storage_io::write_string(archive, &changes_path(archive, id), &data)?;
+        index.kept.push((id, data.len()));
+    }
+    let mut total: usize = index.kept.iter().map(|(_, size)| size).sum();
+    while index.kept.len() > UNDO_DEPTH || total > UNDO_BYTES {
+        let (old, size) = index.kept.remove(0);
+        storage_io::delete(archive, &changes_path(archive, old))?;
+        total -= size;
+    }
+    storage_io::write_string(
+        archive,
+        &index_path(archive),
+        &serde_json::to_string(&index)?,
+    )?;
+
+    let line = LogLine {
+        id,
+        at: Utc::now().timestamp(),
+        operation: operation.to_string(),
+        target,
+        files: changes.iter().map(|c| c.path.clone()).collect(),
+    };
+    let text = serde_json::to_string(&line)? + "\n";
+    storage_io::append(archive, &log_path(archive), &text)?;
+    Ok(line)
+}
+
+/// Run the mutation `f` and journal the archive files it changed as
+/// `operation`. The mutation and its entry are stored together, or not at
+/// all; a mutation that changed nothing leaves no entry.
+pub fn record<T>(
+    archive: &Path,
+    operation: &str,
+    f: impl FnOnce() -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    storage_io::staged(|| {
+        let value = f()?;
+        let changes = archive_changes(archive)?;
+        if !changes.is_empty() {
+            append(archive, operation, None, &changes)?;
+        }
+        Ok(value)
+    })
+}
+
+/// The staged changes to user data; the journal itself and derived files
+/// such as `cache/` are left out.
+fn archive_changes(archive: &Path) -> Result<Vec<FileChange>, AppError> {
+    let mut changes = Vec::new();
+    for change in storage_io::staged_changes(archive)? {
+        let Ok(relative) = change.path.strip_prefix(archive) else {
+            continue;
+        };
+        let Some(parts) = relative
+            .iter()
+            .map(|part| part.to_str())
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let path = parts.join("/");
+        if snapshot::is_archive_file(&path) {
+            changes.push(FileChange {
+                path,
+                before: change.before,
+                after: change.after,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Ids of the entries that can be undone, most recent last, and of those
+/// that can be redone, next one last.
+fn stacks(log: &[LogLine]) -> (Vec<u64>, Vec<u64>) {
+    let mut done = Vec::new();
+    let mut undone = Vec::new();
+    for line in log {
+        match (line.operation.as_str(), line.target) {
+            (UNDO, Some(target)) => {
+                if done.last() == Some(&target) {
+                    done.pop();
+                    undone.push(target);
+                }
+            }
+            (REDO, Some(target)) => {
+                if undone.last() == Some(&target) {
+                    undone.pop();
+                    done.push(target);
+                }
+            }
+            _ => {
+                done.push(line.id);
+                undone.clear();
+            }
+        }
+    }
+    (done, undone)
+}
+
+fn entry(line: &LogLine, undone: &[u64]) -> JournalEntry {
+    JournalEntry {
+        id: line.id,
+        at: line.at,
+        operation: line.operation.clone(),
+        target: line.target,
+        files: line.files.clone(),
+        undone: undone.contains(&line.id),
+    }
+}
+
+/// Journal entries, most recent first; at most `limit` of them.
+pub fn history(archive: &Path, limit: Option<u32>) -> Result<Vec<JournalEntry>, AppError> {
+    let log = read_log(archive)?;
+    let (_, undone) = stacks(&log);
+    let limit = limit.map_or(usize::MAX, |n| n as usize);
+    Ok(log
+        .iter()
+        .rev()
+        .take(limit)
+        .map(|line| entry(line, &undone))
+        .collect())
+}
+
+/// Revert the most recent change that is not undone yet. Returns the new
+/// `undo` entry and the events of the change, or `None` when there is
+/// nothing left to undo.
+pub fn undo(archive: &Path) -> Result<Option<(JournalEntry, Vec<ArchiveEvent>)>, AppError> {
+    step(archive, UNDO)
+}
+
+/// Apply again the change undone last, unless another change was made
+/// since. Returns the new `redo` entry and the events of the change, or
+/// `None` when there is nothing to redo.
+pub fn redo(archive: &Path) -> Result<Option<(JournalEntry, Vec<ArchiveEvent>)>, AppError> {
+    step(archive, REDO)
+}
+
+fn step(
+    archive: &Path,
+    operation: &str,
+) -> Result<Option<(JournalEntry, Vec<ArchiveEvent>)>, AppError> {
+    storage_io::staged(|| {
+        let mut log = read_log(archive)?;
+        let (done, undone) = stacks(&log);
+        let target = if operation == UNDO {
+            done.last()
+        } else {
+            undone.last()
+        };
+        let Some(&target) = target else {
+            return Ok(None);
+        };
+        // Older entries only have their log line left.
+        let Some(data) = storage_io::read_to_string(archive, &changes_path(archive, target))?
+        else {
+            return Ok(None);
+        };
+        let changes: Vec<FileChange> = serde_json::from_str(&data)?;
+
+        let mut applied = Vec::with_capacity(changes.len());
+        let mut events = Vec::new();
+        for change in changes {
+            let (expected, content) = if operation == UNDO {
+                (change.after, change.before)
+            } else {
+                (change.before, change.after)
+            };
+            let path = archive.join(&change.path);
+            if storage_io::read_to_string(archive, &path)? != expected {
+                return Err(AppError::InvalidInput {
+                    detail: format!(
+                        "{} changed after journal entry {target}, cannot {operation} it",
+                        change.path
+                    ),
+                });
+            }
+            match &content {
+                Some(content) => storage_io::write_string(archive, &path, content)?,
+                None => {
+                    storage_io::delete(archive, &path)?;
+                }
+            }
+            events.extend(events::file_events(
+                &change.path,
+                expected.as_deref(),
+                content.as_deref(),
+            ));
+            applied.push(FileChange {
+                path: change.path,
+                before: expected,
+                after: content,
+            });
+        }
+
+        log.push(append(archive, operation, Some(target), &applied)?);
+        let (_, undone) = stacks(&log);
+        let line = log.last().expect("entry just appended");
+        Ok(Some((entry(line, &undone), events)))
+    })
+}
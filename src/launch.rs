@@ -0,0 +1,175 @@
+//! Launch commands of goals, resolved for the platform an app runs on.
+//!
+//! A goal carries typed `LaunchCommand`s next to its legacy `commands`
+//! strings. Resolving picks each command's variant for the platform (or its
+//! default), fills in the template variables and quotes them for the kind
+//! of command, so a goal name with spaces or quotes cannot break a shell
+//! line or a URL.
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::ffi_types::AppError;
+use crate::goals;
+use crate::notes;
+use crate::types::{CommandKind, Goal, LaunchCommand, Platform, ResolvedCommand};
+
+/// The platform this library was built for.
+pub fn current_platform() -> Platform {
+    if cfg!(target_arch = "wasm32") {
+        Platform::Web
+    } else if cfg!(target_os = "android") {
+        Platform::Android
+    } else if cfg!(target_os = "ios") {
+        Platform::Ios
+    } else if cfg!(target_os = "macos") {
+        Platform::MacOs
+    } else if cfg!(target_os = "windows") {
+        Platform::Windows
+    } else {
+        Platform::Linux
+    }
+}
+
+/// Reject commands that could never resolve to anything.
+pub fn validate_commands(commands: &[LaunchCommand]) -> Result<(), AppError> {
+    for (i, command) in commands.iter().enumerate() {
+        if command.value.is_none() && command.variants.is_empty() {
+            return Err(AppError::InvalidInput {
+                detail: format!("launch command {i} has no value for any platform"),
+            });
+        }
+        let mut values = command
+            .value
+            .iter()
+            .chain(command.variants.iter().map(|v| &v.value));
+        if values.any(|v| v.trim().is_empty()) {
+            return Err(AppError::InvalidInput {
+                detail: format!("launch command {i} has an empty value"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The commands of `goal_id` that apply on `platform`: its launch commands
+/// first, then its legacy command strings.
+pub fn resolve_launch_commands(
+    archive: &Path,
+    goal_id: u64,
+    platform: Platform,
+) -> Result<Vec<ResolvedCommand>, AppError> {
+    let goal = goals::get_goal(archive, goal_id)?;
+    let vars = variables(archive, &goal);
+    let mut resolved: Vec<ResolvedCommand> = goal
+        .launch_commands
+        .iter()
+        .filter_map(|command| {
+            let (kind, template) = match command.variants.iter().find(|v| v.platform == platform) {
+                Some(variant) => (variant.kind, &variant.value),
+                None => (command.kind, command.value.as_ref()?),
+            };
+            Some(ResolvedCommand {
+                label: command.label.clone(),
+                kind,
+                value: expand(template, &vars, |value| quote(kind, platform, value)),
+                legacy: false,
+            })
+        })
+        .collect();
+    resolved.extend(goal.commands.iter().map(|command| ResolvedCommand {
+        label: None,
+        kind: if looks_like_url(command) {
+            CommandKind::Url
+        } else {
+            CommandKind::Shell
+        },
+        value: command.clone(),
+        legacy: true,
+    }));
+    Ok(resolved)
+}
+
+fn variables(archive: &Path, goal: &Goal) -> Vec<(&'static str, String)> {
+    vec![
+        ("goal_name", goal.name.clone()),
+        ("goal_id", goal.id.to_string()),
+        ("archive", archive.display().to_string()),
+        (
+            "note_path",
+            notes::notes_path(archive, goal.id).display().to_string(),
+        ),
+        ("date", Local::now().format("%Y-%m-%d").to_string()),
+    ]
+}
+
+/// Replace `{name}` with the quoted value of the variable; `{{` and `}}`
+/// are literal braces. Unknown names are left as they are, so shell
+/// constructs such as `${HOME}` survive.
+fn expand(
+    template: &str,
+    vars: &[(&'static str, String)],
+    quote: impl Fn(&str) -> String,
+) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let value = tail
+            .find('}')
+            .filter(|_| tail.starts_with('{'))
+            .and_then(|end| {
+                let (_, value) = vars.iter().find(|(name, _)| *name == &tail[1..end])?;
+                Some((end, value))
+            });
+        match value {
+            Some((end, value)) => {
+                out.push_str(&quote(value));
+                rest = &tail[end + 1..];
+            }
+            None => {
+                out.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Quote a variable's value so it stays a single shell word, or a single
+/// URL component (slashes are kept, so paths still work in `file://` URLs).
+fn quote(kind: CommandKind, platform: Platform, value: &str) -> String {
+    match (kind, platform) {
+        (CommandKind::Shell, Platform::Windows) => format!("\"{}\"", value.replace('"', "\"\"")),
+        (CommandKind::Shell, _) => format!("'{}'", value.replace('\'', "'\\''")),
+        (CommandKind::Url, _) => value
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{b:02X}"),
+            })
+            .collect(),
+        (CommandKind::AppId | CommandKind::File, _) => value.to_string(),
+    }
+}
+
+fn looks_like_url(command: &str) -> bool {
+    let Some((scheme, rest)) = command.split_once("://") else {
+        return false;
+    };
+    !rest.is_empty()
+        && !scheme.is_empty()
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !command.contains(char::is_whitespace)
+}
@@ -3,7 +3,18 @@
 //! This crate exposes a small, focused set of functions for listing and
 //! manipulating goals, notes, and sessions. The functions are exported via
 //! `uniffi` for use by language bindings.
+mod archive;
+mod batch;
+mod check;
+mod events;
+mod export;
 mod ffi_types;
+mod ical;
+mod importer;
+mod journal;
+mod launch;
+mod manifest;
+mod search;
 
 // Hide internal module pages from the crate-level docs; the re-exported
 // items are still visible at the crate root and will appear in the docs.
@@ -14,17 +25,38 @@ pub mod notes;
 #[doc(hidden)]
 pub mod session_graph;
 #[doc(hidden)]
+mod session_index;
+mod snapshot;
+#[cfg(target_arch = "wasm32")]
+mod storage_idb;
+#[doc(hidden)]
 mod storage_io;
+mod templates;
+mod transfer;
 #[doc(hidden)]
 pub mod types;
+#[cfg(target_arch = "wasm32")]
+#[doc(hidden)]
+pub mod wasm;
 
-use chrono::{NaiveDate, TimeZone, Utc};
-use std::path::Path;
+use std::sync::Arc;
 
 use ffi_types::AppError;
 
+pub use archive::{Archive, ArchiveOptions};
+pub use events::{ArchiveEvent, ArchiveListener};
+pub use export::ExportSink;
 pub use ffi_types::AppError as Error;
-pub use types::{timestamp_to_date_iso, Goal, GoalStatus, Session, SessionKind};
+pub use types::{
+    timestamp_to_date_iso, ArchiveIssue, BatchOp, BatchResult, CheckReport, CommandKind,
+    CommandVariant, CsvColumns, DayTotal, ExportFormat, FrontmatterField, FrontmatterValue, Goal,
+    GoalSort, GoalStatus, GoalTemplate, IcsImportOptions, IcsImportReport, ImportMode,
+    ImportSummary, IssueKind, JournalEntry, LaunchCommand, OverlapPolicy, Platform, RankedGoal,
+    ResolvedCommand, RetentionPolicy, ScoredGoal, SearchHit, SearchHitKind, SearchRanking, Session,
+    SessionKind, SessionMetadata, SessionPage, SessionValidation, SkippedEvent, SkippedRow,
+    SnapshotInfo, TemplateGoal, TextRange, TrackerFormat, TrackerImportOptions,
+    TrackerImportReport,
+};
 
 #[cfg(not(target_arch = "wasm32"))]
 uniffi::setup_scaffolding!();
@@ -33,14 +65,16 @@ uniffi::setup_scaffolding!();
 ///
 /// - `archive_path`: path to the archive directory.
 /// - `statuses`: optional filter to restrict returned goals by `GoalStatus`.
+/// - `sort`: optional order of the result, defaults to `GoalSort::Archive`.
 ///
 /// Returns `Ok(Vec<Goal>)` on success or an `AppError` on failure.
 #[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
 pub fn list_goals(
     archive_path: String,
     statuses: Option<Vec<GoalStatus>>,
+    sort: Option<GoalSort>,
 ) -> Result<Vec<Goal>, AppError> {
-    goals::list_goals(Path::new(&archive_path), statuses.as_deref())
+    Archive::attach_supported(archive_path)?.list_goals(statuses, sort)
 }
 
 /// Return goals that are currently trashed
@@ -48,7 +82,7 @@ pub fn list_goals(
 /// Returns `Ok(Vec<Goal>)` on success or an `AppError` on failure.
 #[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
 pub fn list_trash(archive_path: String) -> Result<Vec<Goal>, AppError> {
-    goals::list_trash(Path::new(&archive_path))
+    Archive::attach_supported(archive_path)?.list_trash()
 }
 
 /// Search goals by `query` in the archive at `archive_path`.
@@ -67,15 +101,68 @@ pub fn search_goals(
     statuses: Option<Vec<GoalStatus>>,
     sort_by_recent: Option<bool>,
 ) -> Result<Vec<Goal>, AppError> {
-    goals::search_goals(
-        Path::new(&archive_path),
-        &query,
+    Archive::attach_supported(archive_path)?.search_goals(
+        query,
+        is_reward,
+        statuses,
+        sort_by_recent,
+    )
+}
+
+/// Same as `search_goals`, but each result also carries its fuzzy score,
+/// the character indices of the name that matched `query` (for bolding them
+/// in a picker) and whether recent activity moved it up the list.
+///
+/// Takes the same parameters as `search_goals` and returns results in the
+/// same order.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn search_goals_scored(
+    archive_path: String,
+    query: String,
+    is_reward: Option<bool>,
+    statuses: Option<Vec<GoalStatus>>,
+    sort_by_recent: Option<bool>,
+) -> Result<Vec<ScoredGoal>, AppError> {
+    Archive::attach_supported(archive_path)?.search_goals_scored(
+        query,
         is_reward,
-        statuses.as_deref(),
-        sort_by_recent.unwrap_or(true),
+        statuses,
+        sort_by_recent,
     )
 }
 
+/// Search goals and rank them by a weighted mix of fuzzy score, recency,
+/// session frequency and pinning, computed over the whole history.
+///
+/// - `query`, `is_reward`, `statuses`: as for `search_goals`.
+/// - `ranking`: weights of the signals; see `SearchRanking` for defaults.
+///
+/// Returns the goals, best first, or `AppError::InvalidInput` for weights
+/// that are not finite or a half-life that is not positive.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn search_goals_ranked(
+    archive_path: String,
+    query: String,
+    is_reward: Option<bool>,
+    statuses: Option<Vec<GoalStatus>>,
+    ranking: Option<SearchRanking>,
+) -> Result<Vec<RankedGoal>, AppError> {
+    Archive::attach_supported(archive_path)?
+        .search_goals_ranked(query, is_reward, statuses, ranking)
+}
+
+/// Search goal names, goal notes and session names for `query`.
+///
+/// - `query`: text to look for. Names match fuzzily, like `search_goals`;
+///   a note line matches when it contains every word of the query.
+///
+/// Goals in the trash are left out. Returns the hits, best first, each with
+/// a snippet and the character ranges to highlight in it.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn search_archive(archive_path: String, query: String) -> Result<Vec<SearchHit>, AppError> {
+    Archive::attach_supported(archive_path)?.search_archive(query)
+}
+
 /// Add a new goal
 ///
 /// - `name`: the goal name.
@@ -91,13 +178,7 @@ pub fn add_goal(
     commands: Vec<String>,
     quantity_name: Option<String>,
 ) -> Result<Goal, AppError> {
-    goals::add_goal(
-        Path::new(&archive_path),
-        &name,
-        is_reward,
-        commands,
-        quantity_name,
-    )
+    Archive::attach_supported(archive_path)?.add_goal(name, is_reward, commands, quantity_name)
 }
 
 /// Retrieve the note content for the goal identified by `goal_id`.
@@ -105,7 +186,7 @@ pub fn add_goal(
 /// Returns the note text as `String` or an `AppError` if retrieval fails.
 #[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
 pub fn get_note(archive_path: String, goal_id: u64) -> Result<String, AppError> {
-    notes::get_note(Path::new(&archive_path), goal_id)
+    Archive::attach_supported(archive_path)?.get_note(goal_id)
 }
 
 /// Replace the note content for the goal `goal_id` with `content`.
@@ -117,8 +198,43 @@ pub fn edit_note(
     goal_id: u64,
     content: String,
 ) -> Result<bool, AppError> {
-    notes::edit_note(Path::new(&archive_path), goal_id, &content)?;
-    Ok(true)
+    Archive::attach_supported(archive_path)?.edit_note(goal_id, content)
+}
+
+/// Read the YAML frontmatter of the note for goal `goal_id`: the block
+/// between `---` lines at the top of the note, where metadata that does not
+/// belong in `goals.yaml` (links, motivation, resources) can live.
+///
+/// Returns the fields in file order; empty when the note has none.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn get_note_frontmatter(
+    archive_path: String,
+    goal_id: u64,
+) -> Result<Vec<FrontmatterField>, AppError> {
+    Archive::attach_supported(archive_path)?.get_note_frontmatter(goal_id)
+}
+
+/// Set and remove frontmatter fields of the note for goal `goal_id`
+/// without touching the rest of the note.
+///
+/// - `set`: fields to add or overwrite.
+/// - `remove`: keys of fields to delete.
+///
+/// Returns all fields after the update.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn update_note_frontmatter(
+    archive_path: String,
+    goal_id: u64,
+    set: Vec<FrontmatterField>,
+    remove: Vec<String>,
+) -> Result<Vec<FrontmatterField>, AppError> {
+    Archive::attach_supported(archive_path)?.update_note_frontmatter(goal_id, set, remove)
+}
+
+/// Retrieve the note for goal `goal_id` without its frontmatter.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn get_note_body(archive_path: String, goal_id: u64) -> Result<String, AppError> {
+    Archive::attach_supported(archive_path)?.get_note_body(goal_id)
 }
 
 /// Update the `status` of the goal identified by `goal_id`.
@@ -130,7 +246,7 @@ pub fn set_goal_status(
     goal_id: u64,
     status: GoalStatus,
 ) -> Result<Goal, AppError> {
-    goals::set_goal_status(Path::new(&archive_path), goal_id, status)
+    Archive::attach_supported(archive_path)?.set_goal_status(goal_id, status)
 }
 
 /// Mark a goal as trashed or untrashed.
@@ -144,7 +260,113 @@ pub fn set_goal_trashed(
     goal_id: u64,
     trashed: bool,
 ) -> Result<Goal, AppError> {
-    goals::set_goal_trashed(Path::new(&archive_path), goal_id, trashed)
+    Archive::attach_supported(archive_path)?.set_goal_trashed(goal_id, trashed)
+}
+
+/// Pin a goal to the top of the manual order, or unpin it.
+///
+/// Pinned goals also get a boost in `search_goals_ranked`.
+///
+/// Returns the updated `Goal` or an `AppError` on failure.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn set_pinned(archive_path: String, goal_id: u64, pinned: bool) -> Result<Goal, AppError> {
+    Archive::attach_supported(archive_path)?.set_pinned(goal_id, pinned)
+}
+
+/// Replace the typed launch commands of a goal.
+///
+/// - `commands`: the new commands; each needs a default `value` or at least
+///   one platform variant, and no value may be empty.
+///
+/// The legacy `Goal::commands` strings are kept. Returns the updated `Goal`
+/// or an `AppError` on failure.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn set_launch_commands(
+    archive_path: String,
+    goal_id: u64,
+    commands: Vec<LaunchCommand>,
+) -> Result<Goal, AppError> {
+    Archive::attach_supported(archive_path)?.set_launch_commands(goal_id, commands)
+}
+
+/// Resolve the commands of a goal for a platform.
+///
+/// - `platform`: the platform to resolve for; defaults to the platform this
+///   library was built for (`Web` in the wasm build).
+///
+/// Returns the goal's launch commands that apply on the platform, with
+/// their template variables filled in, followed by its legacy command
+/// strings, or an `AppError` on failure.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn resolve_launch_commands(
+    archive_path: String,
+    goal_id: u64,
+    platform: Option<Platform>,
+) -> Result<Vec<ResolvedCommand>, AppError> {
+    Archive::attach_supported(archive_path)?.resolve_launch_commands(goal_id, platform)
+}
+
+/// Move a goal in the manual order, e.g. after a drag and drop.
+///
+/// - `before_id`: the goal to place it right before; `None` moves it to the
+///   end.
+///
+/// Only the moved goal is rewritten, so concurrent moves of other goals are
+/// kept. Returns the updated `Goal` or an `AppError` on failure.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn move_goal(
+    archive_path: String,
+    goal_id: u64,
+    before_id: Option<u64>,
+) -> Result<Goal, AppError> {
+    Archive::attach_supported(archive_path)?.move_goal(goal_id, before_id)
+}
+
+/// Goal templates stored in the archive's `templates/` folder, by id.
+///
+/// Returns the templates; files that are not valid templates are skipped
+/// so they do not hide the others.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn list_templates(archive_path: String) -> Result<Vec<GoalTemplate>, AppError> {
+    Archive::attach_supported(archive_path)?.list_templates()
+}
+
+/// Store a template in the archive as `templates/{id}.yaml`, replacing the
+/// template with the same id.
+///
+/// Returns an `AppError::InvalidInput` for an invalid id, a template
+/// without goals or a goal without a name.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn save_template(archive_path: String, template: GoalTemplate) -> Result<(), AppError> {
+    Archive::attach_supported(archive_path)?.save_template(template)
+}
+
+/// Delete a stored template.
+///
+/// Returns `AppError::NotFound` when there is no template with that id.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn delete_template(archive_path: String, id: String) -> Result<(), AppError> {
+    Archive::attach_supported(archive_path)?.delete_template(id)
+}
+
+/// Parse a template shipped by an app as YAML, in the same shape as the
+/// files in `templates/`, and check that it can be applied.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn parse_template(yaml: String) -> Result<GoalTemplate, AppError> {
+    templates::parse_template(&yaml)
+}
+
+/// Create every goal of a template, with one write of `goals.yaml`, and
+/// write their initial notes.
+///
+/// - `template`: a stored template from `list_templates` or one returned
+///   by `parse_template`.
+///
+/// Returns the ids of the new goals, in template order, or an `AppError`
+/// on failure, in which case no goal was created.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn apply_template(archive_path: String, template: GoalTemplate) -> Result<Vec<u64>, AppError> {
+    Archive::attach_supported(archive_path)?.apply_template(template)
 }
 
 /// Add a session for the specified goal and return a `Session`.
@@ -152,8 +374,15 @@ pub fn set_goal_trashed(
 /// - `start_ts_secs`: Unix timestamp (seconds) for session start.
 /// - `duration_secs`: duration of the session in seconds.
 /// - `is_reward`: whether the session is tied to a reward goal.
+/// - `validation`: optional rules for overlaps, future starts and duration
+///   bounds. Defaults to `SessionValidation::default()` (reject overlaps and
+///   future sessions, durations from a minute to 23h59).
+/// - `metadata`: optional rating, tags and note for the session.
 ///
-/// Returns the created `Session` or an `AppError` on failure.
+/// Returns the created `Session` or an `AppError` on failure. Validation
+/// failures are reported as `SessionOverlap`, `SessionInFuture` or
+/// `InvalidDuration`.
+#[allow(clippy::too_many_arguments)]
 #[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
 pub fn add_session(
     archive_path: String,
@@ -163,22 +392,88 @@ pub fn add_session(
     duration_secs: u32,
     is_reward: bool,
     quantity: Option<u32>,
+    validation: Option<SessionValidation>,
+    metadata: Option<SessionMetadata>,
 ) -> Result<Session, AppError> {
-    let start_at = Utc
-        .timestamp_opt(start_ts_secs, 0)
-        .single()
-        .ok_or_else(|| AppError::InvalidInput {
-            detail: format!("invalid start_ts_secs: {start_ts_secs}"),
-        })?;
-
-    session_graph::add_session(
-        Path::new(&archive_path),
+    Archive::attach_supported(archive_path)?.add_session(
         goal_id,
-        &goal_name,
-        start_at,
+        goal_name,
+        start_ts_secs,
         duration_secs,
         is_reward,
         quantity,
+        validation,
+        metadata,
+    )
+}
+
+/// Apply several goal, session and note operations as one change.
+///
+/// - `ops`: the operations, applied in order; each sees the changes of the
+///   operations before it.
+/// - `validation`: rules for every `AddSession`, as for `add_session`.
+///
+/// Every file touched by the batch is written once, at the end. Returns one
+/// `BatchResult` per operation, or the `AppError` of the first operation
+/// that failed, in which case the archive is left unchanged.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn apply_batch(
+    archive_path: String,
+    ops: Vec<BatchOp>,
+    validation: Option<SessionValidation>,
+) -> Result<Vec<BatchResult>, AppError> {
+    Archive::attach_supported(archive_path)?.apply_batch(ops, validation)
+}
+
+/// Update the rating, tags and note of the session `session_id` recorded on
+/// `date_iso` (YYYY-MM-DD).
+///
+/// Rating and tags are replaced. The note is overwritten only when
+/// `metadata.note` is set; an empty note detaches it from the session.
+///
+/// Returns the updated `Session` or an `AppError` on failure.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn update_session(
+    archive_path: String,
+    date_iso: String,
+    session_id: String,
+    metadata: SessionMetadata,
+) -> Result<Session, AppError> {
+    Archive::attach_supported(archive_path)?.update_session(date_iso, session_id, metadata)
+}
+
+/// Retrieve the note of the session `session_id` recorded on `date_iso`.
+///
+/// Returns an empty string when the session has no note.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn get_session_note(
+    archive_path: String,
+    date_iso: String,
+    session_id: String,
+) -> Result<String, AppError> {
+    Archive::attach_supported(archive_path)?.get_session_note(date_iso, session_id)
+}
+
+/// List sessions between two dates filtered by metadata.
+///
+/// - `start_date_iso` / `end_date_iso`: same defaults as `list_sessions_between_dates`.
+/// - `tags`: optional list of tags a session must all carry.
+/// - `min_rating`: optional minimum focus rating.
+///
+/// Returns a vector of `Session` or an `AppError` on failure.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn query_sessions(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    tags: Option<Vec<String>>,
+    min_rating: Option<u8>,
+) -> Result<Vec<Session>, AppError> {
+    Archive::attach_supported(archive_path)?.query_sessions(
+        start_date_iso,
+        end_date_iso,
+        tags,
+        min_rating,
     )
 }
 
@@ -192,13 +487,7 @@ pub fn list_day_sessions(
     archive_path: String,
     date_iso: String,
 ) -> Result<Vec<Session>, AppError> {
-    let date = NaiveDate::parse_from_str(&date_iso, "%Y-%m-%d").map_err(|e| {
-        AppError::InvalidInput {
-            detail: format!("date_iso must be YYYY-MM-DD: {e}"),
-        }
-    })?;
-
-    session_graph::list_day_sessions(Path::new(&archive_path), date)
+    Archive::attach_supported(archive_path)?.list_day_sessions(date_iso)
 }
 
 /// List sessions between two dates (inclusive).
@@ -213,9 +502,304 @@ pub fn list_sessions_between_dates(
     start_date_iso: Option<String>,
     end_date_iso: Option<String>,
 ) -> Result<Vec<Session>, AppError> {
-    session_graph::list_sessions_between_dates(
-        Path::new(&archive_path),
-        start_date_iso.as_deref(),
-        end_date_iso.as_deref(),
+    Archive::attach_supported(archive_path)?
+        .list_sessions_between_dates(start_date_iso, end_date_iso)
+}
+
+/// Per-day totals between two dates (inclusive).
+///
+/// - `start_date_iso`: optional start date in `YYYY-MM-DD` format (defaults to 7 days ago).
+/// - `end_date_iso`: optional end date in `YYYY-MM-DD` format (defaults to today).
+///
+/// Days without sessions are omitted. Totals come from the session index,
+/// so this does not parse the day graphs unless they changed.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn list_day_totals(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+) -> Result<Vec<DayTotal>, AppError> {
+    Archive::attach_supported(archive_path)?.list_day_totals(start_date_iso, end_date_iso)
+}
+
+/// List all sessions recorded for `goal_id`, newest first, one page at a time.
+///
+/// - `start_date_iso` / `end_date_iso`: optional inclusive bounds in
+///   `YYYY-MM-DD` format. Unlike `list_sessions_between_dates` there is no
+///   default window: omitted bounds cover the whole history.
+/// - `kind`: optional filter on `SessionKind`.
+/// - `limit`: page size, defaults to 50.
+/// - `cursor`: `next_cursor` from the previous page, or `None` for the first page.
+///
+/// Returns a `SessionPage` or an `AppError` on failure.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn list_goal_sessions(
+    archive_path: String,
+    goal_id: u64,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    kind: Option<SessionKind>,
+    limit: Option<u32>,
+    cursor: Option<String>,
+) -> Result<SessionPage, AppError> {
+    Archive::attach_supported(archive_path)?.list_goal_sessions(
+        goal_id,
+        start_date_iso,
+        end_date_iso,
+        kind,
+        limit,
+        cursor,
     )
 }
+
+/// Every day with at least one session, oldest first, as `YYYY-MM-DD`.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn list_recorded_days(archive_path: String) -> Result<Vec<String>, AppError> {
+    Archive::attach_supported(archive_path)?.list_recorded_days()
+}
+
+/// The earliest day with a session as `YYYY-MM-DD`, or `None` if the
+/// archive has no sessions.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn first_recorded_day(archive_path: String) -> Result<Option<String>, AppError> {
+    Archive::attach_supported(archive_path)?.first_recorded_day()
+}
+
+/// The latest day with a session as `YYYY-MM-DD`, or `None` if the archive
+/// has no sessions.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn last_recorded_day(archive_path: String) -> Result<Option<String>, AppError> {
+    Archive::attach_supported(archive_path)?.last_recorded_day()
+}
+
+/// Schema version of the archive as recorded in its `success.yaml`
+/// manifest; 0 for archives created before the manifest existed.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn archive_schema_version(archive_path: String) -> Result<u32, AppError> {
+    Archive::attach(archive_path).schema_version()
+}
+
+/// Newest archive schema version this library can read and write.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn supported_schema_version() -> u32 {
+    manifest::SCHEMA_VERSION
+}
+
+/// Upgrade the archive to `supported_schema_version()` and return it.
+///
+/// The archive's files are copied to `backups/schema-v<old>-<timestamp>/`
+/// before anything is changed. Every write migrates the archive this way
+/// too, so calling this is only needed to upgrade ahead of time.
+///
+/// Fails with `AppError::UnsupportedSchemaVersion` if the archive is newer
+/// than this library.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn migrate_archive(archive_path: String) -> Result<u32, AppError> {
+    Archive::attach_supported(archive_path)?.migrate()
+}
+
+/// Check the archive for inconsistencies left by hand edits or concurrent
+/// writers, such as duplicate ids, sessions missing from the day chain,
+/// sessions of unknown goals and orphan notes.
+///
+/// - `repair`: also apply the fixes that cannot lose data. Issues fixed
+///   this way are marked `repaired` in the report; the rest need a person.
+///
+/// Returns a `CheckReport` or an `AppError` if the archive cannot be read.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn check_archive(archive_path: String, repair: bool) -> Result<CheckReport, AppError> {
+    Archive::attach_supported(archive_path)?.check_archive(repair)
+}
+
+/// Revert the most recent change made through the library that is not
+/// undone yet: goal, session, note and template changes and CSV or ICS
+/// imports are journaled, snapshot restores and `import_archive` are not.
+///
+/// Returns the journal entry recording the undo, `None` when there is
+/// nothing left to undo, or an `AppError::InvalidInput` when a file of the
+/// change was modified since by something that is not journaled.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn undo(archive_path: String) -> Result<Option<JournalEntry>, AppError> {
+    Archive::attach_supported(archive_path)?.undo()
+}
+
+/// Apply again the change undone last. Any new change made after the undo
+/// clears what can be redone.
+///
+/// Returns the journal entry recording the redo, or `None` when there is
+/// nothing to redo.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn redo(archive_path: String) -> Result<Option<JournalEntry>, AppError> {
+    Archive::attach_supported(archive_path)?.redo()
+}
+
+/// List the journal of changes, most recent first.
+///
+/// - `limit`: maximum number of entries, all of them when `None`.
+///
+/// The journal is kept in `journal/` inside the archive. Every entry stays
+/// listed, but only the last 100 can be undone.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn history(archive_path: String, limit: Option<u32>) -> Result<Vec<JournalEntry>, AppError> {
+    Archive::attach_supported(archive_path)?.history(limit)
+}
+
+/// Capture the whole archive (goals, day graphs and notes) in a single
+/// timestamped bundle under `snapshots/`.
+///
+/// - `label`: optional description shown by `list_snapshots`.
+///
+/// On the web the bundle lives in browser storage next to the archive, so
+/// it counts against the same quota.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn create_snapshot(
+    archive_path: String,
+    label: Option<String>,
+) -> Result<SnapshotInfo, AppError> {
+    Archive::attach_supported(archive_path)?.create_snapshot(label)
+}
+
+/// List the archive's snapshots, newest first.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn list_snapshots(archive_path: String) -> Result<Vec<SnapshotInfo>, AppError> {
+    Archive::attach_supported(archive_path)?.list_snapshots()
+}
+
+/// Replace the archive's goals, day graphs and notes with those captured
+/// in snapshot `snapshot_id`. Files created since are removed.
+///
+/// The current state is snapshotted first; that snapshot is returned so
+/// the restore itself can be undone.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn restore_snapshot(
+    archive_path: String,
+    snapshot_id: String,
+) -> Result<SnapshotInfo, AppError> {
+    Archive::attach_supported(archive_path)?.restore_snapshot(snapshot_id)
+}
+
+/// Delete the snapshots that `policy` does not keep and return their ids.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn prune_snapshots(
+    archive_path: String,
+    policy: RetentionPolicy,
+) -> Result<Vec<String>, AppError> {
+    Archive::attach_supported(archive_path)?.prune_snapshots(policy)
+}
+
+/// Export the whole archive (goals, day graphs and notes) as one JSON
+/// string, to be saved as a file and imported elsewhere with
+/// `import_archive`. This is how an archive kept in browser storage moves
+/// to the filesystem or another device.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn export_archive(archive_path: String) -> Result<String, AppError> {
+    Archive::attach_supported(archive_path)?.export_archive()
+}
+
+/// Import an archive exported by `export_archive` (or a snapshot bundle).
+///
+/// - `bundle`: the exported JSON string.
+/// - `mode`: `ImportMode::Merge` to add to the archive with id remapping,
+///   `ImportMode::Replace` to make the archive a copy of the bundle.
+///
+/// The archive is snapshotted first; the summary carries that snapshot's
+/// id so the import can be undone with `restore_snapshot`. Merged sessions
+/// that overlap the archive's go through the default `SessionValidation`
+/// overlap policy, and `Replace` keeps the archive's manifest when the
+/// bundle has none.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn import_archive(
+    archive_path: String,
+    bundle: String,
+    mode: ImportMode,
+) -> Result<ImportSummary, AppError> {
+    Archive::attach_supported(archive_path)?.import_archive(bundle, mode)
+}
+
+/// Export sessions for spreadsheets, one row per session with its local
+/// date, start and end, duration, goal id and name, kind, quantity and the
+/// goal's quantity unit.
+///
+/// - `start_date_iso` / `end_date_iso`: optional inclusive range
+///   (`YYYY-MM-DD`); the whole history when omitted.
+/// - `format`: CSV (with a header line) or JSON Lines.
+/// - `sink`: receives the output in chunks of at most one day, so long
+///   histories are never built up as one string.
+///
+/// Returns the number of sessions written.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn export_sessions(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    format: ExportFormat,
+    sink: Arc<dyn ExportSink>,
+) -> Result<u64, AppError> {
+    Archive::attach_supported(archive_path)?.export_sessions(
+        start_date_iso,
+        end_date_iso,
+        format,
+        sink,
+    )
+}
+
+/// Export every goal, trashed ones included, in the same formats as
+/// `export_sessions`. Returns the number of goals written.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn export_goals(
+    archive_path: String,
+    format: ExportFormat,
+    sink: Arc<dyn ExportSink>,
+) -> Result<u64, AppError> {
+    Archive::attach_supported(archive_path)?.export_goals(format, sink)
+}
+
+/// Export sessions as an iCalendar file with one event per session, titled
+/// with the goal name, for viewing in a calendar app.
+///
+/// - `start_date_iso` / `end_date_iso`: optional inclusive range
+///   (`YYYY-MM-DD`); the whole history when omitted.
+/// - `sink`: receives the file in chunks, as with `export_sessions`.
+///
+/// Returns the number of sessions written.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn export_ics(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    sink: Arc<dyn ExportSink>,
+) -> Result<u64, AppError> {
+    Archive::attach_supported(archive_path)?.export_ics(start_date_iso, end_date_iso, sink)
+}
+
+/// Backfill sessions from the events of an iCalendar file.
+///
+/// Each timed event becomes a session of the goal it matches (see
+/// `IcsImportOptions`) and is validated like a session passed to
+/// `add_session`. Events without a match or failing validation are listed
+/// in the report instead; with the default validation, importing the same
+/// file twice skips every event the second time as an overlap.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn import_ics(
+    archive_path: String,
+    ics: String,
+    options: Option<IcsImportOptions>,
+) -> Result<IcsImportReport, AppError> {
+    Archive::attach_supported(archive_path)?.import_ics(ics, options)
+}
+
+/// Import sessions from a CSV file exported by another time tracker (Toggl,
+/// Clockify, Forest) or any CSV described by a column mapping.
+///
+/// Each row is recorded like a call to `add_session` for the goal it
+/// names, creating missing goals if asked to. Rows already in the archive
+/// count as duplicates and are left out. Options default to a dry run, so
+/// the report can be shown before anything is written.
+#[cfg_attr(not(target_arch = "wasm32"), uniffi::export)]
+pub fn import_tracker_csv(
+    archive_path: String,
+    csv: String,
+    options: TrackerImportOptions,
+) -> Result<TrackerImportReport, AppError> {
+    Archive::attach_supported(archive_path)?.import_tracker_csv(csv, options)
+}
@@ -0,0 +1,160 @@
+//! Archive manifest and schema migrations.
+//!
+//! `success.yaml` at the root of an archive records the schema version of
+//! the files next to it. Archives written before the manifest existed have
+//! no such file and are treated as version 0.
+//!
+//! Upgrades run one version at a time, after copying the archive's files to
+//! `backups/schema-v<old>-<timestamp>/`. The manifest is rewritten after
+//! every step, so an interrupted upgrade resumes where it stopped. Archives
+//! with a newer schema than this library supports are refused rather than
+//! rewritten in a format their app may not understand.
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::ffi_types::AppError;
+use crate::goals::goals_path;
+use crate::{snapshot, storage_io};
+
+/// Schema version written by this library.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A migration upgrades an archive from version `n` to `n + 1`, where `n`
+/// is its index in `MIGRATIONS`.
+type Migration = fn(&Path) -> Result<(), AppError>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [adopt_manifest];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    schema_version: u32,
+    /// Fields added by other apps or newer versions, kept as they are.
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+pub fn manifest_path(archive: &Path) -> PathBuf {
+    archive.join("success.yaml")
+}
+
+fn read_manifest(archive: &Path) -> Result<Option<Manifest>, AppError> {
+    let Some(content) = storage_io::read_to_string(archive, &manifest_path(archive))? else {
+        return Ok(None);
+    };
+    Ok(Some(serde_yaml::from_str(&content)?))
+}
+
+/// Schema version recorded in the manifest content `content`.
+pub fn parse_schema_version(content: &str) -> Result<u32, AppError> {
+    Ok(serde_yaml::from_str::<Manifest>(content)?.schema_version)
+}
+
+/// Fail with `AppError::UnsupportedSchemaVersion` if `version` is newer
+/// than this library.
+pub fn check_supported(version: u32) -> Result<(), AppError> {
+    if version > SCHEMA_VERSION {
+        return Err(AppError::UnsupportedSchemaVersion {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    Ok(())
+}
+
+/// Fingerprints of the manifests found supported, by archive, so repeated
+/// checks of an unchanged manifest do not read it again.
+fn supported_manifests() -> MutexGuard<'static, HashMap<PathBuf, String>> {
+    static SUPPORTED: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+    SUPPORTED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Refuse to read an archive written with a newer schema, whose files this
+/// library could misread. Nothing is migrated.
+pub fn ensure_supported(archive: &Path) -> Result<(), AppError> {
+    let path = manifest_path(archive);
+    let Some(fingerprint) = storage_io::fingerprint(archive, &path)? else {
+        return Ok(());
+    };
+    if supported_manifests().get(archive) == Some(&fingerprint) {
+        return Ok(());
+    }
+    if let Some(content) = storage_io::read_to_string(archive, &path)? {
+        check_supported(parse_schema_version(&content)?)?;
+    }
+    if !storage_io::recently_modified(archive, &path)? {
+        supported_manifests().insert(archive.to_path_buf(), fingerprint);
+    }
+    Ok(())
+}
+
+fn write_manifest(archive: &Path, manifest: &Manifest) -> Result<(), AppError> {
+    let content = serde_yaml::to_string(manifest)?;
+    storage_io::write_string(archive, &manifest_path(archive), &content)?;
+    Ok(())
+}
+
+/// Schema version of the archive, without migrating it.
+pub fn schema_version(archive: &Path) -> Result<u32, AppError> {
+    Ok(read_manifest(archive)?.map_or(0, |m| m.schema_version))
+}
+
+/// Bring the archive up to `SCHEMA_VERSION` and return that version.
+///
+/// A new archive (no manifest and no `goals.yaml`) just gets a manifest.
+/// Fails with `AppError::UnsupportedSchemaVersion` if the archive is newer
+/// than this library.
+pub fn migrate(archive: &Path) -> Result<u32, AppError> {
+    let mut manifest = match read_manifest(archive)? {
+        Some(manifest) => manifest,
+        None if !storage_io::exists(archive, &goals_path(archive))? => {
+            write_manifest(
+                archive,
+                &Manifest {
+                    schema_version: SCHEMA_VERSION,
+                    ..Default::default()
+                },
+            )?;
+            return Ok(SCHEMA_VERSION);
+        }
+        None => Manifest::default(),
+    };
+    check_supported(manifest.schema_version)?;
+    if manifest.schema_version == SCHEMA_VERSION {
+        return Ok(SCHEMA_VERSION);
+    }
+
+    back_up(archive, manifest.schema_version)?;
+    while manifest.schema_version < SCHEMA_VERSION {
+        MIGRATIONS[manifest.schema_version as usize](archive)?;
+        manifest.schema_version += 1;
+        write_manifest(archive, &manifest)?;
+    }
+    Ok(SCHEMA_VERSION)
+}
+
+/// Copy the archive's files (see `snapshot::archive_files`) to a new
+/// directory in `backups/`.
+fn back_up(archive: &Path, version: u32) -> Result<(), AppError> {
+    let stamp = Local::now().format("%Y%m%dT%H%M%S");
+    let backup = archive
+        .join("backups")
+        .join(format!("schema-v{version}-{stamp}"));
+    for relative in snapshot::archive_files(archive)? {
+        if let Some(content) = storage_io::read_to_string(archive, &archive.join(&relative))? {
+            storage_io::write_string(archive, &backup.join(&relative), &content)?;
+        }
+    }
+    Ok(())
+}
+
+/// 0 → 1: introduce `success.yaml`. The files themselves are unchanged.
+fn adopt_manifest(_archive: &Path) -> Result<(), AppError> {
+    Ok(())
+}
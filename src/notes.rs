@@ -1,12 +1,26 @@
 use std::path::Path;
 
+use chrono::NaiveDate;
+use serde_yaml::{Mapping, Value};
+
 use crate::ffi_types::AppError;
 use crate::storage_io;
+use crate::types::{FrontmatterField, FrontmatterValue};
 
 pub fn notes_path(archive: &Path, goal_id: u64) -> std::path::PathBuf {
     archive.join("notes").join(format!("goal_{goal_id}.md"))
 }
 
+/// Archive-relative path of the note attached to a session, e.g.
+/// `notes/sessions/2024-05-01/sess_2.md`.
+pub fn session_note_relative_path(date: NaiveDate, session_id: &str) -> String {
+    format!("notes/sessions/{}/{session_id}.md", date.format("%Y-%m-%d"))
+}
+
+pub fn session_note_path(archive: &Path, date: NaiveDate, session_id: &str) -> std::path::PathBuf {
+    archive.join(session_note_relative_path(date, session_id))
+}
+
 pub fn get_note(archive: &Path, goal_id: u64) -> Result<String, AppError> {
     let path = notes_path(archive, goal_id);
     Ok(storage_io::read_to_string(archive, &path)?.unwrap_or_default())
@@ -14,11 +28,166 @@ pub fn get_note(archive: &Path, goal_id: u64) -> Result<String, AppError> {
 
 pub fn edit_note(archive: &Path, goal_id: u64, content: &str) -> Result<(), AppError> {
     let path = notes_path(archive, goal_id);
+    write_note(archive, &path, content)
+}
+
+pub fn get_session_note(
+    archive: &Path,
+    date: NaiveDate,
+    session_id: &str,
+) -> Result<String, AppError> {
+    let path = session_note_path(archive, date, session_id);
+    Ok(storage_io::read_to_string(archive, &path)?.unwrap_or_default())
+}
+
+/// Write the note of a session; an empty note deletes the file.
+pub fn edit_session_note(
+    archive: &Path,
+    date: NaiveDate,
+    session_id: &str,
+    content: &str,
+) -> Result<(), AppError> {
+    let path = session_note_path(archive, date, session_id);
+    if content.trim().is_empty() {
+        storage_io::delete(archive, &path)?;
+        return Ok(());
+    }
+    write_note(archive, &path, content)
+}
+
+fn write_note(archive: &Path, path: &Path, content: &str) -> Result<(), AppError> {
     let content_with_newline = if content.ends_with('\n') {
         content.to_string()
     } else {
         format!("{}\n", content)
     };
-    storage_io::write_string(archive, &path, &content_with_newline)?;
+    storage_io::write_string(archive, path, &content_with_newline)?;
     Ok(())
 }
+
+/// Split a note into its YAML frontmatter and its body. A note has
+/// frontmatter when its first line is `---` and a later line is `---` or
+/// `...`; the body is everything after that line.
+pub fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn parse_frontmatter(yaml: &str) -> Result<Mapping, AppError> {
+    match serde_yaml::from_str::<Value>(yaml)? {
+        Value::Null => Ok(Mapping::new()),
+        Value::Mapping(mapping) => Ok(mapping),
+        _ => Err(AppError::Parse {
+            detail: "note frontmatter is not a set of key: value fields".into(),
+        }),
+    }
+}
+
+fn yaml_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn field_value(value: &Value) -> FrontmatterValue {
+    match value {
+        Value::Sequence(items) => FrontmatterValue::List {
+            items: items.iter().map(yaml_text).collect(),
+        },
+        other => FrontmatterValue::Text {
+            value: yaml_text(other),
+        },
+    }
+}
+
+fn fields(mapping: &Mapping) -> Vec<FrontmatterField> {
+    mapping
+        .iter()
+        .map(|(key, value)| FrontmatterField {
+            key: yaml_text(key),
+            value: field_value(value),
+        })
+        .collect()
+}
+
+/// Frontmatter fields of the goal note, in file order. Empty when the note
+/// has no frontmatter.
+pub fn get_note_frontmatter(
+    archive: &Path,
+    goal_id: u64,
+) -> Result<Vec<FrontmatterField>, AppError> {
+    let content = get_note(archive, goal_id)?;
+    match split_frontmatter(&content) {
+        Some((yaml, _)) => Ok(fields(&parse_frontmatter(yaml)?)),
+        None => Ok(vec![]),
+    }
+}
+
+/// The goal note without its frontmatter.
+pub fn get_note_body(archive: &Path, goal_id: u64) -> Result<String, AppError> {
+    let content = get_note(archive, goal_id)?;
+    Ok(match split_frontmatter(&content) {
+        Some((_, body)) => body.to_string(),
+        None => content,
+    })
+}
+
+/// Set the fields in `set` and remove those named in `remove`, leaving the
+/// body as it is, and return the resulting fields. Existing fields keep
+/// their position and new ones are appended. The frontmatter block is
+/// dropped once it has no fields left, and a note left empty is deleted.
+///
+/// The block is rewritten as a whole, so comments in it are not kept.
+pub fn update_note_frontmatter(
+    archive: &Path,
+    goal_id: u64,
+    set: Vec<FrontmatterField>,
+    remove: &[String],
+) -> Result<Vec<FrontmatterField>, AppError> {
+    let content = get_note(archive, goal_id)?;
+    let (mut mapping, body) = match split_frontmatter(&content) {
+        Some((yaml, body)) => (parse_frontmatter(yaml)?, body),
+        None => (Mapping::new(), content.as_str()),
+    };
+    for key in remove {
+        mapping.retain(|k, _| yaml_text(k) != *key);
+    }
+    for field in set {
+        let value = match field.value {
+            FrontmatterValue::Text { value } => Value::String(value),
+            FrontmatterValue::List { items } => {
+                Value::Sequence(items.into_iter().map(Value::String).collect())
+            }
+        };
+        let existing = mapping.keys().find(|k| yaml_text(k) == field.key).cloned();
+        let key = existing.unwrap_or(Value::String(field.key));
+        mapping.insert(key, value);
+    }
+
+    let updated = if mapping.is_empty() {
+        body.to_string()
+    } else {
+        format!("---\n{}---\n{body}", serde_yaml::to_string(&mapping)?)
+    };
+    let path = notes_path(archive, goal_id);
+    if updated.trim().is_empty() {
+        // Removing the last field of a note without a body leaves nothing.
+        storage_io::delete(archive, &path)?;
+    } else {
+        write_note(archive, &path, &updated)?;
+    }
+    Ok(fields(&mapping))
+}
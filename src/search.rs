@@ -0,0 +1,347 @@
+//! Search across everything the archive knows about a goal: its name, its
+//! note and the names given to its sessions.
+//!
+//! Names are matched fuzzily with the same `SkimMatcherV2` scoring as
+//! `search_goals`. Notes are prose, where a fuzzy match on scattered letters
+//! is almost always noise, so a note line only matches when it contains
+//! every word of the query; the fuzzy score is still used to rank it.
+//!
+//! Goals can also be ranked by a weighted mix of match quality, recent and
+//! frequent use and pinning, for launchers where the top result is started
+//! with a single tap.
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::{Local, Utc};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use crate::ffi_types::AppError;
+use crate::notes;
+use crate::session_index::{self, SessionName};
+use crate::types::{Goal, RankedGoal, SearchHit, SearchHitKind, SearchRanking, TextRange};
+
+/// Characters of a long note line kept before the first match.
+const SNIPPET_CONTEXT: usize = 30;
+/// Longest snippet taken from a note line, ellipses excluded.
+const SNIPPET_LEN: usize = 120;
+
+/// Search the names, notes and session names of the goals that are not in
+/// the trash. Every goal yields at most one hit of each kind (its best note
+/// line; its most recent session per distinct name). Hits are sorted by
+/// score, best first; an empty query finds nothing.
+pub fn search_archive(
+    archive: &Path,
+    goals: &[Goal],
+    query: &str,
+) -> Result<Vec<SearchHit>, AppError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+    let matcher = SkimMatcherV2::default();
+    let goals: HashMap<u64, &Goal> = goals
+        .iter()
+        .filter(|g| !g.trashed)
+        .map(|g| (g.id, g))
+        .collect();
+    let mut ids: Vec<u64> = goals.keys().copied().collect();
+    ids.sort_unstable();
+
+    let mut hits = Vec::new();
+    for id in &ids {
+        let goal = goals[id];
+        if let Some((score, indices)) = matcher.fuzzy_indices(&goal.name, query) {
+            hits.push(hit(
+                goal,
+                SearchHitKind::GoalName,
+                score,
+                goal.name.clone(),
+                &indices,
+            ));
+        }
+    }
+
+    // Session names come from the index, which keeps the distinct names of
+    // every day, rather than from parsing each day graph.
+    let mut seen = HashSet::new();
+    for (date, entry) in session_index::load_index(archive)?.days.iter().rev() {
+        let mut sessions: Vec<(u64, &SessionName)> = entry
+            .goals
+            .iter()
+            .flat_map(|(goal_id, stats)| stats.names.iter().map(|n| (*goal_id, n)))
+            .collect();
+        sessions.sort_by_key(|(_, s)| Reverse(s.start_at));
+        for (goal_id, session) in sessions {
+            let Some(goal) = goals.get(&goal_id) else {
+                continue;
+            };
+            let name = session.name.as_str();
+            let key = name.to_lowercase();
+            if key == goal.name.trim().to_lowercase() || !seen.insert((goal.id, key)) {
+                continue;
+            }
+            if let Some((score, indices)) = matcher.fuzzy_indices(name, query) {
+                hits.push(SearchHit {
+                    session_id: Some(session.session_id.clone()),
+                    date: Some(date.format("%Y-%m-%d").to_string()),
+                    ..hit(
+                        goal,
+                        SearchHitKind::SessionName,
+                        score,
+                        name.to_string(),
+                        &indices,
+                    )
+                });
+            }
+        }
+    }
+
+    let words: Vec<Vec<char>> = query.split_whitespace().map(fold).collect();
+    for id in &ids {
+        let goal = goals[id];
+        let note = notes::get_note(archive, goal.id)?;
+        let best = note
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let line = line.trim();
+                let ranges = find_words(line, &words)?;
+                let score = query
+                    .split_whitespace()
+                    .filter_map(|word| matcher.fuzzy_match(line, word))
+                    .sum::<i64>();
+                Some((score, i, line, ranges))
+            })
+            .max_by_key(|(score, i, _, _)| (*score, Reverse(*i)));
+        if let Some((score, i, line, ranges)) = best {
+            let (snippet, highlights) = snippet(line, &ranges);
+            hits.push(SearchHit {
+                line: Some(i as u32 + 1),
+                highlights,
+                ..hit(goal, SearchHitKind::GoalNote, score, snippet, &[])
+            });
+        }
+    }
+
+    // Stable, so equal scores keep names before session names before notes.
+    hits.sort_by_key(|h| Reverse(h.score));
+    Ok(hits)
+}
+
+/// Activity of one goal over the whole history.
+#[derive(Default, Clone, Copy)]
+struct Activity {
+    last_start_at: Option<i64>,
+    sessions: u32,
+    frecency: f64,
+}
+
+/// Filter `goals` by `query` and `is_reward`, then order them by the
+/// weighted signals of `ranking`, read from the session index over the
+/// whole history. Ties go to the better fuzzy match, then the older goal.
+pub fn rank_goals_weighted(
+    archive: &Path,
+    goals: Vec<Goal>,
+    query: &str,
+    is_reward: Option<bool>,
+    ranking: &SearchRanking,
+) -> Result<Vec<RankedGoal>, AppError> {
+    let weights = [
+        ranking.fuzzy_weight,
+        ranking.recency_weight,
+        ranking.frequency_weight,
+        ranking.pinned_weight,
+    ];
+    if weights.iter().any(|w| !w.is_finite())
+        || !(ranking.half_life_days.is_finite() && ranking.half_life_days > 0.0)
+    {
+        return Err(AppError::InvalidInput {
+            detail: "ranking weights must be finite and half_life_days greater than zero".into(),
+        });
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let query = query.trim();
+    let matched: Vec<(Goal, i64, Vec<usize>)> = goals
+        .into_iter()
+        .filter(|g| is_reward.is_none_or(|flag| g.is_reward == flag))
+        .filter_map(|g| {
+            if query.is_empty() {
+                return Some((g, 0, vec![]));
+            }
+            let (score, indices) = matcher.fuzzy_indices(&g.name, query)?;
+            Some((g, score, indices))
+        })
+        .collect();
+
+    let decay = |age_days: f64| 0.5f64.powf(age_days.max(0.0) / ranking.half_life_days);
+    let today = Local::now().date_naive();
+    let mut activity: HashMap<u64, Activity> = HashMap::new();
+    for (day, entry) in &session_index::load_index(archive)?.days {
+        let weight = decay((today - *day).num_days() as f64);
+        for (goal_id, stats) in &entry.goals {
+            let goal = activity.entry(*goal_id).or_default();
+            goal.last_start_at = goal.last_start_at.max(Some(stats.last_start_at));
+            goal.sessions += stats.sessions;
+            goal.frecency += f64::from(stats.sessions) * weight;
+        }
+    }
+
+    let best_score = matched
+        .iter()
+        .map(|(_, score, _)| *score)
+        .max()
+        .unwrap_or(0);
+    let busiest = matched
+        .iter()
+        .filter_map(|(g, _, _)| activity.get(&g.id))
+        .map(|a| a.frecency)
+        .fold(0.0, f64::max);
+    let now = Utc::now().timestamp();
+    let mut ranked: Vec<RankedGoal> = matched
+        .into_iter()
+        .map(|(goal, score, indices)| {
+            let active = activity.get(&goal.id).copied().unwrap_or_default();
+            let pinned = goal.pinned || ranking.pinned_goal_ids.contains(&goal.id);
+            let mut rank = 0.0;
+            if best_score > 0 {
+                rank += ranking.fuzzy_weight * score as f64 / best_score as f64;
+            }
+            if let Some(last) = active.last_start_at {
+                rank += ranking.recency_weight * decay((now - last) as f64 / 86_400.0);
+            }
+            if busiest > 0.0 {
+                rank += ranking.frequency_weight * active.frecency / busiest;
+            }
+            if pinned {
+                rank += ranking.pinned_weight;
+            }
+            RankedGoal {
+                goal,
+                rank,
+                score,
+                indices: indices.into_iter().map(|i| i as u32).collect(),
+                last_active_at: active.last_start_at,
+                session_count: active.sessions,
+                pinned,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.rank
+            .total_cmp(&a.rank)
+            .then(b.score.cmp(&a.score))
+            .then(a.goal.id.cmp(&b.goal.id))
+    });
+    Ok(ranked)
+}
+
+fn hit(
+    goal: &Goal,
+    kind: SearchHitKind,
+    score: i64,
+    snippet: String,
+    indices: &[usize],
+) -> SearchHit {
+    SearchHit {
+        kind,
+        goal_id: goal.id,
+        goal_name: goal.name.clone(),
+        session_id: None,
+        date: None,
+        line: None,
+        score,
+        snippet,
+        highlights: merge_indices(indices),
+    }
+}
+
+/// Turn the matched character indices of a fuzzy match into ranges.
+fn merge_indices(indices: &[usize]) -> Vec<TextRange> {
+    let mut ranges: Vec<TextRange> = Vec::new();
+    for &i in indices {
+        let i = i as u32;
+        match ranges.last_mut() {
+            Some(last) if last.end == i => last.end = i + 1,
+            _ => ranges.push(TextRange {
+                start: i,
+                end: i + 1,
+            }),
+        }
+    }
+    ranges
+}
+
+/// Lower-case `text` one character at a time, so that character offsets in
+/// the result are offsets in `text` too.
+fn fold(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Every occurrence of every word in `line`, ignoring case, as sorted,
+/// merged character ranges; `None` unless each word occurs at least once.
+fn find_words(line: &str, words: &[Vec<char>]) -> Option<Vec<(usize, usize)>> {
+    let haystack = fold(line);
+    let mut found = Vec::new();
+    for word in words {
+        let before = found.len();
+        found.extend(
+            haystack
+                .windows(word.len())
+                .enumerate()
+                .filter(|(_, window)| window == word)
+                .map(|(start, _)| (start, start + word.len())),
+        );
+        if found.len() == before {
+            return None;
+        }
+    }
+    found.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in found {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    Some(merged)
+}
+
+/// Cut a long `line` down to a window starting a little before the first
+/// match, and shift `ranges` to match the cut text.
+fn snippet(line: &str, ranges: &[(usize, usize)]) -> (String, Vec<TextRange>) {
+    let chars: Vec<char> = line.chars().collect();
+    let first = ranges.first().map_or(0, |r| r.0);
+    let start = if chars.len() <= SNIPPET_LEN {
+        0
+    } else {
+        first
+            .saturating_sub(SNIPPET_CONTEXT)
+            .min(chars.len() - SNIPPET_LEN)
+    };
+    let end = chars.len().min(start + SNIPPET_LEN);
+
+    let mut text = String::new();
+    let mut offset = start as i64;
+    if start > 0 {
+        text.push('…');
+        offset -= 1;
+    }
+    text.extend(&chars[start..end]);
+    if end < chars.len() {
+        text.push('…');
+    }
+    let highlights = ranges
+        .iter()
+        .filter(|(s, e)| *s < end && *e > start)
+        .map(|(s, e)| TextRange {
+            start: ((*s).max(start) as i64 - offset) as u32,
+            end: ((*e).min(end) as i64 - offset) as u32,
+        })
+        .collect();
+    (text, highlights)
+}
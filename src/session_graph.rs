@@ -7,8 +7,13 @@ use chrono::{
 
 use crate::ffi_types::AppError;
 use crate::goals::{get_goal, set_goal_status};
+use crate::notes::{edit_session_note, get_session_note, session_note_relative_path};
+use crate::session_index;
 use crate::storage_io;
-use crate::types::{GoalStatus, Session, SessionKind};
+use crate::types::{
+    DayTotal, GoalStatus, OverlapPolicy, Session, SessionKind, SessionMetadata, SessionPage,
+    SessionValidation,
+};
 
 pub fn ensure_archive_structure(archive: &Path) -> Result<(), AppError> {
     storage_io::ensure_archive_structure(archive)?;
@@ -31,6 +36,19 @@ pub fn get_formatted_session_time_range(node: &Session) -> String {
     format!("{start}-{end}")
 }
 
+/// Slack allowed between the caller's clock and ours before a session is
+/// considered to start in the future.
+const FUTURE_TOLERANCE_SECS: i64 = 60;
+
+/// Duration bounds a day graph can store: times are written as `HH:MM`, so a
+/// shorter session collapses to nothing and a whole day reads back as empty.
+const MIN_STORABLE_SECS: u32 = 60;
+const MAX_STORABLE_SECS: u32 = 24 * 60 * 60 - 60;
+
+/// Characters with a meaning in a mermaid node label.
+const TAG_FORBIDDEN: [char; 6] = ['[', ']', ',', ':', ';', '#'];
+
+#[allow(clippy::too_many_arguments)]
 pub fn add_session(
     archive: &Path,
     goal_id: u64,
@@ -39,6 +57,8 @@ pub fn add_session(
     duration_secs: u32,
     is_reward: bool,
     quantity: Option<u32>,
+    validation: &SessionValidation,
+    metadata: &SessionMetadata,
 ) -> Result<Session, AppError> {
     ensure_archive_structure(archive)?;
     if quantity.is_some() {
@@ -49,6 +69,9 @@ pub fn add_session(
             });
         }
     }
+    validate_timing(start_at, duration_secs, validation)?;
+    let tags = validate_metadata(metadata)?;
+
     let day = start_at.with_timezone(&Local).date_naive();
     let mut nodes = list_day_sessions(archive, day).unwrap_or_default();
     let kind = if is_reward {
@@ -56,18 +79,36 @@ pub fn add_session(
     } else {
         SessionKind::Goal
     };
-    let id = next_session_id(&nodes, kind);
     let end_at = start_at + ChronoDuration::seconds(duration_secs as i64);
+    check_neighbouring_days(
+        archive,
+        day,
+        end_at.with_timezone(&Local).date_naive(),
+        start_at.timestamp(),
+        end_at.timestamp(),
+        validation.overlap,
+    )?;
 
     let node = Session {
-        id,
+        id: next_session_id(&nodes, kind),
         name: goal_name.to_string(),
         goal_id,
         kind,
         quantity,
         start_at: start_at.timestamp(),
         end_at: end_at.timestamp(),
+        rating: metadata.rating,
+        tags,
+        note_path: None,
     };
+    let node = place_session(
+        archive,
+        day,
+        &mut nodes,
+        node,
+        validation.overlap,
+        metadata.note.clone(),
+    )?;
 
     if !is_reward {
         if let Err(err) = set_goal_status(archive, goal_id, GoalStatus::DOING) {
@@ -75,13 +116,273 @@ pub fn add_session(
         }
     }
 
-    nodes.push(node.clone());
     save_day_sessions(archive, &nodes, day)?;
     Ok(node)
 }
 
+/// Add the new session `node` to `nodes`, the sessions of `day`, applying
+/// the overlap `policy`: an overlapping session is refused, kept alongside
+/// the others, or folded into the sessions of the same goal it overlaps,
+/// whose notes move into its own. `note` is added to the note of the
+/// resulting session. The day graph itself is not saved.
+fn place_session(
+    archive: &Path,
+    day: NaiveDate,
+    nodes: &mut Vec<Session>,
+    mut node: Session,
+    policy: OverlapPolicy,
+    note: Option<String>,
+) -> Result<Session, AppError> {
+    // Notes of the sessions merged into `node`, whose ids disappear.
+    let mut note_texts = Vec::new();
+    let overlapping = overlapping_sessions(nodes, node.start_at, node.end_at);
+    if !overlapping.is_empty() {
+        let conflicting_ids: Vec<String> = overlapping.iter().map(|n| n.id.clone()).collect();
+        match policy {
+            OverlapPolicy::Allow => {}
+            OverlapPolicy::Reject => {
+                return Err(AppError::SessionOverlap {
+                    session_ids: conflicting_ids,
+                })
+            }
+            OverlapPolicy::Merge => {
+                if overlapping
+                    .iter()
+                    .any(|n| n.goal_id != node.goal_id || n.kind != node.kind)
+                {
+                    return Err(AppError::SessionOverlap {
+                        session_ids: conflicting_ids,
+                    });
+                }
+                node = merge_sessions(node, &overlapping);
+                for merged in &overlapping {
+                    if merged.id != node.id && merged.note_path.is_some() {
+                        note_texts.push(get_session_note(archive, day, &merged.id)?);
+                        edit_session_note(archive, day, &merged.id, "")?;
+                    }
+                }
+                nodes.retain(|n| !conflicting_ids.contains(&n.id));
+            }
+        }
+    }
+
+    note_texts.extend(note);
+    if !note_texts.is_empty() {
+        // A merged session may already carry a note; keep all the texts.
+        let mut content = match &node.note_path {
+            Some(_) => get_session_note(archive, day, &node.id)?,
+            None => String::new(),
+        };
+        for text in note_texts {
+            content = if content.is_empty() {
+                text
+            } else {
+                format!("{content}\n{text}")
+            };
+        }
+        node.note_path = write_note_for(archive, day, &node.id, &content)?;
+    }
+
+    nodes.push(node.clone());
+    Ok(node)
+}
+
+/// Replace the rating and tags of the session `session_id` recorded on
+/// `date`. When `metadata.note` is set the session note is overwritten, and
+/// an empty note detaches it from the session.
+pub fn update_session(
+    archive: &Path,
+    date: NaiveDate,
+    session_id: &str,
+    metadata: &SessionMetadata,
+) -> Result<Session, AppError> {
+    let tags = validate_metadata(metadata)?;
+    let mut nodes = list_day_sessions(archive, date)?;
+    let node = nodes
+        .iter_mut()
+        .find(|n| n.id == session_id)
+        .ok_or_else(|| AppError::NotFound {
+            resource: "session".into(),
+            id: session_id.to_string(),
+        })?;
+
+    node.rating = metadata.rating;
+    node.tags = tags;
+    if let Some(note) = &metadata.note {
+        node.note_path = write_note_for(archive, date, session_id, note)?;
+    }
+
+    let updated = node.clone();
+    save_day_sessions(archive, &nodes, date)?;
+    Ok(updated)
+}
+
+/// List sessions between two dates that carry every tag in `tags` and,
+/// when `min_rating` is set, a rating of at least `min_rating`.
+pub fn query_sessions(
+    archive: &Path,
+    start_date_iso: Option<&str>,
+    end_date_iso: Option<&str>,
+    tags: &[String],
+    min_rating: Option<u8>,
+) -> Result<Vec<Session>, AppError> {
+    let sessions = list_sessions_between_dates(archive, start_date_iso, end_date_iso)?;
+    Ok(sessions
+        .into_iter()
+        .filter(|s| tags.iter().all(|t| s.tags.contains(t)))
+        .filter(|s| match min_rating {
+            Some(min) => s.rating.is_some_and(|r| r >= min),
+            None => true,
+        })
+        .collect())
+}
+
+fn write_note_for(
+    archive: &Path,
+    date: NaiveDate,
+    session_id: &str,
+    content: &str,
+) -> Result<Option<String>, AppError> {
+    edit_session_note(archive, date, session_id, content)?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(session_note_relative_path(date, session_id)))
+}
+
+/// Check the rating range and return the trimmed, non-empty tags.
+fn validate_metadata(metadata: &SessionMetadata) -> Result<Vec<String>, AppError> {
+    if let Some(rating) = metadata.rating {
+        if !(1..=5).contains(&rating) {
+            return Err(AppError::InvalidInput {
+                detail: format!("rating must be between 1 and 5, got {rating}"),
+            });
+        }
+    }
+    let mut tags = Vec::new();
+    for tag in &metadata.tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        // Tags are written into the mermaid node label.
+        if tag.contains(TAG_FORBIDDEN) || tag.contains("-->") || tag.contains(char::is_control) {
+            return Err(AppError::InvalidInput {
+                detail: format!(
+                    "tag must not contain control characters, '-->' or any of {}: {tag:?}",
+                    TAG_FORBIDDEN.iter().collect::<String>()
+                ),
+            });
+        }
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    Ok(tags)
+}
+
+pub fn validate_timing(
+    start_at: DateTime<Utc>,
+    duration_secs: u32,
+    validation: &SessionValidation,
+) -> Result<(), AppError> {
+    let min_secs = validation.min_duration_secs.max(MIN_STORABLE_SECS);
+    let max_secs = validation.max_duration_secs.min(MAX_STORABLE_SECS);
+    if duration_secs < min_secs || duration_secs > max_secs {
+        return Err(AppError::InvalidDuration {
+            duration_secs,
+            min_secs,
+            max_secs,
+        });
+    }
+    if !validation.allow_future
+        && start_at.timestamp() > Utc::now().timestamp() + FUTURE_TOLERANCE_SECS
+    {
+        return Err(AppError::SessionInFuture {
+            start_at: start_at.timestamp(),
+        });
+    }
+    Ok(())
+}
+
+/// Refuse a session of `day` that overlaps one recorded in another day file:
+/// one from the day before that runs past midnight, or one on the days up to
+/// `end_day` the session runs into. Sessions are not merged across day files.
+fn check_neighbouring_days(
+    archive: &Path,
+    day: NaiveDate,
+    end_day: NaiveDate,
+    start_at: i64,
+    end_at: i64,
+    policy: OverlapPolicy,
+) -> Result<(), AppError> {
+    if policy == OverlapPolicy::Allow {
+        return Ok(());
+    }
+    let mut others = Vec::new();
+    let days = std::iter::successors(day.pred_opt(), |d| d.succ_opt())
+        .take_while(|d| *d <= end_day)
+        .filter(|d| *d != day);
+    for other_day in days {
+        others.extend(list_day_sessions(archive, other_day).unwrap_or_default());
+    }
+    let overlapping = overlapping_sessions(&others, start_at, end_at);
+    if overlapping.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::SessionOverlap {
+        session_ids: overlapping.iter().map(|n| n.id.clone()).collect(),
+    })
+}
+
+/// Sessions in `nodes` that overlap the range from `start_at` to `end_at`.
+pub fn overlapping_sessions(nodes: &[Session], start_at: i64, end_at: i64) -> Vec<&Session> {
+    nodes
+        .iter()
+        .filter(|n| n.start_at < end_at && start_at < n.end_at)
+        .collect()
+}
+
+/// Fold `node` into the sessions it overlaps, keeping the id of the earliest
+/// one so references to it stay valid.
+pub(crate) fn merge_sessions(node: Session, overlapping: &[&Session]) -> Session {
+    let first = overlapping
+        .iter()
+        .min_by_key(|n| n.start_at)
+        .expect("at least one overlapping session");
+    let start_at = overlapping
+        .iter()
+        .map(|n| n.start_at)
+        .fold(node.start_at, i64::min);
+    let end_at = overlapping
+        .iter()
+        .map(|n| n.end_at)
+        .fold(node.end_at, i64::max);
+    let quantity = overlapping
+        .iter()
+        .filter_map(|n| n.quantity)
+        .chain(node.quantity)
+        .max();
+    let mut tags = node.tags.clone();
+    for tag in overlapping.iter().flat_map(|n| &n.tags) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    Session {
+        id: first.id.clone(),
+        name: first.name.clone(),
+        start_at,
+        end_at,
+        quantity,
+        rating: node.rating.or(first.rating),
+        tags,
+        note_path: first.note_path.clone(),
+        ..node
+    }
+}
+
 pub fn list_day_sessions(archive: &Path, date: NaiveDate) -> Result<Vec<Session>, AppError> {
-    ensure_archive_structure(archive)?;
     let mermaid_path = day_mermaid_path(archive, date);
     if let Some(content) = storage_io::read_to_string(archive, &mermaid_path)? {
         return parse_mermaid(&content, date);
@@ -110,12 +411,11 @@ pub fn list_sessions_between_dates(
         end_date - ChronoDuration::days(7)
     };
 
+    let index = session_index::load_index_within(archive, Some((start_date, end_date)))?;
     let mut sessions = Vec::new();
-    let mut current = start_date;
-    while current <= end_date {
-        let day_sessions = list_day_sessions(archive, current).unwrap_or_default();
+    for day in index.days_between(start_date, end_date) {
+        let day_sessions = list_day_sessions(archive, day).unwrap_or_default();
         sessions.extend(day_sessions);
-        current += ChronoDuration::days(1);
     }
 
     sessions.sort_by_key(|s| s.start_at);
@@ -131,32 +431,175 @@ pub fn save_day_sessions(
     let mut sorted = nodes.to_vec();
     sorted.sort_by_key(|n| n.start_at);
 
+    // A day without sessions has no graph file rather than an empty one.
     let mermaid_path = day_mermaid_path(archive, date);
-    let mermaid = to_mermaid(&sorted);
-    storage_io::write_string(archive, &mermaid_path, &mermaid)?;
+    if sorted.is_empty() {
+        storage_io::delete(archive, &mermaid_path)?;
+    } else {
+        storage_io::write_string(archive, &mermaid_path, &to_mermaid(&sorted))?;
+    }
+
+    session_index::record_day(archive, date, &sorted);
 
     Ok(())
 }
 
+/// Every day with at least one session, oldest first, found by listing
+/// `graphs/` through the session index.
+pub fn list_recorded_days(archive: &Path) -> Result<Vec<NaiveDate>, AppError> {
+    Ok(session_index::load_index(archive)?.recorded_days())
+}
+
+/// Per-day totals between two dates (inclusive), read from the session
+/// index. Days without sessions are omitted.
+pub fn list_day_totals(
+    archive: &Path,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<DayTotal>, AppError> {
+    let index = session_index::load_index_within(archive, Some((start, end)))?;
+    Ok(index
+        .days_between(start, end)
+        .into_iter()
+        .filter_map(|day| {
+            let entry = index.days.get(&day)?;
+            Some(DayTotal {
+                date_iso: day_key(day),
+                goal_secs: entry.goal_secs,
+                reward_secs: entry.reward_secs,
+                session_count: entry.session_ids.len() as u32,
+            })
+        })
+        .collect())
+}
+
+/// List the sessions of `goal_id`, newest first, using the session index to
+/// only read the days that contain the goal.
+///
+/// The cursor has the form `YYYY-MM-DD/<start_at>/<session id>` and points at
+/// the last session of the previous page.
+#[allow(clippy::too_many_arguments)]
+pub fn list_goal_sessions(
+    archive: &Path,
+    goal_id: u64,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    kind: Option<SessionKind>,
+    limit: usize,
+    cursor: Option<&str>,
+) -> Result<SessionPage, AppError> {
+    if limit == 0 {
+        return Err(AppError::InvalidInput {
+            detail: "limit must be greater than 0".into(),
+        });
+    }
+    let cursor = cursor.map(parse_cursor).transpose()?;
+    let index = session_index::load_index(archive)?;
+
+    let mut sessions = Vec::new();
+    let mut has_more = false;
+    for day in index.days_for_goal(goal_id).into_iter().rev() {
+        if start.is_some_and(|s| day < s) {
+            break;
+        }
+        if end.is_some_and(|e| day > e) {
+            continue;
+        }
+        if cursor.as_ref().is_some_and(|(c_day, _, _)| day > *c_day) {
+            continue;
+        }
+
+        let mut day_sessions: Vec<Session> = list_day_sessions(archive, day)?
+            .into_iter()
+            .filter(|s| s.goal_id == goal_id)
+            .filter(|s| kind.is_none_or(|k| s.kind == k))
+            .filter(|s| match &cursor {
+                Some((c_day, c_start, c_id)) if day == *c_day => {
+                    (s.start_at, &s.id) < (*c_start, c_id)
+                }
+                _ => true,
+            })
+            .collect();
+        day_sessions.sort_by(|a, b| (b.start_at, &b.id).cmp(&(a.start_at, &a.id)));
+
+        for session in day_sessions {
+            if sessions.len() == limit {
+                has_more = true;
+                break;
+            }
+            sessions.push((day, session));
+        }
+        if has_more {
+            break;
+        }
+    }
+
+    let next_cursor = if has_more {
+        sessions
+            .last()
+            .map(|(day, s)| format!("{}/{}/{}", day_key(*day), s.start_at, s.id))
+    } else {
+        None
+    };
+    Ok(SessionPage {
+        sessions: sessions.into_iter().map(|(_, s)| s).collect(),
+        next_cursor,
+    })
+}
+
+fn parse_cursor(cursor: &str) -> Result<(NaiveDate, i64, String), AppError> {
+    let invalid = || AppError::InvalidInput {
+        detail: format!("invalid cursor: {cursor}"),
+    };
+    let mut parts = cursor.splitn(3, '/');
+    let day = parts
+        .next()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .ok_or_else(invalid)?;
+    let start_at = parts
+        .next()
+        .and_then(|t| t.parse::<i64>().ok())
+        .ok_or_else(invalid)?;
+    let id = parts.next().ok_or_else(invalid)?;
+    Ok((day, start_at, id.to_string()))
+}
+
 fn day_key(date: NaiveDate) -> String {
     date.format("%Y-%m-%d").to_string()
 }
 
-fn day_mermaid_path(archive: &Path, date: NaiveDate) -> PathBuf {
-    archive
-        .join("graphs")
-        .join(format!("{}.mmd", day_key(date)))
+pub fn graphs_dir(archive: &Path) -> PathBuf {
+    archive.join("graphs")
 }
 
-fn next_session_id(nodes: &[Session], kind: SessionKind) -> String {
-    let counter = nodes.iter().filter(|n| n.kind == kind).count() + 1;
-    match kind {
-        SessionKind::Goal => format!("sess_{counter}"),
-        SessionKind::Reward => format!("rew_{counter}"),
-    }
+pub fn day_mermaid_path(archive: &Path, date: NaiveDate) -> PathBuf {
+    graphs_dir(archive).join(format!("{}.mmd", day_key(date)))
 }
 
-fn parse_mermaid(content: &str, date: NaiveDate) -> Result<Vec<Session>, AppError> {
+/// Parse the date out of a day graph file name such as `2024-05-01.mmd`.
+pub fn parse_day_file_name(name: &str) -> Option<NaiveDate> {
+    let stem = name.strip_suffix(".mmd")?;
+    NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok()
+}
+
+pub fn next_session_id(nodes: &[Session], kind: SessionKind) -> String {
+    let prefix = match kind {
+        SessionKind::Goal => "sess_",
+        SessionKind::Reward => "rew_",
+    };
+    // Use the highest existing counter rather than the count, so ids stay
+    // unique once merged sessions have been removed from the day.
+    let counter = nodes
+        .iter()
+        .filter_map(|n| n.id.strip_prefix(prefix)?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    format!("{prefix}{counter}")
+}
+
+/// Sessions of a day graph in chain order, as `list_day_sessions` sees them.
+pub fn parse_mermaid(content: &str, date: NaiveDate) -> Result<Vec<Session>, AppError> {
     let mut nodes = Vec::new();
     let mut labels = HashMap::new();
     let mut edges: Vec<(String, String)> = Vec::new();
@@ -202,42 +645,76 @@ fn parse_mermaid(content: &str, date: NaiveDate) -> Result<Vec<Session>, AppErro
 
     let mut cursor = start;
     while let Some(id) = cursor {
-        if let Some(label) = labels.get(&id) {
-            let (name, goal_id, quantity, explicit_time) = split_label(label, date);
-            let clean_id = sanitize_id(&id);
-            let kind = if clean_id.starts_with("rew_") {
-                SessionKind::Reward
-            } else {
-                SessionKind::Goal
-            };
-
-            if let Some((start_at, end_at)) = explicit_time {
-                nodes.push(Session {
-                    id: clean_id,
-                    name,
-                    goal_id,
-                    kind,
-                    quantity,
-                    start_at: start_at.timestamp(),
-                    end_at: end_at.timestamp(),
-                });
-            }
+        if let Some(session) = labels
+            .get(&id)
+            .and_then(|l| session_from_label(&id, l, date))
+        {
+            nodes.push(session);
         }
         cursor = outgoing.get(&id).cloned();
     }
     Ok(nodes)
 }
 
-#[allow(clippy::type_complexity)]
-fn split_label(
-    label: &str,
-    date: NaiveDate,
-) -> (
-    String,
-    u64,
-    Option<u32>,
-    Option<(DateTime<Utc>, DateTime<Utc>)>,
-) {
+/// Every labelled node of a day graph in file order, whether or not it is
+/// reachable through the `-->` chain and including repeated ids. Nodes
+/// whose label has no valid time range come back as `None`.
+///
+/// `parse_mermaid` is what the rest of the library sees; this is for
+/// finding what it would silently skip.
+pub fn parse_day_nodes(content: &str, date: NaiveDate) -> Vec<(String, Option<Session>)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.contains("-->"))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(id, label)| {
+            let id = sanitize_id(id.trim());
+            let session = session_from_label(&id, label.trim(), date);
+            (id, session)
+        })
+        .collect()
+}
+
+fn session_from_label(id: &str, label: &str, date: NaiveDate) -> Option<Session> {
+    let parsed = split_label(label, date);
+    let (start_at, end_at) = parsed.time_range?;
+    let id = sanitize_id(id);
+    let kind = if id.starts_with("rew_") {
+        SessionKind::Reward
+    } else {
+        SessionKind::Goal
+    };
+    let note_path = parsed
+        .has_note
+        .then(|| session_note_relative_path(date, &id));
+    Some(Session {
+        id,
+        name: parsed.name,
+        goal_id: parsed.goal_id,
+        kind,
+        quantity: parsed.quantity,
+        start_at: start_at.timestamp(),
+        end_at: end_at.timestamp(),
+        rating: parsed.rating,
+        tags: parsed.tags,
+        note_path,
+    })
+}
+
+/// The pieces encoded in a mermaid node label, e.g.
+/// `Study [rating 4] [tags deep, anki] [note] [id 3] [q 20] [10#colon;00-10#colon;25]`.
+struct ParsedLabel {
+    name: String,
+    goal_id: u64,
+    quantity: Option<u32>,
+    rating: Option<u8>,
+    tags: Vec<String>,
+    has_note: bool,
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+fn split_label(label: &str, date: NaiveDate) -> ParsedLabel {
     let (without_time, time_range) = match label.rsplit_once('[') {
         Some((head, tail)) => (
             head.trim(),
@@ -246,14 +723,17 @@ fn split_label(
         None => (label.trim(), None),
     };
 
-    let mut goal_id = 0;
-    let mut quantity = None;
-    let mut name = without_time.trim().to_string();
+    let mut parsed = ParsedLabel {
+        name: without_time.trim().to_string(),
+        goal_id: 0,
+        quantity: None,
+        rating: None,
+        tags: Vec::new(),
+        has_note: false,
+        time_range,
+    };
 
-    loop {
-        let Some((head, tail)) = name.rsplit_once('[') else {
-            break;
-        };
+    while let Some((head, tail)) = parsed.name.rsplit_once('[') {
         let tag = tail.trim_end_matches(']').trim();
         if let Some(id_tail) = tag.strip_prefix("id") {
             if let Ok(id_val) = id_tail
@@ -261,8 +741,8 @@ fn split_label(
                 .trim_start_matches(|c: char| c == ':' || c.is_whitespace())
                 .parse::<u64>()
             {
-                goal_id = id_val;
-                name = head.trim().to_string();
+                parsed.goal_id = id_val;
+                parsed.name = head.trim().to_string();
                 continue;
             }
         }
@@ -272,15 +752,36 @@ fn split_label(
                 .trim_start_matches(|c: char| c == ':' || c.is_whitespace())
                 .parse::<u32>()
             {
-                quantity = Some(q_val);
-                name = head.trim().to_string();
+                parsed.quantity = Some(q_val);
+                parsed.name = head.trim().to_string();
                 continue;
             }
         }
+        if let Some(r_tail) = tag.strip_prefix("rating") {
+            if let Ok(r_val) = r_tail.trim().parse::<u8>() {
+                parsed.rating = Some(r_val);
+                parsed.name = head.trim().to_string();
+                continue;
+            }
+        }
+        if let Some(t_tail) = tag.strip_prefix("tags ") {
+            parsed.tags = t_tail
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect();
+            parsed.name = head.trim().to_string();
+            continue;
+        }
+        if tag == "note" {
+            parsed.has_note = true;
+            parsed.name = head.trim().to_string();
+            continue;
+        }
         break;
     }
 
-    (name, goal_id, quantity, time_range)
+    parsed
 }
 
 fn parse_time_range(range: &str, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
@@ -327,9 +828,21 @@ fn to_mermaid(nodes: &[Session]) -> String {
             .quantity
             .map(|v| format!(" [q {}]", v))
             .unwrap_or_default();
+        // Metadata goes before `[id N]` so older readers, which stop at the
+        // first tag they do not know, still recover the goal id and quantity.
+        let mut meta = String::new();
+        if let Some(rating) = n.rating {
+            meta.push_str(&format!(" [rating {rating}]"));
+        }
+        if !n.tags.is_empty() {
+            meta.push_str(&format!(" [tags {}]", n.tags.join(", ")));
+        }
+        if n.note_path.is_some() {
+            meta.push_str(" [note]");
+        }
         out.push_str(&format!(
-            "    {}: {} [id {}]{} [{}]\n",
-            n.id, n.name, n.goal_id, qty, times
+            "    {}: {}{} [id {}]{} [{}]\n",
+            n.id, n.name, meta, n.goal_id, qty, times
         ));
         if let Some(next) = nodes.get(i + 1) {
             out.push_str(&format!("    {} --> {}\n", n.id, next.id));
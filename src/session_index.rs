@@ -0,0 +1,253 @@
+//! Derived index over the day graphs stored in `graphs/`.
+//!
+//! The index lives in `cache/session_index.json` and only mirrors data that
+//! is already present in the graph files, so it can be deleted at any time
+//! and is rebuilt on the next query that needs it. Each day entry remembers
+//! the fingerprint (mtime or content hash) of its graph file, so edits made
+//! by other apps or by hand are picked up incrementally.
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ffi_types::AppError;
+use crate::session_graph::{day_mermaid_path, graphs_dir, list_day_sessions, parse_day_file_name};
+use crate::storage_io;
+use crate::types::{Session, SessionKind};
+
+/// Bump when the layout of the index changes; older indexes are rebuilt.
+const INDEX_VERSION: u32 = 3;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionIndex {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub days: BTreeMap<NaiveDate, DayEntry>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DayEntry {
+    #[serde(default)]
+    pub fingerprint: String,
+    #[serde(default)]
+    pub session_ids: Vec<String>,
+    #[serde(default)]
+    pub goal_secs: i64,
+    #[serde(default)]
+    pub reward_secs: i64,
+    /// Per-goal stats for the day, keyed by goal id.
+    #[serde(default)]
+    pub goals: BTreeMap<u64, GoalDayStats>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GoalDayStats {
+    pub sessions: u32,
+    pub secs: i64,
+    pub last_start_at: i64,
+    /// Distinct session names of the day, compared without case, each with
+    /// its latest session.
+    #[serde(default)]
+    pub names: Vec<SessionName>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionName {
+    pub name: String,
+    pub session_id: String,
+    pub start_at: i64,
+}
+
+impl DayEntry {
+    fn from_sessions(fingerprint: String, sessions: &[Session]) -> Self {
+        let mut entry = DayEntry {
+            fingerprint,
+            ..Default::default()
+        };
+        for session in sessions {
+            let secs = (session.end_at - session.start_at).max(0);
+            match session.kind {
+                SessionKind::Goal => entry.goal_secs += secs,
+                SessionKind::Reward => entry.reward_secs += secs,
+            }
+            entry.session_ids.push(session.id.clone());
+            let stats = entry.goals.entry(session.goal_id).or_default();
+            stats.sessions += 1;
+            stats.secs += secs;
+            stats.last_start_at = stats.last_start_at.max(session.start_at);
+
+            let name = session.name.trim();
+            let latest = SessionName {
+                name: name.to_string(),
+                session_id: session.id.clone(),
+                start_at: session.start_at,
+            };
+            match stats
+                .names
+                .iter_mut()
+                .find(|n| n.name.to_lowercase() == name.to_lowercase())
+            {
+                Some(known) if known.start_at < session.start_at => *known = latest,
+                Some(_) => {}
+                None => stats.names.push(latest),
+            }
+        }
+        entry
+    }
+}
+
+impl SessionIndex {
+    /// Days with at least one session for `goal_id`, oldest first.
+    pub fn days_for_goal(&self, goal_id: u64) -> Vec<NaiveDate> {
+        self.days
+            .iter()
+            .filter(|(_, entry)| entry.goals.contains_key(&goal_id))
+            .map(|(day, _)| *day)
+            .collect()
+    }
+
+    /// Days with at least one session, oldest first.
+    pub fn recorded_days(&self) -> Vec<NaiveDate> {
+        self.days
+            .iter()
+            .filter(|(_, entry)| !entry.session_ids.is_empty())
+            .map(|(day, _)| *day)
+            .collect()
+    }
+
+    /// Days with data between `start` and `end` (inclusive), oldest first.
+    pub fn days_between(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        if start > end {
+            return vec![];
+        }
+        self.days.range(start..=end).map(|(day, _)| *day).collect()
+    }
+
+    /// Start of the most recent session of every goal, keyed by goal id,
+    /// optionally restricted to days between `range.0` and `range.1`.
+    pub fn last_activity(&self, range: Option<(NaiveDate, NaiveDate)>) -> HashMap<u64, i64> {
+        let (start, end) = range.unwrap_or((NaiveDate::MIN, NaiveDate::MAX));
+        let mut last = HashMap::new();
+        if start > end {
+            return last;
+        }
+        for entry in self.days.range(start..=end).map(|(_, entry)| entry) {
+            for (goal_id, stats) in &entry.goals {
+                let ts = last.entry(*goal_id).or_insert(stats.last_start_at);
+                *ts = (*ts).max(stats.last_start_at);
+            }
+        }
+        last
+    }
+}
+
+fn index_path(archive: &Path) -> PathBuf {
+    archive.join("cache").join("session_index.json")
+}
+
+fn read_index(archive: &Path) -> Result<Option<SessionIndex>, AppError> {
+    let Some(data) = storage_io::read_to_string(archive, &index_path(archive))? else {
+        return Ok(None);
+    };
+    // A corrupt or outdated cache is not an error: it is simply rebuilt.
+    Ok(serde_json::from_str::<SessionIndex>(&data)
+        .ok()
+        .filter(|index| index.version == INDEX_VERSION))
+}
+
+fn write_index(archive: &Path, index: &SessionIndex) -> Result<(), AppError> {
+    let data = serde_json::to_string(index)?;
+    storage_io::write_string(archive, &index_path(archive), &data)?;
+    Ok(())
+}
+
+/// Load the index and bring it up to date with `graphs/`.
+pub fn load_index(archive: &Path) -> Result<SessionIndex, AppError> {
+    load_index_within(archive, None)
+}
+
+/// Like `load_index`, but only re-checks fingerprints of days within
+/// `range`. New and deleted day files are always picked up.
+pub fn load_index_within(
+    archive: &Path,
+    range: Option<(NaiveDate, NaiveDate)>,
+) -> Result<SessionIndex, AppError> {
+    let mut index = read_index(archive)?.unwrap_or_else(|| SessionIndex {
+        version: INDEX_VERSION,
+        days: BTreeMap::new(),
+    });
+    let mut changed = false;
+
+    let mut on_disk = Vec::new();
+    for name in storage_io::list_dir(archive, &graphs_dir(archive))? {
+        if let Some(day) = parse_day_file_name(&name) {
+            on_disk.push(day);
+        }
+    }
+
+    let before = index.days.len();
+    index
+        .days
+        .retain(|day, _| on_disk.binary_search(day).is_ok());
+    changed |= index.days.len() != before;
+
+    for day in on_disk {
+        let known = index.days.get(&day);
+        let in_range = range.is_none_or(|(start, end)| start <= day && day <= end);
+        if known.is_some() && !in_range {
+            continue;
+        }
+        let Some(fingerprint) = storage_io::fingerprint(archive, &day_mermaid_path(archive, day))?
+        else {
+            continue;
+        };
+        if known.is_some_and(|entry| entry.fingerprint == fingerprint) {
+            continue;
+        }
+        let sessions = list_day_sessions(archive, day)?;
+        index
+            .days
+            .insert(day, DayEntry::from_sessions(fingerprint, &sessions));
+        changed = true;
+    }
+
+    if changed {
+        write_index(archive, &index)?;
+    }
+    Ok(index)
+}
+
+/// Refresh the entry for `day` after its graph has been rewritten or
+/// deleted.
+///
+/// Inside `storage_io::staged` this waits for the graph to be stored, so the
+/// entry gets the fingerprint the next query will see. Does nothing when no
+/// index exists yet; it will be built on first use.
+pub fn record_day(archive: &Path, day: NaiveDate, sessions: &[Session]) {
+    let archive = archive.to_path_buf();
+    let sessions = sessions.to_vec();
+    storage_io::after_commit(move || {
+        if let Err(err) = record_day_now(&archive, day, &sessions) {
+            eprintln!("Failed to update session index: {err}");
+        }
+    });
+}
+
+fn record_day_now(archive: &Path, day: NaiveDate, sessions: &[Session]) -> Result<(), AppError> {
+    let Some(mut index) = read_index(archive)? else {
+        return Ok(());
+    };
+    match storage_io::fingerprint(archive, &day_mermaid_path(archive, day))? {
+        Some(fingerprint) => {
+            index
+                .days
+                .insert(day, DayEntry::from_sessions(fingerprint, sessions));
+        }
+        None => {
+            index.days.remove(&day);
+        }
+    }
+    write_index(archive, &index)
+}
@@ -0,0 +1,249 @@
+//! Snapshots of a whole archive.
+//!
+//! A snapshot is a single JSON bundle in `snapshots/<id>.json` holding the
+//! content of every archive file: the manifest, `goals.yaml`, the day
+//! graphs and all notes. Derived data (`cache/`), migration backups and
+//! other snapshots are left out. Being one storage entry, a bundle works
+//! the same on the filesystem and in browser storage, where it is the only
+//! way for web users to keep a copy of their data.
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{Duration, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{self, ArchiveEvent};
+use crate::ffi_types::AppError;
+use crate::manifest;
+use crate::storage_io;
+use crate::types::{RetentionPolicy, SnapshotInfo};
+
+/// Bump when the layout of a bundle changes.
+const BUNDLE_FORMAT: u32 = 1;
+
+/// The archive manifest, see `manifest`.
+const MANIFEST: &str = "success.yaml";
+
+/// Files at the root of the archive that belong in a snapshot.
+const ROOT_FILES: [&str; 2] = [MANIFEST, "goals.yaml"];
+
+/// Directories whose whole content belongs in a snapshot.
+const DATA_DIRS: [&str; 3] = ["graphs", "notes", "templates"];
+
+/// The content of an archive at one point in time. Snapshots and
+/// exported archives share this layout.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: u32,
+    pub created_at: i64,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// File contents keyed by archive-relative path.
+    pub files: BTreeMap<String, String>,
+}
+
+impl Bundle {
+    /// Read the archive's current files into a bundle.
+    pub fn capture(archive: &Path, label: Option<String>) -> Result<Self, AppError> {
+        let mut files = BTreeMap::new();
+        for relative in archive_files(archive)? {
+            if let Some(content) = storage_io::read_to_string(archive, &archive.join(&relative))? {
+                files.insert(relative, content);
+            }
+        }
+        Ok(Bundle {
+            format: BUNDLE_FORMAT,
+            created_at: Local::now().timestamp(),
+            label,
+            files,
+        })
+    }
+
+    /// Parse and validate a bundle; `name` identifies it in errors.
+    pub fn parse(data: &str, name: &str) -> Result<Self, AppError> {
+        let bundle: Bundle = serde_json::from_str(data)?;
+        if bundle.format > BUNDLE_FORMAT {
+            return Err(AppError::Parse {
+                detail: format!("{name} uses unknown bundle format {}", bundle.format),
+            });
+        }
+        if let Some(path) = bundle.files.keys().find(|p| !is_archive_file(p)) {
+            return Err(AppError::Parse {
+                detail: format!("{name} contains a file outside the archive: {path}"),
+            });
+        }
+        if let Some(content) = bundle.files.get("success.yaml") {
+            manifest::check_supported(manifest::parse_schema_version(content)?)?;
+        }
+        Ok(bundle)
+    }
+
+    /// Make the archive's files match the bundle, deleting the ones it
+    /// does not contain, and return the resulting events. The archive keeps
+    /// its manifest unless the bundle comes with one: bundles made before
+    /// the manifest existed have none.
+    pub fn replace_archive_files(&self, archive: &Path) -> Result<Vec<ArchiveEvent>, AppError> {
+        let mut events = Vec::new();
+        for relative in archive_files(archive)? {
+            if self.files.contains_key(&relative) || relative == MANIFEST {
+                continue;
+            }
+            let path = archive.join(&relative);
+            let old = storage_io::read_to_string(archive, &path)?;
+            storage_io::delete(archive, &path)?;
+            events.extend(events::file_events(&relative, old.as_deref(), None));
+        }
+        for (relative, content) in &self.files {
+            let path = archive.join(relative);
+            let old = storage_io::read_to_string(archive, &path)?;
+            if old.as_deref() == Some(content.as_str()) {
+                continue;
+            }
+            storage_io::write_string(archive, &path, content)?;
+            events.extend(events::file_events(relative, old.as_deref(), Some(content)));
+        }
+        Ok(events)
+    }
+}
+
+fn snapshots_dir(archive: &Path) -> PathBuf {
+    archive.join("snapshots")
+}
+
+fn bundle_path(archive: &Path, id: &str) -> Result<PathBuf, AppError> {
+    // Ids are generated by `create`; anything else could escape the
+    // snapshots directory.
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(AppError::NotFound {
+            resource: "snapshot".into(),
+            id: id.to_string(),
+        });
+    }
+    Ok(snapshots_dir(archive).join(format!("{id}.json")))
+}
+
+/// Archive-relative paths of the user data in `archive`: the root files
+/// that exist and everything below `graphs/`, `notes/` and `templates/`.
+pub fn archive_files(archive: &Path) -> Result<Vec<String>, AppError> {
+    let mut files = Vec::new();
+    for name in ROOT_FILES {
+        if storage_io::exists(archive, &archive.join(name))? {
+            files.push(name.to_string());
+        }
+    }
+    for dir in DATA_DIRS {
+        for name in storage_io::list_files(archive, &archive.join(dir))? {
+            files.push(format!("{dir}/{name}"));
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `relative` is a path `archive_files` could have returned.
+pub fn is_archive_file(relative: &str) -> bool {
+    let normal = Path::new(relative)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    normal
+        && (ROOT_FILES.contains(&relative)
+            || DATA_DIRS.iter().any(|dir| {
+                relative
+                    .strip_prefix(dir)
+                    .is_some_and(|r| r.starts_with('/'))
+            }))
+}
+
+fn info(id: &str, bundle: &Bundle, size_bytes: usize) -> SnapshotInfo {
+    SnapshotInfo {
+        id: id.to_string(),
+        created_at: bundle.created_at,
+        label: bundle.label.clone(),
+        file_count: bundle.files.len() as u32,
+        size_bytes: size_bytes as u64,
+    }
+}
+
+fn read_bundle(archive: &Path, id: &str) -> Result<(Bundle, usize), AppError> {
+    let Some(data) = storage_io::read_to_string(archive, &bundle_path(archive, id)?)? else {
+        return Err(AppError::NotFound {
+            resource: "snapshot".into(),
+            id: id.to_string(),
+        });
+    };
+    let bundle = Bundle::parse(&data, &format!("snapshot {id}"))?;
+    Ok((bundle, data.len()))
+}
+
+pub fn create(archive: &Path, label: Option<String>) -> Result<SnapshotInfo, AppError> {
+    let now = Local::now();
+    let bundle = Bundle::capture(archive, label)?;
+
+    // Ids sort in creation order, also for snapshots taken within the same
+    // millisecond: those get an increasing suffix.
+    let base = now.format("%Y%m%d-%H%M%S-%3f").to_string();
+    let taken = storage_io::list_dir(archive, &snapshots_dir(archive))?
+        .iter()
+        .filter_map(|name| name.strip_suffix(".json")?.strip_prefix(&base))
+        .map(|suffix| suffix.trim_start_matches('-').parse::<u32>().unwrap_or(0))
+        .max();
+    let id = match taken {
+        Some(n) => format!("{base}-{:03}", n + 1),
+        None => base,
+    };
+    let data = serde_json::to_string(&bundle)?;
+    storage_io::write_string(archive, &bundle_path(archive, &id)?, &data)?;
+    Ok(info(&id, &bundle, data.len()))
+}
+
+/// All readable snapshots, newest first.
+pub fn list(archive: &Path) -> Result<Vec<SnapshotInfo>, AppError> {
+    let mut snapshots = Vec::new();
+    for name in storage_io::list_dir(archive, &snapshots_dir(archive))? {
+        let Some(id) = name.strip_suffix(".json") else {
+            continue;
+        };
+        // A damaged bundle should not hide the others.
+        if let Ok((bundle, size)) = read_bundle(archive, id) {
+            snapshots.push(info(id, &bundle, size));
+        }
+    }
+    snapshots.sort_by(|a, b| (b.created_at, &b.id).cmp(&(a.created_at, &a.id)));
+    Ok(snapshots)
+}
+
+/// Delete the snapshots `policy` does not keep and return their ids.
+pub fn prune(archive: &Path, policy: &RetentionPolicy) -> Result<Vec<String>, AppError> {
+    if policy.keep_last.is_none() && policy.keep_days.is_none() {
+        return Ok(vec![]);
+    }
+    let cutoff = policy
+        .keep_days
+        .map(|days| (Local::now() - Duration::days(i64::from(days))).timestamp());
+    let mut removed = Vec::new();
+    for (rank, snapshot) in list(archive)?.into_iter().enumerate() {
+        let recent_enough = policy.keep_last.is_some_and(|n| rank < n as usize);
+        let young_enough = cutoff.is_some_and(|cutoff| snapshot.created_at > cutoff);
+        if recent_enough || young_enough {
+            continue;
+        }
+        storage_io::delete(archive, &bundle_path(archive, &snapshot.id)?)?;
+        removed.push(snapshot.id);
+    }
+    Ok(removed)
+}
+
+/// Replace the archive's files with those of snapshot `id`.
+///
+/// The current state is snapshotted first, so a restore can be undone;
+/// that snapshot is returned along with the events describing the change.
+pub fn restore(archive: &Path, id: &str) -> Result<(SnapshotInfo, Vec<ArchiveEvent>), AppError> {
+    let (bundle, _) = read_bundle(archive, id)?;
+    let created = Local
+        .timestamp_opt(bundle.created_at, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| id.to_string());
+    let safety = create(archive, Some(format!("Before restoring {created}")))?;
+    let events = bundle.replace_archive_files(archive)?;
+    Ok((safety, events))
+}
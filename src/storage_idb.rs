@@ -0,0 +1,272 @@
+//! IndexedDB backend for the web target.
+//!
+//! The rest of the library is synchronous, while IndexedDB only offers
+//! asynchronous requests. An archive is therefore *mounted* once with
+//! `mount`, which loads all of its files from IndexedDB into memory. Reads
+//! are then served from memory, and writes update memory immediately and
+//! are persisted in the background; `flush` waits for them.
+//!
+//! Other tabs that mounted the same archive are told about writes over a
+//! `BroadcastChannel`, so their in-memory copies stay current.
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbRequest, IdbTransactionMode};
+
+use crate::storage_io::{is_archive_key, StorageIoError, StorageIoResult};
+
+const DB_NAME: &str = "successlib";
+const DB_VERSION: u32 = 1;
+const STORE: &str = "files";
+const CHANNEL: &str = "successlib";
+
+/// Called with `(key, old_value, new_value)` for writes made by other tabs.
+pub type ChangeHook = Rc<dyn Fn(&str, Option<String>, Option<String>)>;
+
+#[derive(Default)]
+struct State {
+    db: Option<IdbDatabase>,
+    channel: Option<web_sys::BroadcastChannel>,
+    /// Key prefixes (`{archive}_`) of the mounted archives.
+    mounted: HashSet<String>,
+    files: HashMap<String, String>,
+    pending: Vec<js_sys::Promise>,
+    hooks: Vec<ChangeHook>,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+fn unavailable(_: JsValue) -> StorageIoError {
+    StorageIoError::StorageUnavailable
+}
+
+/// Resolve once `request` succeeds, with its result.
+async fn await_request(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let done = request.clone();
+        let on_success = Closure::once_into_js(move |_: web_sys::Event| {
+            let result = done.result().unwrap_or(JsValue::UNDEFINED);
+            let _ = resolve.call1(&JsValue::NULL, &result);
+        });
+        let on_error = Closure::once_into_js(move |_: web_sys::Event| {
+            let _ = reject.call1(&JsValue::NULL, &"IndexedDB request failed".into());
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
+
+async fn open_db() -> StorageIoResult<IdbDatabase> {
+    if let Some(db) = STATE.with(|s| s.borrow().db.clone()) {
+        return Ok(db);
+    }
+    let window = web_sys::window().ok_or(StorageIoError::StorageUnavailable)?;
+    let factory = window
+        .indexed_db()
+        .map_err(unavailable)?
+        .ok_or(StorageIoError::StorageUnavailable)?;
+    let request = factory
+        .open_with_u32(DB_NAME, DB_VERSION)
+        .map_err(unavailable)?;
+    let on_upgrade = Closure::once_into_js({
+        let request = request.clone();
+        move |_: web_sys::Event| {
+            if let Ok(db) = request.result() {
+                let _ = db
+                    .unchecked_into::<IdbDatabase>()
+                    .create_object_store(STORE);
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+    let db: IdbDatabase = await_request(&request)
+        .await
+        .map_err(unavailable)?
+        .unchecked_into();
+
+    STATE.with(|s| s.borrow_mut().db = Some(db.clone()));
+    listen_to_other_tabs();
+    Ok(db)
+}
+
+fn listen_to_other_tabs() {
+    let Ok(channel) = web_sys::BroadcastChannel::new(CHANNEL) else {
+        return;
+    };
+    let on_message =
+        Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |event: web_sys::MessageEvent| {
+            let message: js_sys::Array = event.data().unchecked_into();
+            let Some(key) = message.get(0).as_string() else {
+                return;
+            };
+            let new = message.get(1).as_string();
+            let (old, hooks) = STATE.with(|s| {
+                let mut state = s.borrow_mut();
+                if !state.mounted.iter().any(|p| is_archive_key(p, &key)) {
+                    return (None, vec![]);
+                }
+                let old = match &new {
+                    Some(value) => state.files.insert(key.clone(), value.clone()),
+                    None => state.files.remove(&key),
+                };
+                (old, state.hooks.clone())
+            });
+            for hook in hooks {
+                hook(&key, old.clone(), new.clone());
+            }
+        });
+    channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+    STATE.with(|s| s.borrow_mut().channel = Some(channel));
+}
+
+/// Load every file of the archive whose keys start with `prefix` into
+/// memory, migrating entries still stored in localStorage. Keys of other
+/// archives sharing the prefix are left alone.
+pub async fn mount(prefix: &str, local_storage: &web_sys::Storage) -> StorageIoResult<()> {
+    let db = open_db().await?;
+    let store = db
+        .transaction_with_str(STORE)
+        .and_then(|tx| tx.object_store(STORE))
+        .map_err(unavailable)?;
+    // U+FFFF sorts after every character that can appear in a key.
+    let range = web_sys::IdbKeyRange::bound(
+        &JsValue::from_str(prefix),
+        &JsValue::from_str(&format!("{prefix}\u{ffff}")),
+    )
+    .map_err(unavailable)?;
+    let keys_request = store.get_all_keys_with_key(&range).map_err(unavailable)?;
+    let values_request = store.get_all_with_key(&range).map_err(unavailable)?;
+    let keys: js_sys::Array = await_request(&keys_request)
+        .await
+        .map_err(unavailable)?
+        .unchecked_into();
+    let values: js_sys::Array = await_request(&values_request)
+        .await
+        .map_err(unavailable)?
+        .unchecked_into();
+
+    let mut files = HashMap::new();
+    for (key, value) in keys.iter().zip(values.iter()) {
+        if let (Some(key), Some(value)) = (key.as_string(), value.as_string()) {
+            // The range also holds archives whose name starts with ours.
+            if is_archive_key(prefix, &key) {
+                files.insert(key, value);
+            }
+        }
+    }
+
+    // Move the archive's localStorage entries over, unless IndexedDB
+    // already has a copy.
+    let mut legacy = Vec::new();
+    let len = local_storage.length().map_err(unavailable)?;
+    for i in 0..len {
+        if let Ok(Some(key)) = local_storage.key(i) {
+            if is_archive_key(prefix, &key) {
+                legacy.push(key);
+            }
+        }
+    }
+    STATE.with(|s| {
+        let mut state = s.borrow_mut();
+        state.files.extend(files.clone());
+        state.mounted.insert(prefix.to_string());
+    });
+    for key in &legacy {
+        if files.contains_key(key) {
+            continue;
+        }
+        if let Ok(Some(value)) = local_storage.get_item(key) {
+            set(key, &value);
+        }
+    }
+    flush().await?;
+    for key in legacy {
+        let _ = local_storage.remove_item(&key);
+    }
+    Ok(())
+}
+
+pub fn is_mounted(prefix: &str) -> bool {
+    STATE.with(|s| s.borrow().mounted.contains(prefix))
+}
+
+pub fn get(key: &str) -> Option<String> {
+    STATE.with(|s| s.borrow().files.get(key).cloned())
+}
+
+/// Store `value` under `key` in memory and persist it in the background.
+pub fn set(key: &str, value: &str) {
+    STATE.with(|s| {
+        let mut state = s.borrow_mut();
+        state.files.insert(key.to_string(), value.to_string());
+        persist(&mut state, key, Some(value));
+    });
+}
+
+/// Remove `key` from memory and, in the background, from IndexedDB.
+/// Returns whether it existed.
+pub fn remove(key: &str) -> bool {
+    STATE.with(|s| {
+        let mut state = s.borrow_mut();
+        let existed = state.files.remove(key).is_some();
+        persist(&mut state, key, None);
+        existed
+    })
+}
+
+/// Write `value` (or a deletion) to IndexedDB and tell the other tabs.
+fn persist(state: &mut State, key: &str, value: Option<&str>) {
+    let Some(db) = state.db.clone() else {
+        return;
+    };
+    let request = db
+        .transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)
+        .and_then(|tx| tx.object_store(STORE))
+        .and_then(|store| match value {
+            Some(value) => store.put_with_key(&value.into(), &key.into()),
+            None => store.delete(&key.into()),
+        });
+    if let Ok(request) = request {
+        let promise =
+            wasm_bindgen_futures::future_to_promise(async move { await_request(&request).await });
+        state.pending.push(promise);
+    }
+    if let Some(channel) = &state.channel {
+        let value = value.map_or(JsValue::NULL, JsValue::from_str);
+        let message = js_sys::Array::of2(&key.into(), &value);
+        let _ = channel.post_message(&message);
+    }
+}
+
+/// Keys starting with `prefix`, in no particular order.
+pub fn keys_with_prefix(prefix: &str) -> Vec<String> {
+    STATE.with(|s| {
+        s.borrow()
+            .files
+            .keys()
+            .filter(|k| k.starts_with(prefix))
+            .cloned()
+            .collect()
+    })
+}
+
+/// Wait until every write made so far has been persisted.
+pub async fn flush() -> StorageIoResult<()> {
+    let pending: js_sys::Array = STATE.with(|s| s.borrow_mut().pending.drain(..).collect());
+    JsFuture::from(js_sys::Promise::all(&pending))
+        .await
+        .map_err(unavailable)?;
+    Ok(())
+}
+
+pub fn add_change_hook(hook: ChangeHook) {
+    STATE.with(|s| s.borrow_mut().hooks.push(hook));
+}
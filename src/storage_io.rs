@@ -1,4 +1,6 @@
-use std::path::Path;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
@@ -12,6 +14,18 @@ pub enum StorageIoError {
     InvalidUtf8Path,
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
+    /// Storing staged files failed and some of the files already stored
+    /// could not be put back either.
+    #[error("{error}; could not restore {}", display_paths(.unrestored))]
+    PartialCommit {
+        error: Box<StorageIoError>,
+        unrestored: Vec<PathBuf>,
+    },
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    paths.join(", ")
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -32,27 +46,191 @@ fn storage_key(prefix: &Path, path: &Path) -> StorageIoResult<String> {
     Ok(format!("{prefix}_{normalized}"))
 }
 
+/// Prefix shared by every key of `archive`, and the name it is mounted
+/// under in the IndexedDB backend.
 #[cfg(target_arch = "wasm32")]
-pub fn read_to_string(archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
-    let storage = local_storage()?;
+fn archive_prefix(archive: &Path) -> StorageIoResult<String> {
+    let prefix = archive.to_str().ok_or(StorageIoError::InvalidUtf8Path)?;
+    Ok(format!("{prefix}_"))
+}
+
+/// Whether `archive` has been moved to IndexedDB with `mount_indexed_db`.
+#[cfg(target_arch = "wasm32")]
+fn is_indexed_db(archive: &Path) -> bool {
+    archive_prefix(archive).is_ok_and(|p| crate::storage_idb::is_mounted(&p))
+}
+
+/// Serve `archive` from IndexedDB instead of localStorage from now on,
+/// migrating its existing localStorage entries.
+#[cfg(target_arch = "wasm32")]
+pub async fn mount_indexed_db(archive: &Path) -> StorageIoResult<()> {
+    let prefix = archive_prefix(archive)?;
+    if crate::storage_idb::is_mounted(&prefix) {
+        return Ok(());
+    }
+    crate::storage_idb::mount(&prefix, &local_storage()?).await
+}
+
+/// Files stored at the root of an archive.
+#[cfg(target_arch = "wasm32")]
+const ROOT_FILES: [&str; 2] = ["goals.yaml", "success.yaml"];
+
+/// Directories of an archive.
+#[cfg(target_arch = "wasm32")]
+const ARCHIVE_DIRS: [&str; 7] = [
+    "graphs",
+    "notes",
+    "templates",
+    "snapshots",
+    "cache",
+    "backups",
+    "journal",
+];
+
+/// Whether `key` stores a file of the archive whose keys start with
+/// `prefix` (`{archive}_`). Keys of an archive whose name merely starts the
+/// same, such as `{archive}_bar_goals.yaml`, do not.
+#[cfg(target_arch = "wasm32")]
+pub fn is_archive_key(prefix: &str, key: &str) -> bool {
+    let Some(rest) = key.strip_prefix(prefix) else {
+        return false;
+    };
+    ROOT_FILES.contains(&rest)
+        || ARCHIVE_DIRS.iter().any(|dir| {
+            rest.strip_prefix(dir)
+                .is_some_and(|name| name.len() > 2 && name.starts_with("__"))
+        })
+}
+
+/// Map a storage key back to the archive-relative path it stores, or
+/// `None` if the key belongs to another archive.
+#[cfg(target_arch = "wasm32")]
+pub fn relative_path_for_key(archive: &Path, key: &str) -> Option<String> {
+    let prefix = archive_prefix(archive).ok()?;
+    if !is_archive_key(&prefix, key) {
+        return None;
+    }
+    Some(key[prefix.len()..].replace("__", "/"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn backend_read_to_string(archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
     let key = storage_key(archive, path)?;
-    Ok(storage
+    if is_indexed_db(archive) {
+        return Ok(crate::storage_idb::get(&key));
+    }
+    let storage = local_storage()?;
+    storage
         .get_item(&key)
-        .map_err(|_| StorageIoError::StorageUnavailable)?)
+        .map_err(|_| StorageIoError::StorageUnavailable)
 }
 
 #[cfg(target_arch = "wasm32")]
-pub fn write_string(archive: &Path, path: &Path, content: &str) -> StorageIoResult<()> {
-    let storage = local_storage()?;
+fn backend_write_string(archive: &Path, path: &Path, content: &str) -> StorageIoResult<()> {
     let key = storage_key(archive, path)?;
+    if is_indexed_db(archive) {
+        crate::storage_idb::set(&key, content);
+        return Ok(());
+    }
+    let storage = local_storage()?;
     storage
         .set_item(&key, content)
         .map_err(|_| StorageIoError::StorageUnavailable)?;
     Ok(())
 }
 
+/// Browser storage cannot append, so the value is rewritten.
+#[cfg(target_arch = "wasm32")]
+fn backend_append(archive: &Path, path: &Path, text: &str) -> StorageIoResult<()> {
+    let content = backend_read_to_string(archive, path)?.unwrap_or_default();
+    backend_write_string(archive, path, &(content + text))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn backend_exists(archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    Ok(backend_read_to_string(archive, path)?.is_some())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn backend_delete(archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    let key = storage_key(archive, path)?;
+    if is_indexed_db(archive) {
+        return Ok(crate::storage_idb::remove(&key));
+    }
+    let storage = local_storage()?;
+    let existed = storage
+        .get_item(&key)
+        .map_err(|_| StorageIoError::StorageUnavailable)?
+        .is_some();
+    storage
+        .remove_item(&key)
+        .map_err(|_| StorageIoError::StorageUnavailable)?;
+    Ok(existed)
+}
+
+/// Cheap change marker for the file at `path`, or `None` if it does not
+/// exist. Browser storage has no modification time, so the content is hashed.
+#[cfg(target_arch = "wasm32")]
+fn backend_fingerprint(archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
+    Ok(backend_read_to_string(archive, path)?.map(|content| content_hash(&content)))
+}
+
+/// Content hashes cannot be fooled by a quick rewrite.
+#[cfg(target_arch = "wasm32")]
+fn backend_recently_modified(_archive: &Path, _path: &Path) -> StorageIoResult<bool> {
+    Ok(false)
+}
+
+/// Keys below `dir` with the `dir` prefix stripped, e.g. `a.md` and
+/// `sub__b.md`.
+#[cfg(target_arch = "wasm32")]
+fn keys_under(archive: &Path, dir: &Path) -> StorageIoResult<Vec<String>> {
+    let prefix = format!("{}__", storage_key(archive, dir)?);
+    let keys = if is_indexed_db(archive) {
+        crate::storage_idb::keys_with_prefix(&prefix)
+    } else {
+        let storage = local_storage()?;
+        let len = storage
+            .length()
+            .map_err(|_| StorageIoError::StorageUnavailable)?;
+        let mut keys = Vec::new();
+        for i in 0..len {
+            if let Some(key) = storage
+                .key(i)
+                .map_err(|_| StorageIoError::StorageUnavailable)?
+            {
+                keys.push(key);
+            }
+        }
+        keys
+    };
+    let mut names: Vec<String> = keys
+        .into_iter()
+        .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn backend_list_dir(archive: &Path, dir: &Path) -> StorageIoResult<Vec<String>> {
+    let mut names = keys_under(archive, dir)?;
+    names.retain(|name| !name.contains("__"));
+    Ok(names)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn backend_list_files(archive: &Path, dir: &Path) -> StorageIoResult<Vec<String>> {
+    let mut names: Vec<String> = keys_under(archive, dir)?
+        .into_iter()
+        .map(|name| name.replace("__", "/"))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
-pub fn read_to_string(_archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
+fn backend_read_to_string(_archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
     if !path.exists() {
         return Ok(None);
     }
@@ -60,7 +238,7 @@ pub fn read_to_string(_archive: &Path, path: &Path) -> StorageIoResult<Option<St
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-pub fn write_string(_archive: &Path, path: &Path, content: &str) -> StorageIoResult<()> {
+fn backend_write_string(_archive: &Path, path: &Path, content: &str) -> StorageIoResult<()> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
@@ -68,30 +246,494 @@ pub fn write_string(_archive: &Path, path: &Path, content: &str) -> StorageIoRes
     Ok(())
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn backend_append(_archive: &Path, path: &Path, text: &str) -> StorageIoResult<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn backend_exists(_archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    Ok(path.is_file())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn backend_delete(_archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Cheap change marker for the file at `path`, or `None` if it does not
+/// exist. Native targets use the modification time and size.
+#[cfg(not(target_arch = "wasm32"))]
+fn backend_fingerprint(_archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mtime = meta
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    Ok(Some(format!("{mtime}-{}", meta.len())))
+}
+
+/// How long after a write another write may share its modification time,
+/// allowing for file systems that store times to the second or two.
+#[cfg(not(target_arch = "wasm32"))]
+const MTIME_GRANULARITY: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[cfg(not(target_arch = "wasm32"))]
+fn backend_recently_modified(_archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    let modified = match std::fs::metadata(path) {
+        Ok(meta) => meta.modified()?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    // A modification time in the future counts as recent.
+    Ok(modified
+        .elapsed()
+        .map_or(true, |elapsed| elapsed < MTIME_GRANULARITY))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn backend_list_dir(_archive: &Path, dir: &Path) -> StorageIoResult<Vec<String>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|_| StorageIoError::InvalidUtf8Path)?;
+        names.push(name);
+    }
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn backend_list_files(_archive: &Path, dir: &Path) -> StorageIoResult<Vec<String>> {
+    fn walk(dir: &Path, prefix: &str, names: &mut Vec<String>) -> StorageIoResult<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| StorageIoError::InvalidUtf8Path)?;
+            if entry.file_type()?.is_dir() {
+                walk(&entry.path(), &format!("{prefix}{name}/"), names)?;
+            } else {
+                names.push(format!("{prefix}{name}"));
+            }
+        }
+        Ok(())
+    }
+
+    let mut names = Vec::new();
+    if dir.is_dir() {
+        walk(dir, "", &mut names)?;
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// A file changed inside `staged`.
+#[derive(Clone)]
+enum StagedFile {
+    /// The new content of the file, `None` once it is deleted.
+    Write(Option<String>),
+    /// Text to add at the end of the stored file.
+    Append(String),
+}
+
+/// Files changed inside `staged`, by archive and path.
+type Staged = BTreeMap<(PathBuf, PathBuf), StagedFile>;
+
+thread_local! {
+    // Per thread, so other threads keep seeing the committed archive.
+    static STAGED: RefCell<Option<Staged>> = const { RefCell::new(None) };
+    /// Work to run once the staged files are stored.
+    static AFTER_COMMIT: RefCell<Vec<Box<dyn FnOnce()>>> = const { RefCell::new(Vec::new()) };
+    /// Files written inside `track_writes`, by archive and path.
+    static WRITTEN: RefCell<Option<BTreeSet<(PathBuf, PathBuf)>>> = const { RefCell::new(None) };
+}
+
+/// Clears the staged files when `staged` returns or unwinds.
+struct StagingGuard;
+
+impl Drop for StagingGuard {
+    fn drop(&mut self) {
+        STAGED.with(|s| s.borrow_mut().take());
+        AFTER_COMMIT.with(|a| a.borrow_mut().clear());
+    }
+}
+
+/// Run `f` with every write and delete held back in memory, then store
+/// them all if `f` succeeds. Reads and listings inside `f` see the held-back
+/// changes. When `f` fails nothing is stored; when storing fails, files
+/// already written are put back as they were.
+///
+/// This is best effort: storage has no transactions, so a crash while
+/// storing leaves the files written so far, and files that cannot be put
+/// back are reported as `StorageIoError::PartialCommit`.
+///
+/// Nested calls join the outermost one.
+pub fn staged<T, E: From<StorageIoError>>(f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    if is_staging() {
+        return f();
+    }
+    STAGED.with(|s| *s.borrow_mut() = Some(Staged::new()));
+    let guard = StagingGuard;
+    let result = f();
+    let files = STAGED.with(|s| s.borrow_mut().take()).unwrap_or_default();
+    let after_commit = AFTER_COMMIT.with(|a| std::mem::take(&mut *a.borrow_mut()));
+    drop(guard);
+    let value = result?;
+    commit(files)?;
+    for f in after_commit {
+        f();
+    }
+    Ok(value)
+}
+
+/// Run `f` and return it along with the files of `archive` it wrote,
+/// appended to or deleted, staged or not. Only the native watcher needs
+/// this.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn track_writes<T>(archive: &Path, f: impl FnOnce() -> T) -> (T, Vec<PathBuf>) {
+    let outer = WRITTEN.with(|w| w.borrow_mut().replace(BTreeSet::new()));
+    let result = f();
+    let written = WRITTEN.with(|w| {
+        let mut w = w.borrow_mut();
+        let written = w.take().unwrap_or_default();
+        *w = outer.map(|mut outer| {
+            outer.extend(written.iter().cloned());
+            outer
+        });
+        written
+    });
+    let paths = written
+        .into_iter()
+        .filter(|(file_archive, _)| file_archive == archive)
+        .map(|(_, path)| path)
+        .collect();
+    (result, paths)
+}
+
+fn note_write(archive: &Path, path: &Path) {
+    WRITTEN.with(|w| {
+        if let Some(written) = w.borrow_mut().as_mut() {
+            written.insert((archive.to_path_buf(), path.to_path_buf()));
+        }
+    });
+}
+
+/// Run `f` once the files of the surrounding `staged` call are stored, or
+/// right away outside `staged`. It is dropped if nothing gets stored.
+pub fn after_commit(f: impl FnOnce() + 'static) {
+    if is_staging() {
+        AFTER_COMMIT.with(|a| a.borrow_mut().push(Box::new(f)));
+    } else {
+        f();
+    }
+}
+
+fn is_staging() -> bool {
+    STAGED.with(|s| s.borrow().is_some())
+}
+
+/// Store the staged files. Appends come last and are not put back when
+/// storing fails, so only the files that follow them are left out.
+fn commit(files: Staged) -> StorageIoResult<()> {
+    let mut writes = Vec::new();
+    let mut appends = Vec::new();
+    for (key, file) in files {
+        match file {
+            StagedFile::Write(content) => writes.push((key, content)),
+            StagedFile::Append(text) => appends.push((key, text)),
+        }
+    }
+    let mut originals = Vec::with_capacity(writes.len());
+    for ((archive, path), _) in &writes {
+        originals.push(backend_read_to_string(archive, path)?);
+    }
+    for (i, ((archive, path), content)) in writes.iter().enumerate() {
+        let stored = match content {
+            Some(content) => backend_write_string(archive, path, content),
+            None => backend_delete(archive, path).map(|_| ()),
+        };
+        if let Err(error) = stored {
+            let mut unrestored = Vec::new();
+            for (((archive, path), _), original) in writes.iter().zip(&originals).take(i) {
+                let restored = match original {
+                    Some(original) => backend_write_string(archive, path, original),
+                    None => backend_delete(archive, path).map(|_| ()),
+                };
+                if restored.is_err() {
+                    unrestored.push(path.clone());
+                }
+            }
+            if unrestored.is_empty() {
+                return Err(error);
+            }
+            return Err(StorageIoError::PartialCommit {
+                error: Box::new(error),
+                unrestored,
+            });
+        }
+    }
+    for ((archive, path), text) in appends {
+        backend_append(&archive, &path, &text)?;
+    }
+    Ok(())
+}
+
+/// A file of the archive changed inside `staged`: its stored content
+/// before the change and its staged content, `None` when absent.
+pub struct StagedChange {
+    pub path: PathBuf,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The files of `archive` staged so far whose content differs from the
+/// stored one, by path; empty outside `staged`.
+pub fn staged_changes(archive: &Path) -> StorageIoResult<Vec<StagedChange>> {
+    let staged: Vec<(PathBuf, StagedFile)> = STAGED.with(|s| {
+        s.borrow()
+            .iter()
+            .flatten()
+            .filter(|((file_archive, _), _)| file_archive == archive)
+            .map(|((_, path), file)| (path.clone(), file.clone()))
+            .collect()
+    });
+    let mut changes = Vec::new();
+    for (path, file) in staged {
+        let before = backend_read_to_string(archive, &path)?;
+        let after = match file {
+            StagedFile::Write(content) => content,
+            StagedFile::Append(text) => Some(before.clone().unwrap_or_default() + &text),
+        };
+        if before != after {
+            changes.push(StagedChange {
+                path,
+                before,
+                after,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// The staged content of `path`: `Some(None)` if it was deleted, `None` if
+/// nothing is staged for it.
+fn staged_file(archive: &Path, path: &Path) -> StorageIoResult<Option<Option<String>>> {
+    let file = STAGED.with(|s| {
+        s.borrow()
+            .as_ref()?
+            .get(&(archive.to_path_buf(), path.to_path_buf()))
+            .cloned()
+    });
+    Ok(match file {
+        None => None,
+        Some(StagedFile::Write(content)) => Some(content),
+        Some(StagedFile::Append(text)) => {
+            let stored = backend_read_to_string(archive, path)?;
+            Some(Some(stored.unwrap_or_default() + &text))
+        }
+    })
+}
+
+/// Stage `content` for `path`; returns `false` outside `staged`.
+fn stage(archive: &Path, path: &Path, content: Option<String>) -> bool {
+    STAGED.with(|s| match s.borrow_mut().as_mut() {
+        Some(staged) => {
+            let key = (archive.to_path_buf(), path.to_path_buf());
+            staged.insert(key, StagedFile::Write(content));
+            true
+        }
+        None => false,
+    })
+}
+
+/// Stage `text` to be added at the end of `path`; returns `false` outside
+/// `staged`.
+fn stage_append(archive: &Path, path: &Path, text: &str) -> bool {
+    STAGED.with(|s| match s.borrow_mut().as_mut() {
+        Some(staged) => {
+            let key = (archive.to_path_buf(), path.to_path_buf());
+            match staged.get_mut(&key) {
+                Some(StagedFile::Write(Some(content)) | StagedFile::Append(content)) => {
+                    content.push_str(text)
+                }
+                Some(file @ StagedFile::Write(None)) => {
+                    *file = StagedFile::Write(Some(text.to_string()))
+                }
+                None => {
+                    staged.insert(key, StagedFile::Append(text.to_string()));
+                }
+            }
+            true
+        }
+        None => false,
+    })
+}
+
+/// Apply the staged files below `dir` to `names`, the `/`-separated paths
+/// relative to `dir` found in storage. Only direct children are considered
+/// unless `recursive` is set.
+fn merge_staged(archive: &Path, dir: &Path, names: &mut Vec<String>, recursive: bool) {
+    STAGED.with(|s| {
+        let staged = s.borrow();
+        let Some(staged) = staged.as_ref() else {
+            return;
+        };
+        for ((file_archive, path), file) in staged {
+            let Some(relative) = path
+                .strip_prefix(dir)
+                .ok()
+                .filter(|_| file_archive == archive)
+            else {
+                continue;
+            };
+            let parts: Vec<&str> = relative.iter().filter_map(|p| p.to_str()).collect();
+            if parts.is_empty() || (parts.len() > 1 && !recursive) {
+                continue;
+            }
+            let name = parts.join("/");
+            names.retain(|n| *n != name);
+            if !matches!(file, StagedFile::Write(None)) {
+                names.push(name);
+            }
+        }
+    });
+    names.sort();
+}
+
+/// FNV-1a hash of `content`: stable across builds, unlike `DefaultHasher`.
+pub fn content_hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+pub fn read_to_string(archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
+    match staged_file(archive, path)? {
+        Some(content) => Ok(content),
+        None => backend_read_to_string(archive, path),
+    }
+}
+
+pub fn write_string(archive: &Path, path: &Path, content: &str) -> StorageIoResult<()> {
+    note_write(archive, path);
+    if stage(archive, path, Some(content.to_string())) {
+        return Ok(());
+    }
+    backend_write_string(archive, path, content)
+}
+
+/// Add `text` at the end of the file at `path`, creating it if needed,
+/// without reading or rewriting what it holds.
+pub fn append(archive: &Path, path: &Path, text: &str) -> StorageIoResult<()> {
+    note_write(archive, path);
+    if stage_append(archive, path, text) {
+        return Ok(());
+    }
+    backend_append(archive, path, text)
+}
+
+pub fn exists(archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    match staged_file(archive, path)? {
+        Some(content) => Ok(content.is_some()),
+        None => backend_exists(archive, path),
+    }
+}
+
+/// Remove the file at `path`. Returns whether it existed.
+pub fn delete(archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    note_write(archive, path);
+    if !is_staging() {
+        return backend_delete(archive, path);
+    }
+    let existed = exists(archive, path)?;
+    stage(archive, path, None);
+    Ok(existed)
+}
+
+/// Cheap change marker for the file at `path`, or `None` if it does not
+/// exist. Staged files are marked by a hash of their content, as in browser
+/// storage.
+pub fn fingerprint(archive: &Path, path: &Path) -> StorageIoResult<Option<String>> {
+    match staged_file(archive, path)? {
+        Some(content) => Ok(content.as_deref().map(content_hash)),
+        None => backend_fingerprint(archive, path),
+    }
+}
+
+/// Whether the file at `path` changed so recently that a same-size rewrite
+/// could still keep its `fingerprint`; callers caching on the fingerprint
+/// should then check the content too.
+pub fn recently_modified(archive: &Path, path: &Path) -> StorageIoResult<bool> {
+    match staged_file(archive, path)? {
+        Some(_) => Ok(false),
+        None => backend_recently_modified(archive, path),
+    }
+}
+
+/// List the names of the files stored directly under `dir`.
+pub fn list_dir(archive: &Path, dir: &Path) -> StorageIoResult<Vec<String>> {
+    let mut names = backend_list_dir(archive, dir)?;
+    merge_staged(archive, dir, &mut names, false);
+    Ok(names)
+}
+
+/// List the files anywhere below `dir` as `/`-separated paths relative to
+/// `dir`.
+pub fn list_files(archive: &Path, dir: &Path) -> StorageIoResult<Vec<String>> {
+    let mut names = backend_list_files(archive, dir)?;
+    merge_staged(archive, dir, &mut names, true);
+    Ok(names)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn ensure_archive_structure(archive: &Path) -> StorageIoResult<()> {
     std::fs::create_dir_all(archive)?;
     std::fs::create_dir_all(archive.join("graphs"))?;
     std::fs::create_dir_all(archive.join("notes"))?;
     let goals_path = archive.join("goals.yaml");
-    if !goals_path.exists() {
-        std::fs::write(goals_path, "[]")?;
+    if !exists(archive, &goals_path)? {
+        write_string(archive, &goals_path, "[]")?;
     }
     Ok(())
 }
 
 #[cfg(target_arch = "wasm32")]
 pub fn ensure_archive_structure(archive: &Path) -> StorageIoResult<()> {
-    let storage = local_storage()?;
-    let key = storage_key(archive, Path::new("goals.yaml"))?;
-    if storage
-        .get_item(&key)
-        .map_err(|_| StorageIoError::StorageUnavailable)?
-        .is_none()
-    {
-        storage
-            .set_item(&key, "[]")
-            .map_err(|_| StorageIoError::StorageUnavailable)?;
+    // Directories are implicit in the key names.
+    let goals_path = archive.join("goals.yaml");
+    if !exists(archive, &goals_path)? {
+        write_string(archive, &goals_path, "[]")?;
     }
     Ok(())
 }
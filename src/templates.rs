@@ -0,0 +1,142 @@
+//! Goal templates: sets of goals created together, e.g. during onboarding.
+//!
+//! Templates live in `templates/{id}.yaml`, so they travel with snapshots
+//! and exports. Applying one reads and writes `goals.yaml` once for all of
+//! its goals, and its notes are stored in the same staged change: either
+//! every goal is created or none is.
+use std::path::{Path, PathBuf};
+
+use crate::ffi_types::AppError;
+use crate::goals::{self, next_goal_id, next_sort_order};
+use crate::launch;
+use crate::notes;
+use crate::storage_io;
+use crate::types::{Goal, GoalStatus, GoalTemplate};
+
+pub fn templates_dir(archive: &Path) -> PathBuf {
+    archive.join("templates")
+}
+
+fn template_path(archive: &Path, id: &str) -> Result<PathBuf, AppError> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AppError::InvalidInput {
+            detail: format!("invalid template id: {id:?}"),
+        });
+    }
+    Ok(templates_dir(archive).join(format!("{id}.yaml")))
+}
+
+/// Parse a template from YAML and check that it can be applied.
+pub fn parse_template(yaml: &str) -> Result<GoalTemplate, AppError> {
+    let template: GoalTemplate = serde_yaml::from_str(yaml)?;
+    validate(&template)?;
+    Ok(template)
+}
+
+fn validate(template: &GoalTemplate) -> Result<(), AppError> {
+    if template.goals.is_empty() {
+        return Err(AppError::InvalidInput {
+            detail: format!("template {:?} has no goals", template.name),
+        });
+    }
+    for goal in &template.goals {
+        if goal.name.trim().is_empty() {
+            return Err(AppError::InvalidInput {
+                detail: format!("template {:?} has a goal without a name", template.name),
+            });
+        }
+        launch::validate_commands(&goal.launch_commands)?;
+    }
+    Ok(())
+}
+
+/// Readable templates stored in the archive, by id.
+pub fn list_templates(archive: &Path) -> Result<Vec<GoalTemplate>, AppError> {
+    let mut templates = Vec::new();
+    for name in storage_io::list_dir(archive, &templates_dir(archive))? {
+        let Some(id) = name.strip_suffix(".yaml") else {
+            continue;
+        };
+        // A damaged or hand-edited template should not hide the others.
+        if let Ok(template) = get_template(archive, id) {
+            templates.push(template);
+        }
+    }
+    templates.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(templates)
+}
+
+pub fn get_template(archive: &Path, id: &str) -> Result<GoalTemplate, AppError> {
+    let path = template_path(archive, id)?;
+    let Some(data) = storage_io::read_to_string(archive, &path)? else {
+        return Err(AppError::NotFound {
+            resource: "template".into(),
+            id: id.to_string(),
+        });
+    };
+    let mut template = parse_template(&data).map_err(|e| AppError::Parse {
+        detail: format!("templates/{id}.yaml: {e}"),
+    })?;
+    // The file name is the id, whatever the content says.
+    template.id = id.to_string();
+    Ok(template)
+}
+
+/// Store `template` as `templates/{id}.yaml`, replacing any template with
+/// the same id.
+pub fn save_template(archive: &Path, template: &GoalTemplate) -> Result<(), AppError> {
+    let path = template_path(archive, &template.id)?;
+    validate(template)?;
+    let data = serde_yaml::to_string(template)?;
+    storage_io::write_string(archive, &path, &data)?;
+    Ok(())
+}
+
+pub fn delete_template(archive: &Path, id: &str) -> Result<(), AppError> {
+    if !storage_io::delete(archive, &template_path(archive, id)?)? {
+        return Err(AppError::NotFound {
+            resource: "template".into(),
+            id: id.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Create the goals of `template` with a single write of `goals.yaml`, then
+/// write their notes, all as one staged change. Returns the ids of the new
+/// goals, in template order.
+pub fn apply_template(archive: &Path, template: &GoalTemplate) -> Result<Vec<u64>, AppError> {
+    validate(template)?;
+    storage_io::staged(|| {
+        let mut goals = goals::read_goals(archive)?;
+        let mut ids = Vec::with_capacity(template.goals.len());
+        for item in &template.goals {
+            let goal = Goal {
+                id: next_goal_id(&goals),
+                name: item.name.trim().to_string(),
+                is_reward: item.is_reward,
+                commands: item.commands.clone(),
+                status: GoalStatus::TODO,
+                trashed: false,
+                quantity_name: item.quantity_name.clone(),
+                pinned: item.pinned,
+                sort_order: next_sort_order(&goals),
+                launch_commands: item.launch_commands.clone(),
+            };
+            ids.push(goal.id);
+            goals.push(goal);
+        }
+        goals::write_goals(archive, &goals)?;
+
+        for (item, id) in template.goals.iter().zip(&ids) {
+            if let Some(note) = &item.note {
+                notes::edit_note(archive, *id, note)?;
+            }
+        }
+        Ok(ids)
+    })
+}
@@ -0,0 +1,233 @@
+//! Moving whole archives between devices.
+//!
+//! An export is the same JSON bundle a snapshot stores (see `snapshot`):
+//! every archive file keyed by its relative path, in one string. It reads
+//! and writes the same on every storage backend, so it is how an archive
+//! kept in browser storage gets to the filesystem and back.
+//!
+//! Importing either replaces the target with the bundle or merges the
+//! bundle into it. Merging remaps ids: a bundle goal with the same name and
+//! kind as a target goal becomes that goal, other goals and all sessions get
+//! fresh ids, and notes follow their owners. Merged sessions are recorded
+//! through `add_session`, so they are validated, their overlaps with the
+//! target's are handled by the overlap policy and their goals become DOING.
+//! The target is snapshotted before anything is written, and a merge that
+//! fails part way stores nothing.
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::archive::parse_start_ts;
+use crate::events::{self, ArchiveEvent};
+use crate::ffi_types::AppError;
+use crate::goals::{self, next_goal_id};
+use crate::manifest;
+use crate::notes::{self, session_note_relative_path};
+use crate::session_graph::{add_session, list_day_sessions, parse_day_file_name, parse_mermaid};
+use crate::snapshot::{self, Bundle};
+use crate::storage_io;
+use crate::types::{
+    Goal, ImportMode, ImportSummary, SessionKind, SessionMetadata, SessionValidation,
+};
+
+/// The whole archive as a single JSON string, labelled with the archive's
+/// directory name.
+pub fn export_archive(archive: &Path) -> Result<String, AppError> {
+    let label = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    Ok(serde_json::to_string(&Bundle::capture(archive, label)?)?)
+}
+
+/// Import the bundle `data` produced by `export_archive` (or a snapshot
+/// file) into `archive` and return what changed. `validation` decides what
+/// happens to merged sessions that overlap the target's.
+pub fn import_archive(
+    archive: &Path,
+    data: &str,
+    mode: ImportMode,
+    validation: &SessionValidation,
+) -> Result<(ImportSummary, Vec<ArchiveEvent>), AppError> {
+    let bundle = Bundle::parse(data, "imported archive")?;
+    let incoming_goals: Vec<Goal> = match bundle.files.get("goals.yaml") {
+        Some(content) => serde_yaml::from_str(content)?,
+        None => vec![],
+    };
+    let safety = snapshot::create(archive, Some("Before import".into()))?;
+    let (mut summary, events) = match mode {
+        ImportMode::Replace => replace(archive, &bundle, &incoming_goals)?,
+        ImportMode::Merge => {
+            storage_io::staged(|| merge(archive, &bundle, &incoming_goals, validation))?
+        }
+    };
+    summary.snapshot_id = safety.id;
+    Ok((summary, events))
+}
+
+fn replace(
+    archive: &Path,
+    bundle: &Bundle,
+    incoming_goals: &[Goal],
+) -> Result<(ImportSummary, Vec<ArchiveEvent>), AppError> {
+    let events = bundle.replace_archive_files(archive)?;
+    // Bundles exported before the manifest existed come without one.
+    manifest::migrate(archive)?;
+
+    let mut summary = ImportSummary {
+        goals_added: incoming_goals.len() as u32,
+        ..Default::default()
+    };
+    for (relative, content) in &bundle.files {
+        if relative.starts_with("notes/") {
+            summary.notes_written += 1;
+        } else if let Some(day) = relative
+            .strip_prefix("graphs/")
+            .and_then(parse_day_file_name)
+        {
+            summary.sessions_added += parse_mermaid(content, day)?.len() as u32;
+        }
+    }
+    Ok((summary, events))
+}
+
+fn merge(
+    archive: &Path,
+    bundle: &Bundle,
+    incoming_goals: &[Goal],
+    validation: &SessionValidation,
+) -> Result<(ImportSummary, Vec<ArchiveEvent>), AppError> {
+    let before = Bundle::capture(archive, None)?;
+    let mut summary = ImportSummary::default();
+
+    // Bundle goal id -> target goal id.
+    let mut goal_ids = HashMap::new();
+    let mut goals = goals::read_goals(archive)?;
+    for goal in incoming_goals {
+        let existing = goals
+            .iter()
+            .find(|g| g.name == goal.name && g.is_reward == goal.is_reward);
+        if let Some(existing) = existing {
+            goal_ids.insert(goal.id, existing.id);
+            summary.goals_matched += 1;
+            continue;
+        }
+        let id = next_goal_id(&goals);
+        goal_ids.insert(goal.id, id);
+        // Merged goals go to the end of the manual order.
+        let sort_order = goals::next_sort_order(&goals);
+        goals.push(Goal {
+            id,
+            sort_order,
+            ..goal.clone()
+        });
+        summary.goals_added += 1;
+    }
+    if summary.goals_added > 0 {
+        goals::write_goals(archive, &goals)?;
+    }
+
+    // Goal notes: copied when the target has none, appended when it has a
+    // different one.
+    for goal in incoming_goals {
+        let Some(incoming) = bundle.files.get(&format!("notes/goal_{}.md", goal.id)) else {
+            continue;
+        };
+        let id = goal_ids[&goal.id];
+        let current = notes::get_note(archive, id)?;
+        let merged = if current.trim().is_empty() {
+            incoming.clone()
+        } else if current.contains(incoming.trim()) {
+            continue;
+        } else {
+            format!("{current}\n{incoming}")
+        };
+        notes::edit_note(archive, id, &merged)?;
+        summary.notes_written += 1;
+    }
+
+    // Templates the target does not have yet.
+    for (relative, content) in &bundle.files {
+        if relative.starts_with("templates/") {
+            let path = archive.join(relative);
+            if !storage_io::exists(archive, &path)? {
+                storage_io::write_string(archive, &path, content)?;
+            }
+        }
+    }
+
+    for (relative, content) in &bundle.files {
+        let Some(day) = relative
+            .strip_prefix("graphs/")
+            .and_then(parse_day_file_name)
+        else {
+            continue;
+        };
+        let mut sessions = list_day_sessions(archive, day)?;
+        for session in parse_mermaid(content, day)? {
+            let Some(&goal_id) = goal_ids.get(&session.goal_id) else {
+                summary.sessions_skipped += 1;
+                continue;
+            };
+            let present = sessions.iter().any(|s| {
+                s.goal_id == goal_id && s.start_at == session.start_at && s.end_at == session.end_at
+            });
+            if present {
+                summary.sessions_skipped += 1;
+                continue;
+            }
+            let note = bundle
+                .files
+                .get(&session_note_relative_path(day, &session.id))
+                .cloned();
+            let has_note = note.is_some();
+            // A matched goal of the target may not track a quantity.
+            let quantity = session.quantity.filter(|_| {
+                goals
+                    .iter()
+                    .any(|g| g.id == goal_id && g.quantity_name.is_some())
+            });
+            let added = add_session(
+                archive,
+                goal_id,
+                &session.name,
+                parse_start_ts(session.start_at)?,
+                u32::try_from(session.end_at - session.start_at).unwrap_or(0),
+                session.kind == SessionKind::Reward,
+                quantity,
+                validation,
+                &SessionMetadata {
+                    rating: session.rating,
+                    tags: session.tags,
+                    note,
+                },
+            );
+            match added {
+                Ok(_) => {}
+                Err(err) if err.rejects_session() => {
+                    summary.sessions_skipped += 1;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            }
+            summary.notes_written += u32::from(has_note);
+            summary.sessions_added += 1;
+            sessions = list_day_sessions(archive, day)?;
+        }
+    }
+
+    let after = Bundle::capture(archive, None)?;
+    Ok((summary, changed_events(&before, &after)))
+}
+
+/// Events for every file whose content differs between two captures.
+fn changed_events(before: &Bundle, after: &Bundle) -> Vec<ArchiveEvent> {
+    let paths: BTreeSet<&String> = before.files.keys().chain(after.files.keys()).collect();
+    let mut events = Vec::new();
+    for path in paths {
+        let old = before.files.get(path).map(String::as_str);
+        let new = after.files.get(path).map(String::as_str);
+        if old != new {
+            events.extend(events::file_events(path, old, new));
+        }
+    }
+    events
+}
@@ -3,6 +3,8 @@
 //! This module defines the primary domain types exposed to foreign
 //! language bindings: `Goal`, `Session`, and their supporting enums.
 //! These types are serializable and annotated for `uniffi` where needed.
+use std::collections::HashMap;
+
 use chrono::{Local, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -37,11 +39,17 @@ pub enum GoalStatus {
 /// - `id`: unique numeric identifier.
 /// - `name`: human-readable name.
 /// - `is_reward`: whether the goal is a reward type.
-/// - `commands`: optional associated commands.
+/// - `commands`: legacy free-form commands, interpreted by each app.
 /// - `status`: current `GoalStatus`.
 /// - `trashed`: whether the goal is in the trash bin.
+/// - `pinned`: whether the goal is listed first in the manual order.
+/// - `sort_order`: position in the manual order, as a fractional index key
+///   compared as a string. Empty for goals created before manual ordering,
+///   which sort first in `goals.yaml` order.
+/// - `launch_commands`: typed commands, resolved per platform by
+///   `resolve_launch_commands`.
 #[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Goal {
     pub id: u64,
     pub name: String,
@@ -55,6 +63,219 @@ pub struct Goal {
     pub trashed: bool,
     #[serde(default)]
     pub quantity_name: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub sort_order: String,
+    #[serde(default)]
+    pub launch_commands: Vec<LaunchCommand>,
+}
+
+/// What a launch command opens.
+///
+/// - `Shell`: a command line for the platform's shell.
+/// - `Url`: a URL to open in the browser or the app registered for it.
+/// - `AppId`: an application id, e.g. an Android package name or a macOS
+///   bundle id.
+/// - `File`: a file or folder to open with its default application.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommandKind {
+    Shell,
+    Url,
+    AppId,
+    File,
+}
+
+/// A platform launch commands can target.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Platform {
+    Linux,
+    MacOs,
+    Windows,
+    Android,
+    Ios,
+    Web,
+}
+
+/// What a `LaunchCommand` runs on one platform instead of its default.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandVariant {
+    pub platform: Platform,
+    pub kind: CommandKind,
+    pub value: String,
+}
+
+/// A typed command attached to a goal.
+///
+/// - `label`: optional name to show, e.g. on a button.
+/// - `kind` / `value`: the default command. `value` is a template that may
+///   use `{goal_name}`, `{goal_id}`, `{archive}`, `{note_path}` and `{date}`;
+///   `{{` and `}}` stand for literal braces. `None` limits the command to
+///   the platforms in `variants`.
+/// - `variants`: replacements of the default on specific platforms.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchCommand {
+    #[serde(default)]
+    pub label: Option<String>,
+    pub kind: CommandKind,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub variants: Vec<CommandVariant>,
+}
+
+/// A command ready to run, returned by `resolve_launch_commands`.
+///
+/// - `label`: the command's label, if any.
+/// - `kind`: how to run `value`.
+/// - `value`: the command with its variables filled in.
+/// - `legacy`: whether it comes from `Goal::commands`. Such strings are
+///   returned unchanged, as a `Url` when they look like one and as `Shell`
+///   otherwise; apps may keep interpreting them as they always did.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedCommand {
+    pub label: Option<String>,
+    pub kind: CommandKind,
+    pub value: String,
+    pub legacy: bool,
+}
+
+/// Order of the goals returned by `list_goals`.
+///
+/// - `Archive`: the order of `goals.yaml`, which is creation order.
+/// - `Manual`: pinned goals first, then the order set with `move_goal`.
+/// - `Recent`: most recently active first, over the whole history; goals
+///   without sessions follow in `goals.yaml` order.
+/// - `Name`: alphabetical, ignoring case.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GoalSort {
+    #[default]
+    Archive,
+    Manual,
+    Recent,
+    Name,
+}
+
+/// A goal returned by `search_goals_scored`, with how it matched.
+///
+/// - `goal`: the matching goal.
+/// - `score`: fuzzy match score of the goal name; 0 for an empty query.
+/// - `indices`: character indices (not bytes) of the name that matched the
+///   query, in ascending order; empty for an empty query.
+/// - `recency_boosted`: whether the goal had a session in the last week and
+///   was therefore ranked ahead of goals without one.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoredGoal {
+    pub goal: Goal,
+    pub score: i64,
+    pub indices: Vec<u32>,
+    pub recency_boosted: bool,
+}
+
+/// Weights of the ranking used by `search_goals_ranked`. Every signal is
+/// scaled to 0..=1 before it is weighted, so the weights compare directly.
+///
+/// - `fuzzy_weight`: how well the name matches, relative to the best match.
+/// - `recency_weight`: 1 for a session just now, halving every
+///   `half_life_days` since the goal's latest session.
+/// - `frequency_weight`: the goal's sessions over its whole history, each
+///   decayed with the same half-life (frecency), relative to the busiest goal.
+/// - `pinned_weight`: added as is for pinned goals and goals in
+///   `pinned_goal_ids`.
+/// - `half_life_days`: must be greater than zero.
+/// - `pinned_goal_ids`: more goals to boost, e.g. a launcher's own favourites.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchRanking {
+    pub fuzzy_weight: f64,
+    pub recency_weight: f64,
+    pub frequency_weight: f64,
+    pub pinned_weight: f64,
+    pub half_life_days: f64,
+    pub pinned_goal_ids: Vec<u64>,
+}
+
+impl Default for SearchRanking {
+    fn default() -> Self {
+        Self {
+            fuzzy_weight: 1.0,
+            recency_weight: 0.3,
+            frequency_weight: 0.2,
+            pinned_weight: 0.5,
+            half_life_days: 14.0,
+            pinned_goal_ids: vec![],
+        }
+    }
+}
+
+/// A goal returned by `search_goals_ranked`.
+///
+/// - `goal`: the matching goal.
+/// - `rank`: the weighted sum results are sorted by, highest first.
+/// - `score` / `indices`: the fuzzy match, as in `ScoredGoal`.
+/// - `last_active_at`: start of the goal's latest session, if any.
+/// - `session_count`: sessions recorded for the goal, ever.
+/// - `pinned`: whether the pinned boost was applied.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankedGoal {
+    pub goal: Goal,
+    pub rank: f64,
+    pub score: i64,
+    pub indices: Vec<u32>,
+    pub last_active_at: Option<i64>,
+    pub session_count: u32,
+    pub pinned: bool,
+}
+
+/// A goal that `apply_template` creates.
+///
+/// - `name`, `is_reward`, `commands`, `launch_commands`, `quantity_name`,
+///   `pinned`: as on `Goal`.
+/// - `note`: initial content of the goal's note, e.g. its daily target in
+///   the frontmatter.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateGoal {
+    pub name: String,
+    #[serde(default)]
+    pub is_reward: bool,
+    #[serde(default)]
+    pub commands: Vec<String>,
+    #[serde(default)]
+    pub launch_commands: Vec<LaunchCommand>,
+    #[serde(default)]
+    pub quantity_name: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// A reusable set of goals, stored in the archive as
+/// `templates/{id}.yaml` or shipped by an app as YAML of the same shape.
+///
+/// - `id`: the file name without `.yaml`; letters, digits, `-` and `_`.
+/// - `name`: human-readable name.
+/// - `description`: optional longer text for pickers.
+/// - `goals`: the goals to create, in order.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoalTemplate {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub goals: Vec<TemplateGoal>,
 }
 
 /// A recorded session entry.
@@ -65,6 +286,9 @@ pub struct Goal {
 /// - `kind`: whether this was a `Goal` or `Reward` session.
 /// - `start_at` / `end_at`: Unix timestamps in seconds (UTC).
 /// - `quantity`: optional quantity recorded during the session.
+/// - `rating`: optional focus rating from 1 to 5.
+/// - `tags`: free-form tags.
+/// - `note_path`: archive-relative path of the session note, if any.
 #[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -78,6 +302,542 @@ pub struct Session {
     pub start_at: i64,
     #[serde(default)]
     pub end_at: i64,
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub note_path: Option<String>,
+}
+
+/// One page of sessions returned by `list_goal_sessions`.
+///
+/// - `sessions`: the sessions in this page, newest first.
+/// - `next_cursor`: pass it back to fetch the next page; `None` on the last page.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPage {
+    pub sessions: Vec<Session>,
+    pub next_cursor: Option<String>,
+}
+
+/// Time recorded on a single day.
+///
+/// - `date_iso`: the day in `YYYY-MM-DD` format.
+/// - `goal_secs` / `reward_secs`: total duration of goal and reward sessions.
+/// - `session_count`: number of sessions recorded that day.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayTotal {
+    pub date_iso: String,
+    pub goal_secs: i64,
+    pub reward_secs: i64,
+    pub session_count: u32,
+}
+
+/// User-provided details attached to a session.
+///
+/// - `rating`: optional focus rating from 1 to 5.
+/// - `tags`: free-form tags; they may not contain control characters,
+///   `-->` or any of `[ ] , : ; #`.
+/// - `note`: optional note text ("what did I do"), stored under
+///   `notes/sessions/YYYY-MM-DD/<session id>.md`; an empty note removes it.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// How `add_session` treats a new session that overlaps an existing one
+/// recorded on the same day.
+///
+/// - `Reject`: fail with `AppError::SessionOverlap`.
+/// - `Merge`: fold the new session into the overlapping ones when they all
+///   belong to the same goal and kind; otherwise reject.
+/// - `Allow`: record the session as-is.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverlapPolicy {
+    #[default]
+    Reject,
+    Merge,
+    Allow,
+}
+
+/// Validation rules applied when recording a session.
+///
+/// - `overlap`: how to handle overlaps with sessions of the same day.
+/// - `allow_future`: accept sessions starting in the future.
+/// - `min_duration_secs` / `max_duration_secs`: accepted duration bounds
+///   (inclusive). Day graphs store times to the minute, so durations under a
+///   minute or of a whole day or more are always rejected.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionValidation {
+    pub overlap: OverlapPolicy,
+    pub allow_future: bool,
+    pub min_duration_secs: u32,
+    pub max_duration_secs: u32,
+}
+
+impl Default for SessionValidation {
+    fn default() -> Self {
+        Self {
+            overlap: OverlapPolicy::Reject,
+            allow_future: false,
+            min_duration_secs: 60,
+            max_duration_secs: 24 * 60 * 60 - 60,
+        }
+    }
+}
+
+/// One operation of `apply_batch`. Each takes the arguments of the function
+/// of the same name and sees the changes made by the operations before it.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BatchOp {
+    AddGoal {
+        name: String,
+        #[serde(default)]
+        is_reward: bool,
+        #[serde(default)]
+        commands: Vec<String>,
+        #[serde(default)]
+        quantity_name: Option<String>,
+    },
+    SetGoalStatus {
+        goal_id: u64,
+        status: GoalStatus,
+    },
+    SetGoalTrashed {
+        goal_id: u64,
+        trashed: bool,
+    },
+    SetPinned {
+        goal_id: u64,
+        pinned: bool,
+    },
+    AddSession {
+        goal_id: u64,
+        goal_name: String,
+        start_ts_secs: i64,
+        duration_secs: u32,
+        #[serde(default)]
+        is_reward: bool,
+        #[serde(default)]
+        quantity: Option<u32>,
+        #[serde(default)]
+        metadata: Option<SessionMetadata>,
+    },
+    UpdateSession {
+        date_iso: String,
+        session_id: String,
+        metadata: SessionMetadata,
+    },
+    EditNote {
+        goal_id: u64,
+        content: String,
+    },
+}
+
+/// What one `BatchOp` produced: the goal it added or changed, the session
+/// it added or updated, or the goal whose note it edited.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BatchResult {
+    Goal { goal: Goal },
+    Session { session: Session },
+    Note { goal_id: u64 },
+}
+
+/// An entry of the operation journal, as listed by `history`.
+///
+/// - `id`: position in the journal, starting at 1.
+/// - `at`: Unix timestamp (seconds) of the change.
+/// - `operation`: the function that made the change (`add_goal`,
+///   `set_goal_trashed`, ...), or `undo` / `redo`.
+/// - `target`: for `undo` and `redo`, the id of the entry they reverted or
+///   applied again.
+/// - `files`: archive-relative paths of the files the change touched.
+/// - `undone`: whether the change is currently undone and can be brought
+///   back with `redo`.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub at: i64,
+    pub operation: String,
+    pub target: Option<u64>,
+    pub files: Vec<String>,
+    pub undone: bool,
+}
+
+/// Category of an `ArchiveIssue` found by `check_archive`.
+///
+/// - `UnreadableFile`: a file that could not be parsed at all.
+/// - `DuplicateGoalId`: two goals in `goals.yaml` share an id.
+/// - `UnknownGoal`: a session refers to a goal id missing from `goals.yaml`.
+/// - `DuplicateSessionId`: two sessions of the same day share an id.
+/// - `BrokenChain`: sessions that are not reachable through the `-->`
+///   chain of their day graph and are therefore invisible.
+/// - `MalformedSession`: a node whose label has no valid time range.
+/// - `MalformedGraphName`: a file in `graphs/` not named `YYYY-MM-DD.mmd`.
+/// - `OrphanNote`: a note for a goal or session that does not exist.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IssueKind {
+    UnreadableFile,
+    DuplicateGoalId,
+    UnknownGoal,
+    DuplicateSessionId,
+    BrokenChain,
+    MalformedSession,
+    MalformedGraphName,
+    OrphanNote,
+}
+
+/// A single inconsistency found by `check_archive`.
+///
+/// - `kind`: the category of the problem.
+/// - `path`: archive-relative path of the affected file.
+/// - `goal_id`: the goal concerned, if any.
+/// - `detail`: human-readable description.
+/// - `repaired`: whether repair mode fixed it.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveIssue {
+    pub kind: IssueKind,
+    pub path: String,
+    pub goal_id: Option<u64>,
+    pub detail: String,
+    pub repaired: bool,
+}
+
+/// Result of `check_archive`.
+///
+/// - `issues`: every problem found, in the order the files were checked.
+/// - `goals_checked` / `days_checked` / `notes_checked`: how much of the
+///   archive was looked at.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckReport {
+    pub issues: Vec<ArchiveIssue>,
+    pub goals_checked: u32,
+    pub days_checked: u32,
+    pub notes_checked: u32,
+}
+
+/// A snapshot of an archive created by `create_snapshot`.
+///
+/// - `id`: identifier to pass to `restore_snapshot`, derived from the
+///   creation time (e.g. `20240501-093000-120`).
+/// - `created_at`: Unix timestamp in seconds.
+/// - `label`: optional description, e.g. why the snapshot was taken.
+/// - `file_count`: number of archive files captured.
+/// - `size_bytes`: size of the stored bundle.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub created_at: i64,
+    pub label: Option<String>,
+    pub file_count: u32,
+    pub size_bytes: u64,
+}
+
+/// Which snapshots `prune_snapshots` keeps.
+///
+/// A snapshot is kept when it is one of the `keep_last` newest or younger
+/// than `keep_days` days. A rule left as `None` keeps nothing on its own;
+/// with both `None` every snapshot is kept.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    #[serde(default)]
+    pub keep_last: Option<u32>,
+    #[serde(default)]
+    pub keep_days: Option<u32>,
+}
+
+/// How `import_archive` combines an exported archive with the target.
+///
+/// - `Merge`: add the bundle's goals, sessions and notes to the target.
+///   Goals are matched by name and kind; the others get fresh ids, and
+///   sessions already present (same goal, start and end) are skipped.
+/// - `Replace`: make the target an exact copy of the bundle.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportMode {
+    Merge,
+    Replace,
+}
+
+/// What `import_archive` changed.
+///
+/// - `goals_added` / `goals_matched`: bundle goals created in the target
+///   and bundle goals mapped onto an existing goal.
+/// - `sessions_added` / `sessions_skipped`: sessions written, and sessions
+///   left out because the target already had them, the overlap policy
+///   refused them or their goal is missing from the bundle.
+/// - `notes_written`: goal and session notes created or extended.
+/// - `snapshot_id`: snapshot of the target taken before the import, for
+///   `restore_snapshot`.
+///
+/// In `Replace` mode everything in the bundle counts as added.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub goals_added: u32,
+    pub goals_matched: u32,
+    pub sessions_added: u32,
+    pub sessions_skipped: u32,
+    pub notes_written: u32,
+    pub snapshot_id: String,
+}
+
+/// Output format of `export_sessions` and `export_goals`.
+///
+/// - `Csv`: comma-separated values with a header line.
+/// - `JsonLines`: one JSON object per line.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+/// How `import_ics` assigns calendar events to goals.
+///
+/// - `goal_mapping`: event summary to goal id; checked first.
+/// - `match_by_name`: otherwise use the goal whose name equals the summary,
+///   ignoring case. Trashed goals are never matched by name.
+/// - `validation`: rules for the imported sessions; the archive's default
+///   validation when `None`.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IcsImportOptions {
+    pub goal_mapping: HashMap<String, u64>,
+    pub match_by_name: bool,
+    pub validation: Option<SessionValidation>,
+}
+
+impl Default for IcsImportOptions {
+    fn default() -> Self {
+        Self {
+            goal_mapping: HashMap::new(),
+            match_by_name: true,
+            validation: None,
+        }
+    }
+}
+
+/// A calendar event `import_ics` did not turn into a session.
+///
+/// - `uid`: the event's `UID`, if it has one.
+/// - `summary`: the event's title.
+/// - `start_at`: Unix timestamp of its start, when it could be read.
+/// - `reason`: why it was skipped, e.g. no matching goal or an overlap.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedEvent {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub start_at: Option<i64>,
+    pub reason: String,
+}
+
+/// Result of `import_ics`: the sessions recorded and the events skipped.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IcsImportReport {
+    pub imported: Vec<Session>,
+    pub skipped: Vec<SkippedEvent>,
+}
+
+/// The time tracker a CSV file passed to `import_tracker_csv` comes from.
+///
+/// - `Toggl`: Toggl Track's detailed report export.
+/// - `Clockify`: Clockify's detailed report export.
+/// - `Forest`: Forest's CSV export; trees that died are skipped.
+/// - `Generic`: any CSV, described by `TrackerImportOptions::columns`.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackerFormat {
+    Toggl,
+    Clockify,
+    Forest,
+    #[default]
+    Generic,
+}
+
+/// Which CSV columns hold what, by header name (case is ignored).
+///
+/// - `goal`: columns naming the goal; the first non-empty one is used.
+/// - `start`: start date and time, or only the time when `start_date` is set.
+/// - `start_date`: optional separate start date column.
+/// - `end` / `end_date`: end time, in the same way as the start. An `end`
+///   holding only a time takes the start date (or the day after, if earlier).
+/// - `duration`: used when there is no end: `H:MM:SS`, `H:MM` or decimal hours.
+/// - `quantity`: optional whole number, kept for goals with a quantity unit.
+/// - `success`: optional column; rows where it is `false`, `no` or `0` are
+///   skipped.
+///
+/// Times without a UTC offset are read as local time. Dates may be written
+/// `YYYY-MM-DD`, `MM/DD/YYYY` or `DD.MM.YYYY`.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvColumns {
+    pub goal: Vec<String>,
+    pub start: String,
+    pub start_date: Option<String>,
+    pub end: Option<String>,
+    pub end_date: Option<String>,
+    pub duration: Option<String>,
+    pub quantity: Option<String>,
+    pub success: Option<String>,
+}
+
+/// Options for `import_tracker_csv`.
+///
+/// - `format`: where the file comes from.
+/// - `columns`: column mapping; required for `Generic`, and replaces the
+///   built-in mapping of the other formats when set.
+/// - `create_missing_goals`: add a goal for every name that matches none
+///   (ignoring case); otherwise such rows are skipped.
+/// - `dry_run`: report what would be imported without writing anything.
+///   On by default, so a first call only previews.
+/// - `validation`: rules for the imported sessions; the archive's default
+///   validation when `None`.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackerImportOptions {
+    pub format: TrackerFormat,
+    pub columns: Option<CsvColumns>,
+    pub create_missing_goals: bool,
+    pub dry_run: bool,
+    pub validation: Option<SessionValidation>,
+}
+
+impl Default for TrackerImportOptions {
+    fn default() -> Self {
+        Self {
+            format: TrackerFormat::Generic,
+            columns: None,
+            create_missing_goals: true,
+            dry_run: true,
+            validation: None,
+        }
+    }
+}
+
+/// A CSV row `import_tracker_csv` did not import.
+///
+/// - `line`: line of the row in the file, counting the header as line 1.
+/// - `reason`: why it was skipped.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedRow {
+    pub line: u32,
+    pub reason: String,
+}
+
+/// Result of `import_tracker_csv`.
+///
+/// - `dry_run`: whether this was only a preview.
+/// - `rows_read`: data rows in the file.
+/// - `sessions`: sessions recorded, or that would be in a dry run.
+/// - `goals_created`: goals added for unmatched names. In a dry run their
+///   ids are provisional.
+/// - `duplicates`: rows skipped because the archive already has a session
+///   of the same goal with the same start and end.
+/// - `skipped`: other rows that were not imported.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackerImportReport {
+    pub dry_run: bool,
+    pub rows_read: u32,
+    pub sessions: Vec<Session>,
+    pub goals_created: Vec<Goal>,
+    pub duplicates: u32,
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// The value of a note frontmatter field.
+///
+/// - `Text`: a single value. Numbers, booleans and nested structures are
+///   read as their YAML text; values written this way are stored as strings.
+/// - `List`: a list of values, e.g. links or resources.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FrontmatterValue {
+    Text { value: String },
+    List { items: Vec<String> },
+}
+
+/// A field of the YAML frontmatter at the top of a goal note.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrontmatterField {
+    pub key: String,
+    pub value: FrontmatterValue,
+}
+
+/// Where `search_archive` found a hit.
+///
+/// - `GoalName`: the goal's name.
+/// - `GoalNote`: a line of the goal's note.
+/// - `SessionName`: the name of a session recorded for the goal, when it
+///   differs from the goal's own name.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchHitKind {
+    GoalName,
+    GoalNote,
+    SessionName,
+}
+
+/// A highlighted part of a search snippet, as character offsets (Unicode
+/// scalar values, not bytes). `end` is exclusive.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// One result of `search_archive`.
+///
+/// - `kind`: what matched.
+/// - `goal_id` / `goal_name`: the goal the hit belongs to.
+/// - `session_id` / `date`: the most recent session with the matching name,
+///   for `SessionName` hits.
+/// - `line`: 1-based line of the note, for `GoalNote` hits.
+/// - `score`: fuzzy match score; results are sorted by it, best first.
+/// - `snippet`: the matching text, shortened around the match when long.
+/// - `highlights`: the matched parts of `snippet`.
+#[cfg_attr(not(target_arch = "wasm32"), derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub kind: SearchHitKind,
+    pub goal_id: u64,
+    pub goal_name: String,
+    pub session_id: Option<String>,
+    pub date: Option<String>,
+    pub line: Option<u32>,
+    pub score: i64,
+    pub snippet: String,
+    pub highlights: Vec<TextRange>,
 }
 
 /// Convert a Unix-seconds timestamp to an ISO date string (`YYYY-MM-DD`)
@@ -0,0 +1,316 @@
+export type GoalStatus = "TODO" | "DOING" | "DONE";
+export type SessionKind = "Goal" | "Reward";
+export type OverlapPolicy = "Reject" | "Merge" | "Allow";
+
+export interface Goal {
+    id: number;
+    name: string;
+    is_reward: boolean;
+    commands: string[];
+    status: GoalStatus;
+    trashed: boolean;
+    quantity_name: string | null;
+    pinned: boolean;
+    sort_order: string;
+    launch_commands: LaunchCommand[];
+}
+
+export type CommandKind = "Shell" | "Url" | "AppId" | "File";
+export type Platform = "Linux" | "MacOs" | "Windows" | "Android" | "Ios" | "Web";
+
+export interface CommandVariant {
+    platform: Platform;
+    kind: CommandKind;
+    value: string;
+}
+
+export interface LaunchCommand {
+    label?: string | null;
+    kind: CommandKind;
+    value?: string | null;
+    variants?: CommandVariant[];
+}
+
+export interface ResolvedCommand {
+    label: string | null;
+    kind: CommandKind;
+    value: string;
+    legacy: boolean;
+}
+
+export type GoalSort = "Archive" | "Manual" | "Recent" | "Name";
+
+export interface TemplateGoal {
+    name: string;
+    is_reward?: boolean;
+    commands?: string[];
+    launch_commands?: LaunchCommand[];
+    quantity_name?: string | null;
+    pinned?: boolean;
+    note?: string | null;
+}
+
+export interface GoalTemplate {
+    id?: string;
+    name: string;
+    description?: string | null;
+    goals: TemplateGoal[];
+}
+
+export interface Session {
+    id: string;
+    name: string;
+    goal_id: number;
+    kind: SessionKind;
+    quantity: number | null;
+    start_at: number;
+    end_at: number;
+    rating: number | null;
+    tags: string[];
+    note_path: string | null;
+}
+
+export interface SessionValidation {
+    overlap: OverlapPolicy;
+    allow_future: boolean;
+    min_duration_secs: number;
+    max_duration_secs: number;
+}
+
+export interface SessionMetadata {
+    rating?: number | null;
+    tags?: string[];
+    note?: string | null;
+}
+
+export type BatchOp =
+    | {
+          type: "AddGoal";
+          name: string;
+          is_reward?: boolean;
+          commands?: string[];
+          quantity_name?: string | null;
+      }
+    | { type: "SetGoalStatus"; goal_id: number; status: GoalStatus }
+    | { type: "SetGoalTrashed"; goal_id: number; trashed: boolean }
+    | { type: "SetPinned"; goal_id: number; pinned: boolean }
+    | {
+          type: "AddSession";
+          goal_id: number;
+          goal_name: string;
+          start_ts_secs: number;
+          duration_secs: number;
+          is_reward?: boolean;
+          quantity?: number | null;
+          metadata?: SessionMetadata | null;
+      }
+    | { type: "UpdateSession"; date_iso: string; session_id: string; metadata: SessionMetadata }
+    | { type: "EditNote"; goal_id: number; content: string };
+
+export type BatchResult =
+    | { type: "Goal"; goal: Goal }
+    | { type: "Session"; session: Session }
+    | { type: "Note"; goal_id: number };
+
+export type FrontmatterValue =
+    | { type: "Text"; value: string }
+    | { type: "List"; items: string[] };
+
+export interface FrontmatterField {
+    key: string;
+    value: FrontmatterValue;
+}
+
+export interface ScoredGoal {
+    goal: Goal;
+    score: number;
+    indices: number[];
+    recency_boosted: boolean;
+}
+
+export interface SearchRanking {
+    fuzzy_weight?: number;
+    recency_weight?: number;
+    frequency_weight?: number;
+    pinned_weight?: number;
+    half_life_days?: number;
+    pinned_goal_ids?: number[];
+}
+
+export interface RankedGoal {
+    goal: Goal;
+    rank: number;
+    score: number;
+    indices: number[];
+    last_active_at: number | null;
+    session_count: number;
+    pinned: boolean;
+}
+
+export type SearchHitKind = "GoalName" | "GoalNote" | "SessionName";
+
+export interface TextRange {
+    start: number;
+    end: number;
+}
+
+export interface SearchHit {
+    kind: SearchHitKind;
+    goal_id: number;
+    goal_name: string;
+    session_id: string | null;
+    date: string | null;
+    line: number | null;
+    score: number;
+    snippet: string;
+    highlights: TextRange[];
+}
+
+export interface SessionPage {
+    sessions: Session[];
+    next_cursor: string | null;
+}
+
+export interface DayTotal {
+    date_iso: string;
+    goal_secs: number;
+    reward_secs: number;
+    session_count: number;
+}
+
+export type IssueKind =
+    | "UnreadableFile"
+    | "DuplicateGoalId"
+    | "UnknownGoal"
+    | "DuplicateSessionId"
+    | "BrokenChain"
+    | "MalformedSession"
+    | "MalformedGraphName"
+    | "OrphanNote";
+
+export interface ArchiveIssue {
+    kind: IssueKind;
+    path: string;
+    goal_id: number | null;
+    detail: string;
+    repaired: boolean;
+}
+
+export interface CheckReport {
+    issues: ArchiveIssue[];
+    goals_checked: number;
+    days_checked: number;
+    notes_checked: number;
+}
+
+export interface JournalEntry {
+    id: number;
+    at: number;
+    operation: string;
+    target: number | null;
+    files: string[];
+    undone: boolean;
+}
+
+export interface SnapshotInfo {
+    id: string;
+    created_at: number;
+    label: string | null;
+    file_count: number;
+    size_bytes: number;
+}
+
+export interface RetentionPolicy {
+    keep_last?: number | null;
+    keep_days?: number | null;
+}
+
+export type ImportMode = "Merge" | "Replace";
+export type ExportFormat = "Csv" | "JsonLines";
+
+export interface IcsImportOptions {
+    goal_mapping?: Record<string, number>;
+    match_by_name?: boolean;
+    validation?: SessionValidation | null;
+}
+
+export interface SkippedEvent {
+    uid: string | null;
+    summary: string;
+    start_at: number | null;
+    reason: string;
+}
+
+export interface IcsImportReport {
+    imported: Session[];
+    skipped: SkippedEvent[];
+}
+
+export type TrackerFormat = "Toggl" | "Clockify" | "Forest" | "Generic";
+
+export interface CsvColumns {
+    goal: string[];
+    start: string;
+    start_date?: string | null;
+    end?: string | null;
+    end_date?: string | null;
+    duration?: string | null;
+    quantity?: string | null;
+    success?: string | null;
+}
+
+export interface TrackerImportOptions {
+    format?: TrackerFormat;
+    columns?: CsvColumns | null;
+    create_missing_goals?: boolean;
+    dry_run?: boolean;
+    validation?: SessionValidation | null;
+}
+
+export interface SkippedRow {
+    line: number;
+    reason: string;
+}
+
+export interface TrackerImportReport {
+    dry_run: boolean;
+    rows_read: number;
+    sessions: Session[];
+    goals_created: Goal[];
+    duplicates: number;
+    skipped: SkippedRow[];
+}
+
+export interface ImportSummary {
+    goals_added: number;
+    goals_matched: number;
+    sessions_added: number;
+    sessions_skipped: number;
+    notes_written: number;
+    snapshot_id: string;
+}
+
+export type ArchiveEvent =
+    | { type: "GoalAdded"; goal_id: number }
+    | { type: "GoalUpdated"; goal_id: number }
+    | { type: "GoalTrashed"; goal_id: number; trashed: boolean }
+    | { type: "SessionAdded"; date_iso: string; session_id: string }
+    | { type: "SessionUpdated"; date_iso: string; session_id: string }
+    | { type: "SessionsChanged"; date_iso: string }
+    | { type: "NoteEdited"; goal_id: number };
+
+export interface AppError extends Error {
+    name: "AppError";
+    kind:
+        | "Io"
+        | "StorageUnavailable"
+        | "InvalidPath"
+        | "NotFound"
+        | "InvalidInput"
+        | "Parse"
+        | "SessionOverlap"
+        | "SessionInFuture"
+        | "InvalidDuration"
+        | "UnsupportedSchemaVersion";
+    [field: string]: unknown;
+}
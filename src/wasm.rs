@@ -0,0 +1,640 @@
+//! JavaScript bindings for the web target.
+//!
+//! Every function exported over `uniffi` has a camelCase counterpart here.
+//! Records cross the boundary as plain JS objects (via
+//! `serde-wasm-bindgen`) and ids are plain numbers. Errors are thrown as JS
+//! `Error`s named `AppError` that carry the variant in `kind` plus its
+//! fields, e.g. `{ kind: "NotFound", resource: "goal", id: "3" }`.
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::events::{ArchiveEvent, ArchiveListener};
+use crate::export::ExportSink;
+use crate::ffi_types::AppError;
+use crate::{storage_idb, storage_io, Archive};
+
+// Written by hand; `tests/typings.rs` checks it against the Rust types.
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = include_str!("wasm.d.ts");
+
+impl From<AppError> for JsValue {
+    fn from(e: AppError) -> Self {
+        let error = js_sys::Error::new(&e.to_string());
+        error.set_name("AppError");
+        if let Ok(fields) = to_js(&e) {
+            js_sys::Object::assign(&error, &fields.unchecked_into());
+        }
+        error.into()
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, AppError> {
+    // Plain objects and numbers rather than `Map`s and `BigInt`s.
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    value.serialize(&serializer).map_err(|e| AppError::Parse {
+        detail: e.to_string(),
+    })
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<Option<T>, AppError> {
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    serde_wasm_bindgen::from_value(value)
+        .map(Some)
+        .map_err(|e| AppError::InvalidInput {
+            detail: e.to_string(),
+        })
+}
+
+fn required_js<T: DeserializeOwned>(value: JsValue, name: &str) -> Result<T, AppError> {
+    from_js(value)?.ok_or_else(|| AppError::InvalidInput {
+        detail: format!("{name} is required"),
+    })
+}
+
+fn to_id(value: f64) -> Result<u64, AppError> {
+    if value < 0.0 || value.fract() != 0.0 || value > u64::MAX as f64 {
+        return Err(AppError::InvalidInput {
+            detail: format!("invalid id: {value}"),
+        });
+    }
+    Ok(value as u64)
+}
+
+#[wasm_bindgen(js_name = listGoals, unchecked_return_type = "Goal[]")]
+pub fn list_goals(
+    archive_path: String,
+    #[wasm_bindgen(unchecked_param_type = "GoalStatus[] | null")] statuses: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "GoalSort | null")] sort: JsValue,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::list_goals(
+        archive_path,
+        from_js(statuses)?,
+        from_js(sort)?,
+    )?)
+}
+
+#[wasm_bindgen(js_name = listTrash, unchecked_return_type = "Goal[]")]
+pub fn list_trash(archive_path: String) -> Result<JsValue, AppError> {
+    to_js(&crate::list_trash(archive_path)?)
+}
+
+#[wasm_bindgen(js_name = searchGoals, unchecked_return_type = "Goal[]")]
+pub fn search_goals(
+    archive_path: String,
+    query: String,
+    is_reward: Option<bool>,
+    #[wasm_bindgen(unchecked_param_type = "GoalStatus[] | null")] statuses: JsValue,
+    sort_by_recent: Option<bool>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::search_goals(
+        archive_path,
+        query,
+        is_reward,
+        from_js(statuses)?,
+        sort_by_recent,
+    )?)
+}
+
+#[wasm_bindgen(js_name = searchGoalsScored, unchecked_return_type = "ScoredGoal[]")]
+pub fn search_goals_scored(
+    archive_path: String,
+    query: String,
+    is_reward: Option<bool>,
+    #[wasm_bindgen(unchecked_param_type = "GoalStatus[] | null")] statuses: JsValue,
+    sort_by_recent: Option<bool>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::search_goals_scored(
+        archive_path,
+        query,
+        is_reward,
+        from_js(statuses)?,
+        sort_by_recent,
+    )?)
+}
+
+#[wasm_bindgen(js_name = searchGoalsRanked, unchecked_return_type = "RankedGoal[]")]
+pub fn search_goals_ranked(
+    archive_path: String,
+    query: String,
+    is_reward: Option<bool>,
+    #[wasm_bindgen(unchecked_param_type = "GoalStatus[] | null")] statuses: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "SearchRanking | null")] ranking: JsValue,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::search_goals_ranked(
+        archive_path,
+        query,
+        is_reward,
+        from_js(statuses)?,
+        from_js(ranking)?,
+    )?)
+}
+
+#[wasm_bindgen(js_name = searchArchive, unchecked_return_type = "SearchHit[]")]
+pub fn search_archive(archive_path: String, query: String) -> Result<JsValue, AppError> {
+    to_js(&crate::search_archive(archive_path, query)?)
+}
+
+#[wasm_bindgen(js_name = addGoal, unchecked_return_type = "Goal")]
+pub fn add_goal(
+    archive_path: String,
+    name: String,
+    is_reward: bool,
+    commands: Vec<String>,
+    quantity_name: Option<String>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::add_goal(
+        archive_path,
+        name,
+        is_reward,
+        commands,
+        quantity_name,
+    )?)
+}
+
+#[wasm_bindgen(js_name = getNote)]
+pub fn get_note(archive_path: String, goal_id: f64) -> Result<String, AppError> {
+    crate::get_note(archive_path, to_id(goal_id)?)
+}
+
+#[wasm_bindgen(js_name = editNote)]
+pub fn edit_note(archive_path: String, goal_id: f64, content: String) -> Result<bool, AppError> {
+    crate::edit_note(archive_path, to_id(goal_id)?, content)
+}
+
+#[wasm_bindgen(js_name = getNoteFrontmatter, unchecked_return_type = "FrontmatterField[]")]
+pub fn get_note_frontmatter(archive_path: String, goal_id: f64) -> Result<JsValue, AppError> {
+    to_js(&crate::get_note_frontmatter(archive_path, to_id(goal_id)?)?)
+}
+
+#[wasm_bindgen(js_name = updateNoteFrontmatter, unchecked_return_type = "FrontmatterField[]")]
+pub fn update_note_frontmatter(
+    archive_path: String,
+    goal_id: f64,
+    #[wasm_bindgen(unchecked_param_type = "FrontmatterField[]")] set: JsValue,
+    remove: Vec<String>,
+) -> Result<JsValue, AppError> {
+    let set = from_js(set)?.unwrap_or_default();
+    to_js(&crate::update_note_frontmatter(
+        archive_path,
+        to_id(goal_id)?,
+        set,
+        remove,
+    )?)
+}
+
+#[wasm_bindgen(js_name = getNoteBody)]
+pub fn get_note_body(archive_path: String, goal_id: f64) -> Result<String, AppError> {
+    crate::get_note_body(archive_path, to_id(goal_id)?)
+}
+
+#[wasm_bindgen(js_name = setGoalStatus, unchecked_return_type = "Goal")]
+pub fn set_goal_status(
+    archive_path: String,
+    goal_id: f64,
+    #[wasm_bindgen(unchecked_param_type = "GoalStatus")] status: JsValue,
+) -> Result<JsValue, AppError> {
+    let status = required_js(status, "status")?;
+    to_js(&crate::set_goal_status(
+        archive_path,
+        to_id(goal_id)?,
+        status,
+    )?)
+}
+
+#[wasm_bindgen(js_name = setGoalTrashed, unchecked_return_type = "Goal")]
+pub fn set_goal_trashed(
+    archive_path: String,
+    goal_id: f64,
+    trashed: bool,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::set_goal_trashed(
+        archive_path,
+        to_id(goal_id)?,
+        trashed,
+    )?)
+}
+
+#[wasm_bindgen(js_name = setPinned, unchecked_return_type = "Goal")]
+pub fn set_pinned(archive_path: String, goal_id: f64, pinned: bool) -> Result<JsValue, AppError> {
+    to_js(&crate::set_pinned(archive_path, to_id(goal_id)?, pinned)?)
+}
+
+#[wasm_bindgen(js_name = setLaunchCommands, unchecked_return_type = "Goal")]
+pub fn set_launch_commands(
+    archive_path: String,
+    goal_id: f64,
+    #[wasm_bindgen(unchecked_param_type = "LaunchCommand[]")] commands: JsValue,
+) -> Result<JsValue, AppError> {
+    let commands = required_js(commands, "commands")?;
+    to_js(&crate::set_launch_commands(
+        archive_path,
+        to_id(goal_id)?,
+        commands,
+    )?)
+}
+
+#[wasm_bindgen(js_name = resolveLaunchCommands, unchecked_return_type = "ResolvedCommand[]")]
+pub fn resolve_launch_commands(
+    archive_path: String,
+    goal_id: f64,
+    #[wasm_bindgen(unchecked_param_type = "Platform | null")] platform: JsValue,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::resolve_launch_commands(
+        archive_path,
+        to_id(goal_id)?,
+        from_js(platform)?,
+    )?)
+}
+
+#[wasm_bindgen(js_name = listTemplates, unchecked_return_type = "GoalTemplate[]")]
+pub fn list_templates(archive_path: String) -> Result<JsValue, AppError> {
+    to_js(&crate::list_templates(archive_path)?)
+}
+
+#[wasm_bindgen(js_name = saveTemplate)]
+pub fn save_template(
+    archive_path: String,
+    #[wasm_bindgen(unchecked_param_type = "GoalTemplate")] template: JsValue,
+) -> Result<(), AppError> {
+    crate::save_template(archive_path, required_js(template, "template")?)
+}
+
+#[wasm_bindgen(js_name = deleteTemplate)]
+pub fn delete_template(archive_path: String, id: String) -> Result<(), AppError> {
+    crate::delete_template(archive_path, id)
+}
+
+#[wasm_bindgen(js_name = parseTemplate, unchecked_return_type = "GoalTemplate")]
+pub fn parse_template(yaml: String) -> Result<JsValue, AppError> {
+    to_js(&crate::parse_template(yaml)?)
+}
+
+#[wasm_bindgen(js_name = applyTemplate, unchecked_return_type = "number[]")]
+pub fn apply_template(
+    archive_path: String,
+    #[wasm_bindgen(unchecked_param_type = "GoalTemplate")] template: JsValue,
+) -> Result<JsValue, AppError> {
+    let template = required_js(template, "template")?;
+    to_js(&crate::apply_template(archive_path, template)?)
+}
+
+#[wasm_bindgen(js_name = moveGoal, unchecked_return_type = "Goal")]
+pub fn move_goal(
+    archive_path: String,
+    goal_id: f64,
+    before_id: Option<f64>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::move_goal(
+        archive_path,
+        to_id(goal_id)?,
+        before_id.map(to_id).transpose()?,
+    )?)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen(js_name = addSession, unchecked_return_type = "Session")]
+pub fn add_session(
+    archive_path: String,
+    goal_id: f64,
+    goal_name: String,
+    start_ts_secs: f64,
+    duration_secs: u32,
+    is_reward: bool,
+    quantity: Option<u32>,
+    #[wasm_bindgen(unchecked_param_type = "SessionValidation | null")] validation: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "SessionMetadata | null")] metadata: JsValue,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::add_session(
+        archive_path,
+        to_id(goal_id)?,
+        goal_name,
+        start_ts_secs as i64,
+        duration_secs,
+        is_reward,
+        quantity,
+        from_js(validation)?,
+        from_js(metadata)?,
+    )?)
+}
+
+#[wasm_bindgen(js_name = applyBatch, unchecked_return_type = "BatchResult[]")]
+pub fn apply_batch(
+    archive_path: String,
+    #[wasm_bindgen(unchecked_param_type = "BatchOp[]")] ops: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "SessionValidation | null")] validation: JsValue,
+) -> Result<JsValue, AppError> {
+    let ops = required_js(ops, "ops")?;
+    to_js(&crate::apply_batch(
+        archive_path,
+        ops,
+        from_js(validation)?,
+    )?)
+}
+
+#[wasm_bindgen(js_name = updateSession, unchecked_return_type = "Session")]
+pub fn update_session(
+    archive_path: String,
+    date_iso: String,
+    session_id: String,
+    #[wasm_bindgen(unchecked_param_type = "SessionMetadata")] metadata: JsValue,
+) -> Result<JsValue, AppError> {
+    let metadata = required_js(metadata, "metadata")?;
+    to_js(&crate::update_session(
+        archive_path,
+        date_iso,
+        session_id,
+        metadata,
+    )?)
+}
+
+#[wasm_bindgen(js_name = getSessionNote)]
+pub fn get_session_note(
+    archive_path: String,
+    date_iso: String,
+    session_id: String,
+) -> Result<String, AppError> {
+    crate::get_session_note(archive_path, date_iso, session_id)
+}
+
+#[wasm_bindgen(js_name = querySessions, unchecked_return_type = "Session[]")]
+pub fn query_sessions(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    tags: Option<Vec<String>>,
+    min_rating: Option<u8>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::query_sessions(
+        archive_path,
+        start_date_iso,
+        end_date_iso,
+        tags,
+        min_rating,
+    )?)
+}
+
+#[wasm_bindgen(js_name = listDaySessions, unchecked_return_type = "Session[]")]
+pub fn list_day_sessions(archive_path: String, date_iso: String) -> Result<JsValue, AppError> {
+    to_js(&crate::list_day_sessions(archive_path, date_iso)?)
+}
+
+#[wasm_bindgen(js_name = listSessionsBetweenDates, unchecked_return_type = "Session[]")]
+pub fn list_sessions_between_dates(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::list_sessions_between_dates(
+        archive_path,
+        start_date_iso,
+        end_date_iso,
+    )?)
+}
+
+#[wasm_bindgen(js_name = listDayTotals, unchecked_return_type = "DayTotal[]")]
+pub fn list_day_totals(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::list_day_totals(
+        archive_path,
+        start_date_iso,
+        end_date_iso,
+    )?)
+}
+
+#[wasm_bindgen(js_name = listGoalSessions, unchecked_return_type = "SessionPage")]
+pub fn list_goal_sessions(
+    archive_path: String,
+    goal_id: f64,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    #[wasm_bindgen(unchecked_param_type = "SessionKind | null")] kind: JsValue,
+    limit: Option<u32>,
+    cursor: Option<String>,
+) -> Result<JsValue, AppError> {
+    to_js(&crate::list_goal_sessions(
+        archive_path,
+        to_id(goal_id)?,
+        start_date_iso,
+        end_date_iso,
+        from_js(kind)?,
+        limit,
+        cursor,
+    )?)
+}
+
+#[wasm_bindgen(js_name = listRecordedDays)]
+pub fn list_recorded_days(archive_path: String) -> Result<Vec<String>, AppError> {
+    crate::list_recorded_days(archive_path)
+}
+
+#[wasm_bindgen(js_name = firstRecordedDay)]
+pub fn first_recorded_day(archive_path: String) -> Result<Option<String>, AppError> {
+    crate::first_recorded_day(archive_path)
+}
+
+#[wasm_bindgen(js_name = lastRecordedDay)]
+pub fn last_recorded_day(archive_path: String) -> Result<Option<String>, AppError> {
+    crate::last_recorded_day(archive_path)
+}
+
+#[wasm_bindgen(js_name = archiveSchemaVersion)]
+pub fn archive_schema_version(archive_path: String) -> Result<u32, AppError> {
+    crate::archive_schema_version(archive_path)
+}
+
+#[wasm_bindgen(js_name = supportedSchemaVersion)]
+pub fn supported_schema_version() -> u32 {
+    crate::supported_schema_version()
+}
+
+#[wasm_bindgen(js_name = migrateArchive)]
+pub fn migrate_archive(archive_path: String) -> Result<u32, AppError> {
+    crate::migrate_archive(archive_path)
+}
+
+#[wasm_bindgen(js_name = checkArchive, unchecked_return_type = "CheckReport")]
+pub fn check_archive(archive_path: String, repair: bool) -> Result<JsValue, AppError> {
+    to_js(&crate::check_archive(archive_path, repair)?)
+}
+
+#[wasm_bindgen(js_name = undo, unchecked_return_type = "JournalEntry | null")]
+pub fn undo(archive_path: String) -> Result<JsValue, AppError> {
+    to_js(&crate::undo(archive_path)?)
+}
+
+#[wasm_bindgen(js_name = redo, unchecked_return_type = "JournalEntry | null")]
+pub fn redo(archive_path: String) -> Result<JsValue, AppError> {
+    to_js(&crate::redo(archive_path)?)
+}
+
+#[wasm_bindgen(js_name = history, unchecked_return_type = "JournalEntry[]")]
+pub fn history(archive_path: String, limit: Option<u32>) -> Result<JsValue, AppError> {
+    to_js(&crate::history(archive_path, limit)?)
+}
+
+#[wasm_bindgen(js_name = createSnapshot, unchecked_return_type = "SnapshotInfo")]
+pub fn create_snapshot(archive_path: String, label: Option<String>) -> Result<JsValue, AppError> {
+    to_js(&crate::create_snapshot(archive_path, label)?)
+}
+
+#[wasm_bindgen(js_name = listSnapshots, unchecked_return_type = "SnapshotInfo[]")]
+pub fn list_snapshots(archive_path: String) -> Result<JsValue, AppError> {
+    to_js(&crate::list_snapshots(archive_path)?)
+}
+
+#[wasm_bindgen(js_name = restoreSnapshot, unchecked_return_type = "SnapshotInfo")]
+pub fn restore_snapshot(archive_path: String, snapshot_id: String) -> Result<JsValue, AppError> {
+    to_js(&crate::restore_snapshot(archive_path, snapshot_id)?)
+}
+
+#[wasm_bindgen(js_name = pruneSnapshots)]
+pub fn prune_snapshots(
+    archive_path: String,
+    #[wasm_bindgen(unchecked_param_type = "RetentionPolicy")] policy: JsValue,
+) -> Result<Vec<String>, AppError> {
+    let policy = required_js(policy, "policy")?;
+    crate::prune_snapshots(archive_path, policy)
+}
+
+#[wasm_bindgen(js_name = exportArchive)]
+pub fn export_archive(archive_path: String) -> Result<String, AppError> {
+    crate::export_archive(archive_path)
+}
+
+#[wasm_bindgen(js_name = importArchive, unchecked_return_type = "ImportSummary")]
+pub fn import_archive(
+    archive_path: String,
+    bundle: String,
+    #[wasm_bindgen(unchecked_param_type = "ImportMode")] mode: JsValue,
+) -> Result<JsValue, AppError> {
+    let mode = required_js(mode, "mode")?;
+    to_js(&crate::import_archive(archive_path, bundle, mode)?)
+}
+
+/// Passes exported text to a JS callback.
+struct JsSink(js_sys::Function);
+
+// See `JsListener`.
+unsafe impl Send for JsSink {}
+unsafe impl Sync for JsSink {}
+
+impl ExportSink for JsSink {
+    fn write(&self, chunk: String) {
+        let _ = self.0.call1(&JsValue::NULL, &JsValue::from_str(&chunk));
+    }
+}
+
+#[wasm_bindgen(js_name = exportSessions)]
+pub fn export_sessions(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    #[wasm_bindgen(unchecked_param_type = "ExportFormat")] format: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "(chunk: string) => void")] on_chunk: js_sys::Function,
+) -> Result<f64, AppError> {
+    let format = required_js(format, "format")?;
+    let sink = Arc::new(JsSink(on_chunk));
+    Ok(crate::export_sessions(archive_path, start_date_iso, end_date_iso, format, sink)? as f64)
+}
+
+#[wasm_bindgen(js_name = exportGoals)]
+pub fn export_goals(
+    archive_path: String,
+    #[wasm_bindgen(unchecked_param_type = "ExportFormat")] format: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "(chunk: string) => void")] on_chunk: js_sys::Function,
+) -> Result<f64, AppError> {
+    let format = required_js(format, "format")?;
+    Ok(crate::export_goals(archive_path, format, Arc::new(JsSink(on_chunk)))? as f64)
+}
+
+#[wasm_bindgen(js_name = exportIcs)]
+pub fn export_ics(
+    archive_path: String,
+    start_date_iso: Option<String>,
+    end_date_iso: Option<String>,
+    #[wasm_bindgen(unchecked_param_type = "(chunk: string) => void")] on_chunk: js_sys::Function,
+) -> Result<f64, AppError> {
+    let sink = Arc::new(JsSink(on_chunk));
+    Ok(crate::export_ics(archive_path, start_date_iso, end_date_iso, sink)? as f64)
+}
+
+#[wasm_bindgen(js_name = importIcs, unchecked_return_type = "IcsImportReport")]
+pub fn import_ics(
+    archive_path: String,
+    ics: String,
+    #[wasm_bindgen(unchecked_param_type = "IcsImportOptions | null")] options: JsValue,
+) -> Result<JsValue, AppError> {
+    let options = from_js(options)?;
+    to_js(&crate::import_ics(archive_path, ics, options)?)
+}
+
+#[wasm_bindgen(js_name = importTrackerCsv, unchecked_return_type = "TrackerImportReport")]
+pub fn import_tracker_csv(
+    archive_path: String,
+    csv: String,
+    #[wasm_bindgen(unchecked_param_type = "TrackerImportOptions | null")] options: JsValue,
+) -> Result<JsValue, AppError> {
+    let options = from_js(options)?.unwrap_or_default();
+    to_js(&crate::import_tracker_csv(archive_path, csv, options)?)
+}
+
+/// Store the archive at `archivePath` in IndexedDB instead of localStorage,
+/// which lifts localStorage's size limit of a few megabytes.
+///
+/// Call this once at startup, before other functions touch the archive.
+/// Entries already in localStorage are moved over. Afterwards reads are
+/// served from memory and writes are saved in the background; await
+/// `flushStorage` before the page closes to be sure they have landed.
+#[wasm_bindgen(js_name = useIndexedDb)]
+pub async fn use_indexed_db(archive_path: String) -> Result<(), AppError> {
+    storage_io::mount_indexed_db(Path::new(&archive_path)).await?;
+    Ok(())
+}
+
+/// Wait until every write to IndexedDB made so far has been saved.
+#[wasm_bindgen(js_name = flushStorage)]
+pub async fn flush_storage() -> Result<(), AppError> {
+    storage_idb::flush().await?;
+    Ok(())
+}
+
+/// Forwards events to a JS callback.
+struct JsListener(js_sys::Function);
+
+// wasm32 without threads is single-threaded, so the callback is never
+// touched from another thread.
+unsafe impl Send for JsListener {}
+unsafe impl Sync for JsListener {}
+
+impl ArchiveListener for JsListener {
+    fn on_event(&self, event: ArchiveEvent) {
+        if let Ok(value) = to_js(&event) {
+            let _ = self.0.call1(&JsValue::NULL, &value);
+        }
+    }
+}
+
+/// Call `callback` with an `ArchiveEvent` whenever the archive changes,
+/// including from other tabs. Returns an id for `unsubscribe`.
+#[wasm_bindgen]
+pub fn subscribe(
+    archive_path: String,
+    #[wasm_bindgen(unchecked_param_type = "(event: ArchiveEvent) => void")]
+    callback: js_sys::Function,
+) -> f64 {
+    Archive::attach(archive_path).subscribe(Arc::new(JsListener(callback))) as f64
+}
+
+#[wasm_bindgen]
+pub fn unsubscribe(archive_path: String, subscription_id: f64) {
+    Archive::attach(archive_path).unsubscribe(subscription_id as u64)
+}
@@ -0,0 +1,47 @@
+//! Check a Success archive for inconsistencies.
+//!
+//! Usage: `success-fsck <archive> [--repair]`
+//!
+//! Exits with 0 when the archive is clean (or every issue was repaired),
+//! 1 when issues remain and 2 when the archive could not be checked.
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let (flags, paths): (Vec<String>, Vec<String>) =
+        std::env::args().skip(1).partition(|a| a.starts_with('-'));
+    let repair = flags.iter().any(|f| f == "--repair");
+    let [archive] = paths.as_slice() else {
+        eprintln!("usage: success-fsck <archive> [--repair]");
+        return ExitCode::from(2);
+    };
+    if flags.iter().any(|f| f != "--repair") {
+        eprintln!("usage: success-fsck <archive> [--repair]");
+        return ExitCode::from(2);
+    }
+
+    let report = match successlib::check_archive(archive.clone(), repair) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("success-fsck: {e}");
+            return ExitCode::from(2);
+        }
+    };
+    for issue in &report.issues {
+        let status = if issue.repaired { " (repaired)" } else { "" };
+        println!("{:?} {}: {}{status}", issue.kind, issue.path, issue.detail);
+    }
+    let open = report.issues.iter().filter(|i| !i.repaired).count();
+    println!(
+        "checked {} goals, {} days, {} notes: {} issues, {} open",
+        report.goals_checked,
+        report.days_checked,
+        report.notes_checked,
+        report.issues.len(),
+        open
+    );
+    if open == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
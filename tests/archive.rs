@@ -0,0 +1,238 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use successlib::{
+    add_goal, list_goals, Archive, ArchiveEvent, ArchiveListener, ArchiveOptions, Error, GoalStatus,
+};
+
+use common::temp_archive_dir;
+
+#[test]
+fn archive_handle_mirrors_free_functions() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let archive = Archive::open(path.clone(), None).unwrap();
+
+    let goal = archive
+        .add_goal("Read".into(), false, vec![], None)
+        .unwrap();
+    archive.set_goal_status(goal.id, GoalStatus::DOING).unwrap();
+
+    let via_handle = archive.list_goals(None, None).unwrap();
+    let via_free_fn = list_goals(path, None, None).unwrap();
+    assert_eq!(via_handle.len(), 1);
+    assert_eq!(via_handle[0].status, GoalStatus::DOING);
+    assert_eq!(via_free_fn[0].status, GoalStatus::DOING);
+}
+
+#[test]
+fn archive_handle_sees_changes_from_other_writers() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let archive = Archive::open(path.clone(), None).unwrap();
+    assert!(archive.list_goals(None, None).unwrap().is_empty());
+
+    add_goal(path, "Written elsewhere".into(), false, vec![], None).unwrap();
+
+    let goals = archive.list_goals(None, None).unwrap();
+    assert_eq!(goals.len(), 1);
+    assert_eq!(goals[0].name, "Written elsewhere");
+}
+
+#[test]
+fn archive_handle_sees_same_size_rewrites() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let archive = Archive::open(path, None).unwrap();
+    archive
+        .add_goal("Read".into(), false, vec![], None)
+        .unwrap();
+    assert_eq!(archive.list_goals(None, None).unwrap()[0].name, "Read");
+
+    // Same size and, as within one clock tick, the same modification time.
+    let goals_yaml = temp.path().join("goals.yaml");
+    let modified = std::fs::metadata(&goals_yaml).unwrap().modified().unwrap();
+    let content = std::fs::read_to_string(&goals_yaml).unwrap();
+    std::fs::write(&goals_yaml, content.replace("Read", "Sing")).unwrap();
+    let file = std::fs::File::options()
+        .write(true)
+        .open(&goals_yaml)
+        .unwrap();
+    file.set_modified(modified).unwrap();
+
+    assert_eq!(archive.list_goals(None, None).unwrap()[0].name, "Sing");
+}
+
+#[test]
+fn archive_handle_trusts_the_times_of_settled_files() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let archive = Archive::open(path, None).unwrap();
+    archive
+        .add_goal("Read".into(), false, vec![], None)
+        .unwrap();
+
+    // Age the file so its modification time can be relied on.
+    let goals_yaml = temp.path().join("goals.yaml");
+    let old = std::time::SystemTime::now() - Duration::from_secs(3600);
+    let set_modified = |time| {
+        std::fs::File::options()
+            .write(true)
+            .open(&goals_yaml)
+            .unwrap()
+            .set_modified(time)
+            .unwrap()
+    };
+    set_modified(old);
+    assert_eq!(archive.list_goals(None, None).unwrap()[0].name, "Read");
+
+    // An unchanged fingerprint is not read again.
+    let content = std::fs::read_to_string(&goals_yaml).unwrap();
+    std::fs::write(&goals_yaml, content.replace("Read", "Sing")).unwrap();
+    set_modified(old);
+    assert_eq!(archive.list_goals(None, None).unwrap()[0].name, "Read");
+
+    set_modified(std::time::SystemTime::now());
+    assert_eq!(archive.list_goals(None, None).unwrap()[0].name, "Sing");
+}
+
+#[test]
+fn opening_a_missing_archive_without_create_fails() {
+    let temp = temp_archive_dir();
+    let path = temp.path().join("missing").to_str().unwrap().to_string();
+    let options = ArchiveOptions {
+        create_if_missing: false,
+        ..Default::default()
+    };
+
+    let err = Archive::open(path.clone(), Some(options)).err().unwrap();
+    assert!(matches!(err, Error::NotFound { .. }));
+
+    Archive::open(path.clone(), None).unwrap();
+    assert!(temp.path().join("missing").join("goals.yaml").exists());
+}
+
+struct Recorder(Mutex<Vec<ArchiveEvent>>);
+
+impl ArchiveListener for Recorder {
+    fn on_event(&self, event: ArchiveEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+fn wait_for(recorder: &Recorder, expected: &ArchiveEvent) -> bool {
+    for _ in 0..50 {
+        if recorder.0.lock().unwrap().contains(expected) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn subscribers_receive_local_and_external_changes() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let archive = Archive::open(path.clone(), None).unwrap();
+    let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+    let subscription = archive.subscribe(recorder.clone());
+
+    let goal = add_goal(path.clone(), "Local".into(), false, vec![], None).unwrap();
+    assert!(recorder
+        .0
+        .lock()
+        .unwrap()
+        .contains(&ArchiveEvent::GoalAdded { goal_id: goal.id }));
+
+    // Another process appends a goal and writes a note.
+    let goals_yaml = temp.path().join("goals.yaml");
+    let mut content = std::fs::read_to_string(&goals_yaml).unwrap();
+    content.push_str("- id: 42\n  name: External\n");
+    std::fs::write(&goals_yaml, content).unwrap();
+    std::fs::write(temp.path().join("notes").join("goal_42.md"), "hi\n").unwrap();
+
+    assert!(wait_for(
+        &recorder,
+        &ArchiveEvent::GoalAdded { goal_id: 42 }
+    ));
+    assert!(wait_for(
+        &recorder,
+        &ArchiveEvent::NoteEdited { goal_id: 42 }
+    ));
+
+    // Our own write was not reported a second time by the watcher.
+    let local_added = recorder
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| **e == ArchiveEvent::GoalAdded { goal_id: goal.id })
+        .count();
+    assert_eq!(local_added, 1);
+
+    archive.unsubscribe(subscription);
+    archive.set_goal_trashed(goal.id, true).unwrap();
+    assert!(!recorder
+        .0
+        .lock()
+        .unwrap()
+        .contains(&ArchiveEvent::GoalTrashed {
+            goal_id: goal.id,
+            trashed: true
+        }));
+}
+
+#[test]
+fn local_writes_do_not_swallow_external_changes_to_other_files() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let archive = Archive::open(path.clone(), None).unwrap();
+    let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+    let subscription = archive.subscribe(recorder.clone());
+
+    // Another process writes a note just before our own write, which only
+    // refreshes the files it touched.
+    std::fs::write(temp.path().join("notes").join("goal_7.md"), "hi\n").unwrap();
+    add_goal(path, "Local".into(), false, vec![], None).unwrap();
+
+    assert!(wait_for(
+        &recorder,
+        &ArchiveEvent::NoteEdited { goal_id: 7 }
+    ));
+    archive.unsubscribe(subscription);
+}
+
+#[test]
+fn resubscribing_watches_session_notes_once() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let archive = Archive::open(path, None).unwrap();
+    let first = archive.subscribe(Arc::new(Recorder(Mutex::new(Vec::new()))));
+    archive.unsubscribe(first);
+    let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+    let subscription = archive.subscribe(recorder.clone());
+
+    let day = temp.path().join("notes/sessions/2024-05-01");
+    std::fs::create_dir_all(&day).unwrap();
+    std::fs::write(day.join("sess_2.md"), "Felt good\n").unwrap();
+    let expected = ArchiveEvent::SessionUpdated {
+        date_iso: "2024-05-01".into(),
+        session_id: "sess_2".into(),
+    };
+    assert!(wait_for(&recorder, &expected));
+
+    // The change is reported once, by the one watcher of the archive.
+    std::thread::sleep(Duration::from_millis(1200));
+    let seen = recorder
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| **e == expected)
+        .count();
+    assert_eq!(seen, 1);
+    archive.unsubscribe(subscription);
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use successlib::{
+    add_goal, add_session, apply_batch, get_note, list_day_sessions, list_goals, BatchOp,
+    BatchResult, Error, GoalStatus, SessionMetadata,
+};
+
+use common::{temp_archive, yesterday_at};
+
+/// Every file below `dir` with its content, by path relative to `root`.
+fn files(root: &Path, dir: &Path, out: &mut BTreeMap<String, String>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files(root, &path, out);
+        } else {
+            let name = path.strip_prefix(root).unwrap().display().to_string();
+            out.insert(name, std::fs::read_to_string(&path).unwrap());
+        }
+    }
+}
+
+fn contents(archive: &Path) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    files(archive, archive, &mut out);
+    out
+}
+
+#[test]
+fn batch_operations_see_each_other() {
+    let (_temp, path) = temp_archive();
+    let start = yesterday_at(10);
+    let ops = vec![
+        BatchOp::AddGoal {
+            name: "Guitar".into(),
+            is_reward: false,
+            commands: vec![],
+            quantity_name: Some("songs".into()),
+        },
+        BatchOp::AddSession {
+            goal_id: 1,
+            goal_name: "Guitar".into(),
+            start_ts_secs: start,
+            duration_secs: 1800,
+            is_reward: false,
+            quantity: Some(2),
+            metadata: Some(SessionMetadata {
+                note: Some("Learned the intro".into()),
+                ..Default::default()
+            }),
+        },
+        BatchOp::AddSession {
+            goal_id: 1,
+            goal_name: "Guitar".into(),
+            start_ts_secs: start + 3600,
+            duration_secs: 600,
+            is_reward: false,
+            quantity: None,
+            metadata: None,
+        },
+        BatchOp::EditNote {
+            goal_id: 1,
+            content: "# Guitar\n".into(),
+        },
+        BatchOp::SetPinned {
+            goal_id: 1,
+            pinned: true,
+        },
+    ];
+    let results = apply_batch(path.clone(), ops, None).unwrap();
+    assert_eq!(results.len(), 5);
+    let BatchResult::Session { session } = &results[1] else {
+        panic!("{:?}", results[1]);
+    };
+    assert_eq!(session.quantity, Some(2));
+    let BatchResult::Goal { goal } = &results[4] else {
+        panic!("{:?}", results[4]);
+    };
+    // `AddSession` marked the goal as started before it was pinned.
+    assert!(goal.pinned);
+    assert_eq!(goal.status, GoalStatus::DOING);
+
+    let goals = list_goals(path.clone(), None, None).unwrap();
+    assert_eq!(goals, vec![goal.clone()]);
+    let date = successlib::timestamp_to_date_iso(start);
+    assert_eq!(list_day_sessions(path.clone(), date).unwrap().len(), 2);
+    assert_eq!(get_note(path, 1).unwrap(), "# Guitar\n");
+}
+
+#[test]
+fn failing_operation_leaves_the_archive_unchanged() {
+    let (temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Run".into(), false, vec![], None).unwrap();
+    let start = yesterday_at(10);
+    add_session(
+        path.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        1800,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let before = contents(temp.path());
+
+    let ops = vec![
+        BatchOp::AddGoal {
+            name: "Swim".into(),
+            is_reward: false,
+            commands: vec![],
+            quantity_name: None,
+        },
+        BatchOp::SetGoalStatus {
+            goal_id: goal.id,
+            status: GoalStatus::DONE,
+        },
+        BatchOp::EditNote {
+            goal_id: goal.id,
+            content: "changed".into(),
+        },
+        BatchOp::AddSession {
+            goal_id: 2,
+            goal_name: "Swim".into(),
+            start_ts_secs: yesterday_at(14),
+            duration_secs: 600,
+            is_reward: false,
+            quantity: None,
+            metadata: None,
+        },
+        // Overlaps the session recorded above.
+        BatchOp::AddSession {
+            goal_id: goal.id,
+            goal_name: goal.name.clone(),
+            start_ts_secs: start + 600,
+            duration_secs: 600,
+            is_reward: false,
+            quantity: None,
+            metadata: None,
+        },
+    ];
+    let err = apply_batch(path.clone(), ops, None).unwrap_err();
+    assert!(matches!(err, Error::SessionOverlap { .. }), "{err:?}");
+    assert_eq!(contents(temp.path()), before);
+
+    let err = apply_batch(
+        path,
+        vec![BatchOp::SetPinned {
+            goal_id: 9,
+            pinned: true,
+        }],
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::NotFound { .. }), "{err:?}");
+    assert_eq!(contents(temp.path()), before);
+}
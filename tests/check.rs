@@ -0,0 +1,147 @@
+mod common;
+
+use std::fs;
+
+use successlib::{check_archive, list_day_sessions, list_goals, IssueKind};
+
+use common::temp_archive_dir;
+
+fn kinds(report: &successlib::CheckReport) -> Vec<(IssueKind, bool)> {
+    report.issues.iter().map(|i| (i.kind, i.repaired)).collect()
+}
+
+/// An archive with one of every repairable problem, plus an orphan note.
+fn write_damaged_archive(root: &std::path::Path) {
+    fs::create_dir_all(root.join("graphs")).unwrap();
+    fs::create_dir_all(root.join("notes")).unwrap();
+    fs::write(
+        root.join("goals.yaml"),
+        "- id: 1\n  name: Read\n- id: 2\n  name: Run\n- id: 2\n  name: Write\n",
+    )
+    .unwrap();
+    // sess_2 is not linked, and a session of "Run" lost its goal id.
+    fs::write(
+        root.join("graphs/2024-03-01.mmd"),
+        "stateDiagram-v2\n\
+         \x20   [*] --> sess_1\n\
+         \x20   sess_1 : Read [id 1] [09#colon;00-09#colon;30]\n\
+         \x20   sess_2 : Read [id 1] [10#colon;00-10#colon;30]\n\
+         \x20   sess_3 : Run [11#colon;00-11#colon;30]\n\
+         \x20   sess_1 --> sess_3\n",
+    )
+    .unwrap();
+    // Two sessions share an id, in a file with a non-canonical name.
+    fs::write(
+        root.join("graphs/2024-3-2.mmd"),
+        "stateDiagram-v2\n\
+         \x20   [*] --> sess_1\n\
+         \x20   sess_1 : Read [id 1] [09#colon;00-09#colon;30]\n\
+         \x20   sess_1 : Read [id 1] [10#colon;00-10#colon;30]\n",
+    )
+    .unwrap();
+    fs::write(root.join("notes/goal_2.md"), "Run or Write?\n").unwrap();
+    fs::write(root.join("notes/goal_9.md"), "left behind\n").unwrap();
+}
+
+#[test]
+fn check_reports_problems_without_changing_anything() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    write_damaged_archive(temp.path());
+    let goals_before = fs::read_to_string(temp.path().join("goals.yaml")).unwrap();
+
+    let report = check_archive(path, false).unwrap();
+    assert_eq!(
+        kinds(&report),
+        vec![
+            (IssueKind::DuplicateGoalId, false),
+            (IssueKind::MalformedGraphName, false),
+            (IssueKind::BrokenChain, false),
+            (IssueKind::UnknownGoal, false),
+            (IssueKind::OrphanNote, false),
+        ]
+    );
+    assert_eq!(report.issues[0].goal_id, Some(2));
+    assert_eq!(report.issues[3].goal_id, Some(0));
+    assert_eq!(report.issues[4].path, "notes/goal_9.md");
+    assert_eq!(
+        fs::read_to_string(temp.path().join("goals.yaml")).unwrap(),
+        goals_before
+    );
+    assert!(temp.path().join("graphs/2024-3-2.mmd").exists());
+}
+
+#[test]
+fn repair_fixes_what_is_safe_and_leaves_the_rest() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    write_damaged_archive(temp.path());
+
+    let report = check_archive(path.clone(), true).unwrap();
+    assert_eq!(
+        kinds(&report),
+        vec![
+            (IssueKind::DuplicateGoalId, false),
+            (IssueKind::MalformedGraphName, true),
+            (IssueKind::BrokenChain, true),
+            (IssueKind::UnknownGoal, true),
+            (IssueKind::DuplicateSessionId, true),
+            (IssueKind::OrphanNote, false),
+        ]
+    );
+
+    // Duplicate goal ids are left for the user: the note and sessions of
+    // goal 2 could belong to either goal.
+    let goals = list_goals(path.clone(), None, None).unwrap();
+    let ids: Vec<u64> = goals.iter().map(|g| g.id).collect();
+    assert_eq!(ids, vec![1, 2, 2]);
+    assert_eq!(
+        fs::read_to_string(temp.path().join("notes/goal_2.md")).unwrap(),
+        "Run or Write?\n"
+    );
+
+    let first = list_day_sessions(path.clone(), "2024-03-01".into()).unwrap();
+    assert_eq!(first.len(), 3);
+    assert_eq!(first[2].goal_id, 2);
+    let second = list_day_sessions(path.clone(), "2024-03-02".into()).unwrap();
+    let second_ids: Vec<&str> = second.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(second_ids, vec!["sess_1", "sess_2"]);
+
+    // Only the duplicate goal id and the orphan note are left, both alone.
+    let again = check_archive(path, true).unwrap();
+    assert_eq!(
+        kinds(&again),
+        vec![
+            (IssueKind::DuplicateGoalId, false),
+            (IssueKind::OrphanNote, false)
+        ]
+    );
+    assert!(temp.path().join("notes/goal_9.md").exists());
+}
+
+#[test]
+fn days_with_unparsable_nodes_are_not_rewritten() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    fs::create_dir_all(temp.path().join("graphs")).unwrap();
+    fs::write(temp.path().join("goals.yaml"), "- id: 1\n  name: Read\n").unwrap();
+    let graph = "stateDiagram-v2\n\
+                 \x20   [*] --> sess_1\n\
+                 \x20   sess_1 : Read [id 1] [09#colon;00-09#colon;30]\n\
+                 \x20   sess_2 : Read [id 1] [whenever]\n\
+                 \x20   sess_3 : Read [id 1] [11#colon;00-11#colon;30]\n";
+    fs::write(temp.path().join("graphs/2024-03-01.mmd"), graph).unwrap();
+
+    let report = check_archive(path, true).unwrap();
+    assert_eq!(
+        kinds(&report),
+        vec![
+            (IssueKind::MalformedSession, false),
+            (IssueKind::BrokenChain, false),
+        ]
+    );
+    assert_eq!(
+        fs::read_to_string(temp.path().join("graphs/2024-03-01.mmd")).unwrap(),
+        graph
+    );
+}
@@ -0,0 +1,47 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use tempfile::TempDir;
+
+/// An empty directory to use as an archive.
+pub fn temp_archive_dir() -> TempDir {
+    tempfile::tempdir().expect("create temp archive")
+}
+
+/// An empty archive directory and its path.
+pub fn temp_archive() -> (TempDir, String) {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    (temp, path)
+}
+
+/// Timestamp of `hour` o'clock local time, `days` days ago.
+pub fn days_ago_at(days: i64, hour: u32) -> i64 {
+    let day = Local::now().date_naive() - Duration::days(days);
+    Local
+        .from_local_datetime(&day.and_hms_opt(hour, 0, 0).unwrap())
+        .single()
+        .unwrap()
+        .timestamp()
+}
+
+pub fn yesterday_at(hour: u32) -> i64 {
+    days_ago_at(1, hour)
+}
+
+/// A start timestamp at 10:00 local time yesterday, far from midnight so
+/// sessions built on top of it stay on the same day file.
+pub fn yesterday_at_ten() -> i64 {
+    yesterday_at(10)
+}
+
+/// Timestamp of a local time in March 2024.
+pub fn local_ts(day: u32, hour: u32, minute: u32) -> i64 {
+    let date = NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+    Local
+        .from_local_datetime(&date.and_hms_opt(hour, minute, 0).unwrap())
+        .single()
+        .unwrap()
+        .timestamp()
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, Local};
+use successlib::{add_goal, add_session, export_goals, export_sessions, ExportFormat, ExportSink};
+use tempfile::TempDir;
+
+use common::{days_ago_at, temp_archive};
+
+#[derive(Default)]
+struct Collect(Mutex<Vec<String>>);
+
+impl ExportSink for Collect {
+    fn write(&self, chunk: String) {
+        self.0.lock().unwrap().push(chunk);
+    }
+}
+
+impl Collect {
+    fn chunks(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+fn date_iso(days: i64) -> String {
+    (Local::now().date_naive() - Duration::days(days))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// "Push-ups" (with a unit) twice, two days ago and yesterday, and a reward
+/// whose name needs quoting in CSV.
+fn archive_with_history() -> (TempDir, String) {
+    let (temp, path) = temp_archive();
+    let pushups = add_goal(
+        path.clone(),
+        "Push-ups".into(),
+        false,
+        vec![],
+        Some("reps".into()),
+    )
+    .unwrap();
+    let tv = add_goal(path.clone(), "TV, \"a bit\"".into(), true, vec![], None).unwrap();
+    for (days, hour) in [(2, 9), (1, 9)] {
+        add_session(
+            path.clone(),
+            pushups.id,
+            pushups.name.clone(),
+            days_ago_at(days, hour),
+            600,
+            false,
+            Some(30),
+            None,
+            None,
+        )
+        .unwrap();
+    }
+    add_session(
+        path.clone(),
+        tv.id,
+        tv.name,
+        days_ago_at(1, 20),
+        1800,
+        true,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    (temp, path)
+}
+
+#[test]
+fn sessions_export_as_csv_one_day_per_chunk() {
+    let (_temp, path) = archive_with_history();
+    let sink = Arc::new(Collect::default());
+
+    let count = export_sessions(path, None, None, ExportFormat::Csv, sink.clone()).unwrap();
+    assert_eq!(count, 3);
+    let chunks = sink.chunks();
+    assert_eq!(chunks.len(), 3, "header plus one chunk per day");
+    assert_eq!(
+        chunks[0],
+        "date,start,end,duration_secs,goal_id,goal_name,kind,quantity,quantity_unit\r\n"
+    );
+    let day = date_iso(2);
+    assert_eq!(
+        chunks[1],
+        format!("{day},{day} 09:00:00,{day} 09:10:00,600,1,Push-ups,Goal,30,reps\r\n")
+    );
+    let lines: Vec<&str> = chunks[2].lines().collect();
+    assert_eq!(lines.len(), 2);
+    let day = date_iso(1);
+    assert_eq!(
+        lines[1],
+        format!("{day},{day} 20:00:00,{day} 20:30:00,1800,2,\"TV, \"\"a bit\"\"\",Reward,,")
+    );
+}
+
+#[test]
+fn sessions_export_as_json_lines_within_a_range() {
+    let (_temp, path) = archive_with_history();
+    let sink = Arc::new(Collect::default());
+    let day = date_iso(1);
+
+    let count = export_sessions(
+        path,
+        Some(day.clone()),
+        Some(day.clone()),
+        ExportFormat::JsonLines,
+        sink.clone(),
+    )
+    .unwrap();
+    assert_eq!(count, 2);
+    let text = sink.chunks().concat();
+    let rows: Vec<serde_json::Value> = text
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["date"], day.as_str());
+    assert_eq!(rows[0]["goal_name"], "Push-ups");
+    assert_eq!(rows[0]["quantity"], 30);
+    assert_eq!(rows[0]["quantity_unit"], "reps");
+    assert_eq!(rows[1]["kind"], "Reward");
+    assert!(rows[1]["quantity"].is_null());
+}
+
+#[test]
+fn goals_export_includes_units_and_commands() {
+    let (_temp, path) = temp_archive();
+    add_goal(
+        path.clone(),
+        "Read".into(),
+        false,
+        vec!["open book.pdf".into(), "timer 25".into()],
+        Some("pages".into()),
+    )
+    .unwrap();
+    let sink = Arc::new(Collect::default());
+
+    let count = export_goals(path, ExportFormat::Csv, sink.clone()).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(
+        sink.chunks().concat(),
+        "id,name,is_reward,status,trashed,quantity_unit,commands\r\n\
+         1,Read,false,TODO,false,pages,\"open book.pdf\ntimer 25\"\r\n"
+    );
+}
+
+#[test]
+fn csv_fields_that_look_like_formulas_are_escaped() {
+    let (_temp, path) = temp_archive();
+    add_goal(
+        path.clone(),
+        "=HYPERLINK(\"x\")".into(),
+        false,
+        vec![],
+        None,
+    )
+    .unwrap();
+    add_goal(path.clone(), "@home".into(), false, vec![], None).unwrap();
+
+    let csv = Arc::new(Collect::default());
+    export_goals(path.clone(), ExportFormat::Csv, csv.clone()).unwrap();
+    assert_eq!(
+        csv.chunks().concat(),
+        "id,name,is_reward,status,trashed,quantity_unit,commands\r\n\
+         1,\"'=HYPERLINK(\"\"x\"\")\",false,TODO,false,,\r\n\
+         2,'@home,false,TODO,false,,\r\n"
+    );
+
+    // JSON Lines are not meant for spreadsheets and keep the names as they are.
+    let json = Arc::new(Collect::default());
+    export_goals(path, ExportFormat::JsonLines, json.clone()).unwrap();
+    assert!(json.chunks().concat().contains("\"name\":\"@home\""));
+}
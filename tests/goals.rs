@@ -1,7 +1,7 @@
 use chrono::Utc;
 use successlib::{
-    add_goal, add_session, list_goals, list_trash, search_goals, set_goal_status, set_goal_trashed,
-    GoalStatus,
+    add_goal, add_session, list_goals, list_trash, move_goal, search_goals, search_goals_scored,
+    set_goal_status, set_goal_trashed, set_pinned, Error, GoalSort, GoalStatus,
 };
 use tempfile::TempDir;
 
@@ -18,15 +18,20 @@ fn list_goals_filters_by_status() {
     let g2 = add_goal(archive.clone(), "Goal 2".into(), false, vec![], None).unwrap();
     set_goal_status(archive.clone(), g1.id, GoalStatus::DONE).unwrap();
 
-    let default_visible = list_goals(archive.clone(), None).unwrap();
+    let default_visible = list_goals(archive.clone(), None, None).unwrap();
     assert_eq!(default_visible.len(), 1);
     assert_eq!(default_visible[0].id, g2.id);
 
-    let done_only = list_goals(archive.clone(), Some(vec![GoalStatus::DONE])).unwrap();
+    let done_only = list_goals(archive.clone(), Some(vec![GoalStatus::DONE]), None).unwrap();
     assert_eq!(done_only.len(), 1);
     assert_eq!(done_only[0].id, g1.id);
 
-    let union = list_goals(archive.clone(), Some(vec![GoalStatus::DONE, GoalStatus::TODO])).unwrap();
+    let union = list_goals(
+        archive.clone(),
+        Some(vec![GoalStatus::DONE, GoalStatus::TODO]),
+        None,
+    )
+    .unwrap();
     let mut ids: Vec<u64> = union.into_iter().map(|g| g.id).collect();
     ids.sort_unstable();
     assert_eq!(ids, vec![g1.id, g2.id]);
@@ -72,10 +77,12 @@ fn adding_session_moves_goal_to_doing() {
         600,
         false,
         None,
+        None,
+        None,
     )
     .unwrap();
 
-    let goals = list_goals(archive.clone(), Some(vec![GoalStatus::DOING])).unwrap();
+    let goals = list_goals(archive.clone(), Some(vec![GoalStatus::DOING]), None).unwrap();
     assert_eq!(goals.len(), 1);
     assert_eq!(goals[0].id, goal.id);
     assert_eq!(goals[0].status, GoalStatus::DOING);
@@ -90,7 +97,7 @@ fn trashed_goals_are_hidden_and_listtrash_works() {
     let active = add_goal(archive.clone(), "New goal".into(), false, vec![], None).unwrap();
     set_goal_trashed(archive.clone(), trashed.id, true).unwrap();
 
-    let visible = list_goals(archive.clone(), None).unwrap();
+    let visible = list_goals(archive.clone(), None, None).unwrap();
     assert_eq!(visible.len(), 1);
     assert_eq!(visible[0].id, active.id);
 
@@ -119,6 +126,8 @@ fn reward_session_parsing_preserves_goal_id() {
         300,
         true,
         None,
+        None,
+        None,
     )
     .unwrap();
 
@@ -126,3 +135,166 @@ fn reward_session_parsing_preserves_goal_id() {
     assert_eq!(sessions.len(), 1);
     assert_eq!(sessions[0].goal_id, reward.id);
 }
+
+#[test]
+fn search_goals_scored_reports_indices_and_recency() {
+    let temp = temp_archive();
+    let archive = temp.path().to_str().unwrap().to_string();
+
+    let guitar = add_goal(
+        archive.clone(),
+        "Guitar practice".into(),
+        false,
+        vec![],
+        None,
+    )
+    .unwrap();
+    let gym = add_goal(archive.clone(), "Gym".into(), false, vec![], None).unwrap();
+    add_goal(archive.clone(), "Chess".into(), false, vec![], None).unwrap();
+    add_session(
+        archive.clone(),
+        guitar.id,
+        guitar.name.clone(),
+        Utc::now().timestamp() - 3600,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let scored =
+        search_goals_scored(archive.clone(), "gym".into(), None, None, Some(true)).unwrap();
+    let plain = search_goals(archive.clone(), "gym".into(), None, None, Some(true)).unwrap();
+    let ids: Vec<u64> = scored.iter().map(|s| s.goal.id).collect();
+    assert_eq!(ids, plain.iter().map(|g| g.id).collect::<Vec<_>>());
+    assert_eq!(ids, vec![gym.id]);
+    assert_eq!(scored[0].indices, vec![0, 1, 2]);
+    assert!(scored[0].score > 0);
+    assert!(!scored[0].recency_boosted);
+
+    // Both names start with a "g"; the guitar session puts it first.
+    let scored = search_goals_scored(archive.clone(), "g".into(), None, None, Some(true)).unwrap();
+    assert_eq!(
+        scored.iter().map(|s| s.goal.id).collect::<Vec<_>>(),
+        vec![guitar.id, gym.id]
+    );
+    assert!(scored[0].recency_boosted);
+    assert_eq!(scored[0].indices, vec![0]);
+    assert!(!scored[1].recency_boosted);
+
+    let unsorted =
+        search_goals_scored(archive.clone(), "".into(), None, None, Some(false)).unwrap();
+    assert_eq!(unsorted.len(), 3);
+    assert!(unsorted
+        .iter()
+        .all(|s| s.score == 0 && s.indices.is_empty() && !s.recency_boosted));
+}
+
+fn names(archive: &str, sort: GoalSort) -> Vec<String> {
+    list_goals(archive.to_string(), None, Some(sort))
+        .unwrap()
+        .into_iter()
+        .map(|g| g.name)
+        .collect()
+}
+
+#[test]
+fn goals_can_be_reordered_and_pinned() {
+    let temp = temp_archive();
+    let archive = temp.path().to_str().unwrap().to_string();
+
+    let read = add_goal(archive.clone(), "read".into(), false, vec![], None).unwrap();
+    let write = add_goal(archive.clone(), "Write".into(), false, vec![], None).unwrap();
+    let run = add_goal(archive.clone(), "Run".into(), false, vec![], None).unwrap();
+    assert!(read.sort_order < write.sort_order && write.sort_order < run.sort_order);
+
+    move_goal(archive.clone(), run.id, Some(read.id)).unwrap();
+    move_goal(archive.clone(), read.id, None).unwrap();
+    let keys = || -> Vec<String> {
+        list_goals(archive.clone(), None, None)
+            .unwrap()
+            .into_iter()
+            .map(|g| g.sort_order)
+            .collect()
+    };
+    let before = keys();
+    let moved = move_goal(archive.clone(), write.id, Some(read.id)).unwrap();
+    assert_eq!(names(&archive, GoalSort::Manual), ["Run", "Write", "read"]);
+    // Only the moved goal got a new key.
+    let after = keys();
+    assert_eq!((&after[0], &after[2]), (&before[0], &before[2]));
+    assert_eq!(after[1], moved.sort_order);
+
+    let pinned = set_pinned(archive.clone(), read.id, true).unwrap();
+    assert!(pinned.pinned);
+    assert_eq!(names(&archive, GoalSort::Manual), ["read", "Run", "Write"]);
+    assert_eq!(names(&archive, GoalSort::Archive), ["read", "Write", "Run"]);
+    assert_eq!(names(&archive, GoalSort::Name), ["read", "Run", "Write"]);
+
+    // Repeated moves to the front keep producing valid, distinct keys.
+    for _ in 0..20 {
+        move_goal(archive.clone(), write.id, Some(run.id)).unwrap();
+        move_goal(archive.clone(), run.id, Some(write.id)).unwrap();
+    }
+    assert_eq!(names(&archive, GoalSort::Manual), ["read", "Run", "Write"]);
+
+    let err = move_goal(archive.clone(), run.id, Some(run.id)).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+    let err = move_goal(archive.clone(), run.id, Some(42)).unwrap_err();
+    assert!(matches!(err, Error::NotFound { .. }), "{err:?}");
+}
+
+#[test]
+fn goals_without_sort_order_are_keyed_on_first_move() {
+    let temp = temp_archive();
+    let archive = temp.path().to_str().unwrap().to_string();
+    std::fs::write(
+        temp.path().join("goals.yaml"),
+        "- id: 1\n  name: A\n- id: 2\n  name: B\n- id: 3\n  name: C\n",
+    )
+    .unwrap();
+    add_goal(archive.clone(), "D".into(), false, vec![], None).unwrap();
+    assert_eq!(names(&archive, GoalSort::Manual), ["A", "B", "C", "D"]);
+
+    move_goal(archive.clone(), 1, Some(3)).unwrap();
+    assert_eq!(names(&archive, GoalSort::Manual), ["B", "A", "C", "D"]);
+    let goals = list_goals(archive.clone(), None, None).unwrap();
+    assert!(goals.iter().all(|g| !g.sort_order.is_empty()));
+
+    add_session(
+        archive.clone(),
+        3,
+        "C".into(),
+        Utc::now().timestamp() - 7200,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    add_session(
+        archive.clone(),
+        4,
+        "D".into(),
+        Utc::now().timestamp() - 3600,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let recent: Vec<String> = list_goals(
+        archive.clone(),
+        Some(vec![GoalStatus::TODO, GoalStatus::DOING]),
+        Some(GoalSort::Recent),
+    )
+    .unwrap()
+    .into_iter()
+    .map(|g| g.name)
+    .collect();
+    assert_eq!(recent, ["D", "C", "A", "B"]);
+}
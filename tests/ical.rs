@@ -0,0 +1,169 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, Local};
+use successlib::{
+    add_goal, add_session, export_ics, import_ics, list_sessions_between_dates, ExportSink,
+    IcsImportOptions,
+};
+
+use common::{temp_archive, yesterday_at};
+
+#[derive(Default)]
+struct Collect(Mutex<String>);
+
+impl ExportSink for Collect {
+    fn write(&self, chunk: String) {
+        self.0.lock().unwrap().push_str(&chunk);
+    }
+}
+
+/// Yesterday at `hour`, as a floating (local) iCalendar time.
+fn floating(hour: u32) -> String {
+    let day = Local::now().date_naive() - Duration::days(1);
+    format!("{}T{hour:02}0000", day.format("%Y%m%d"))
+}
+
+#[test]
+fn exported_sessions_import_into_another_archive() {
+    let (_source, source) = temp_archive();
+    let pushups = add_goal(
+        source.clone(),
+        "Push-ups, daily".into(),
+        false,
+        vec![],
+        Some("reps".into()),
+    )
+    .unwrap();
+    add_session(
+        source.clone(),
+        pushups.id,
+        pushups.name,
+        yesterday_at(9),
+        600,
+        false,
+        Some(40),
+        None,
+        None,
+    )
+    .unwrap();
+    let sink = Arc::new(Collect::default());
+    assert_eq!(export_ics(source, None, None, sink.clone()).unwrap(), 1);
+    let ics = sink.0.lock().unwrap().clone();
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.contains("SUMMARY:Push-ups\\, daily\r\n"));
+    assert!(ics.contains("DESCRIPTION:Kind: Goal\\nQuantity: 40 reps\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+
+    let (_target, target) = temp_archive();
+    add_goal(target.clone(), "Read".into(), false, vec![], None).unwrap();
+    add_goal(
+        target.clone(),
+        "push-ups, daily".into(),
+        false,
+        vec![],
+        Some("reps".into()),
+    )
+    .unwrap();
+    let report = import_ics(target.clone(), ics.clone(), None).unwrap();
+    assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+    let sessions = list_sessions_between_dates(target.clone(), None, None).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].goal_id, 2);
+    assert_eq!(sessions[0].start_at, yesterday_at(9));
+    assert_eq!(sessions[0].end_at - sessions[0].start_at, 600);
+    assert_eq!(sessions[0].quantity, Some(40));
+
+    // A second import overlaps the first and records nothing.
+    let again = import_ics(target, ics, None).unwrap();
+    assert!(again.imported.is_empty());
+    assert_eq!(again.skipped.len(), 1);
+    assert!(again.skipped[0].reason.contains("overlaps"));
+}
+
+#[test]
+fn calendar_events_are_matched_by_mapping_or_name() {
+    let (_temp, path) = temp_archive();
+    add_goal(path.clone(), "Deep work".into(), false, vec![], None).unwrap();
+    add_goal(path.clone(), "Gaming".into(), true, vec![], None).unwrap();
+    let ics = format!(
+        "BEGIN:VCALENDAR\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:a\r\n\
+         SUMMARY:Focus block with a very long title that a calendar app folds o\r\n \
+         ver two lines\r\n\
+         DTSTART:{}\r\n\
+         DURATION:PT1H30M\r\n\
+         BEGIN:VALARM\r\n\
+         SUMMARY:Reminder\r\n\
+         END:VALARM\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:b\r\n\
+         SUMMARY:GAMING\r\n\
+         DTSTART;TZID=Europe/Berlin:{}\r\n\
+         DTEND;TZID=Europe/Berlin:{}\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:c\r\n\
+         SUMMARY:Holiday\r\n\
+         DTSTART;VALUE=DATE:20240501\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:d\r\n\
+         SUMMARY:Dentist\r\n\
+         DTSTART:{}\r\n\
+         DTEND:{}\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:e\r\n\
+         SUMMARY:Deep work\r\n\
+         DTSTART:{}\r\n\
+         DURATION:P99999999999999W\r\n\
+         END:VEVENT\r\n\
+         END:VCALENDAR\r\n",
+        floating(8),
+        floating(20),
+        floating(21),
+        floating(14),
+        floating(15),
+        floating(16),
+    );
+    let options = IcsImportOptions {
+        goal_mapping: HashMap::from([(
+            "Focus block with a very long title that a calendar app folds over two lines".into(),
+            1,
+        )]),
+        ..Default::default()
+    };
+
+    let report = import_ics(path.clone(), ics, Some(options)).unwrap();
+    let imported: Vec<(u64, i64, i64)> = report
+        .imported
+        .iter()
+        .map(|s| (s.goal_id, s.start_at, s.end_at - s.start_at))
+        .collect();
+    assert_eq!(
+        imported,
+        vec![(1, yesterday_at(8), 5400), (2, yesterday_at(20), 3600)]
+    );
+    let skipped: Vec<(Option<&str>, &str)> = report
+        .skipped
+        .iter()
+        .map(|s| (s.uid.as_deref(), s.reason.as_str()))
+        .collect();
+    assert_eq!(
+        skipped,
+        vec![
+            (Some("c"), "all-day events are not sessions"),
+            (Some("d"), "no goal matches \"Dentist\""),
+            (Some("e"), "duration P99999999999999W is too long"),
+        ]
+    );
+    assert_eq!(
+        list_sessions_between_dates(path, None, None).unwrap().len(),
+        2
+    );
+}
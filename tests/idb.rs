@@ -0,0 +1,47 @@
+//! Browser tests of the IndexedDB backend; run with `wasm-pack test --headless --chrome`.
+#![cfg(target_arch = "wasm32")]
+
+use successlib::{add_goal, list_goals};
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn local_storage() -> web_sys::Storage {
+    web_sys::window().unwrap().local_storage().unwrap().unwrap()
+}
+
+#[wasm_bindgen_test]
+async fn mounting_leaves_archives_with_a_longer_name_alone() {
+    local_storage().clear().unwrap();
+    add_goal("foo".into(), "Run".into(), false, vec![], None).unwrap();
+    add_goal("foo_bar".into(), "Swim".into(), false, vec![], None).unwrap();
+    successlib::edit_note("foo_bar".into(), 1, "# Swim\n".into()).unwrap();
+
+    successlib::wasm::use_indexed_db("foo".into())
+        .await
+        .unwrap();
+    successlib::wasm::flush_storage().await.unwrap();
+
+    // Only the keys of "foo" moved to IndexedDB.
+    assert!(local_storage()
+        .get_item("foo_goals.yaml")
+        .unwrap()
+        .is_none());
+    assert!(local_storage()
+        .get_item("foo_bar_goals.yaml")
+        .unwrap()
+        .is_some());
+    assert!(local_storage()
+        .get_item("foo_bar_notes__goal_1.md")
+        .unwrap()
+        .is_some());
+
+    let foo = list_goals("foo".into(), None, None).unwrap();
+    let foo_bar = list_goals("foo_bar".into(), None, None).unwrap();
+    assert_eq!(foo[0].name, "Run");
+    assert_eq!(foo_bar[0].name, "Swim");
+    assert_eq!(
+        successlib::get_note("foo_bar".into(), 1).unwrap(),
+        "# Swim\n"
+    );
+}
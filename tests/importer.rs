@@ -0,0 +1,237 @@
+mod common;
+
+use successlib::{
+    add_goal, add_session, import_tracker_csv, list_goals, list_sessions_between_dates, CsvColumns,
+    Error, OverlapPolicy, SessionValidation, TrackerFormat, TrackerImportOptions,
+};
+
+use common::{local_ts, temp_archive};
+
+fn all_sessions(path: &str) -> Vec<(u64, i64, i64)> {
+    list_sessions_between_dates(
+        path.into(),
+        Some("2024-03-01".into()),
+        Some("2024-03-31".into()),
+    )
+    .unwrap()
+    .into_iter()
+    .map(|s| (s.goal_id, s.start_at, s.end_at))
+    .collect()
+}
+
+const TOGGL: &str = "\u{feff}User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags\n\
+    Ann,ann@example.com,,Reading,,Chapter 4,No,2024-03-04,09:00:00,2024-03-04,09:45:00,00:45:00,\n\
+    Ann,ann@example.com,,,,\"Inbox, email\",No,2024-03-04,23:30:00,2024-03-05,00:15:00,00:45:00,\n\
+    Ann,ann@example.com,,Reading,,Chapter 5,No,2024-03-05,08:00:00,2024-03-05,08:30:00,00:30:00,\n\
+    Ann,ann@example.com,,Reading,,,No,2024-03-05,not a time,2024-03-05,09:00:00,00:30:00,\n";
+
+#[test]
+fn toggl_export_previews_then_imports() {
+    let (_temp, path) = temp_archive();
+    let reading = add_goal(path.clone(), "reading".into(), false, vec![], None).unwrap();
+    add_session(
+        path.clone(),
+        reading.id,
+        reading.name,
+        local_ts(5, 8, 0),
+        1800,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let options = TrackerImportOptions {
+        format: TrackerFormat::Toggl,
+        ..Default::default()
+    };
+
+    let preview = import_tracker_csv(path.clone(), TOGGL.into(), options.clone()).unwrap();
+    assert!(preview.dry_run);
+    assert_eq!(preview.rows_read, 4);
+    assert_eq!(preview.duplicates, 1);
+    assert_eq!(preview.sessions.len(), 2);
+    let created: Vec<(u64, &str)> = preview
+        .goals_created
+        .iter()
+        .map(|g| (g.id, g.name.as_str()))
+        .collect();
+    assert_eq!(created, vec![(2, "Inbox, email")]);
+    assert_eq!(preview.skipped.len(), 1);
+    assert_eq!(preview.skipped[0].line, 5);
+    assert!(preview.skipped[0].reason.contains("not a time"));
+    // Nothing was written.
+    assert_eq!(list_goals(path.clone(), None, None).unwrap().len(), 1);
+    assert_eq!(all_sessions(&path).len(), 1);
+
+    let report = import_tracker_csv(
+        path.clone(),
+        TOGGL.into(),
+        TrackerImportOptions {
+            dry_run: false,
+            ..options.clone()
+        },
+    )
+    .unwrap();
+    assert_eq!(report.sessions.len(), 2);
+    assert_eq!(report.goals_created.len(), 1);
+    assert_eq!(
+        all_sessions(&path),
+        vec![
+            (1, local_ts(4, 9, 0), local_ts(4, 9, 45)),
+            (2, local_ts(4, 23, 30), local_ts(5, 0, 15)),
+            (1, local_ts(5, 8, 0), local_ts(5, 8, 30)),
+        ]
+    );
+
+    // Importing the same file again only finds duplicates.
+    let again = import_tracker_csv(
+        path.clone(),
+        TOGGL.into(),
+        TrackerImportOptions {
+            dry_run: false,
+            ..options
+        },
+    )
+    .unwrap();
+    assert!(again.sessions.is_empty());
+    assert!(again.goals_created.is_empty());
+    assert_eq!(again.duplicates, 3);
+    assert_eq!(all_sessions(&path).len(), 3);
+}
+
+#[test]
+fn generic_csv_uses_the_column_mapping() {
+    let (_temp, path) = temp_archive();
+    add_goal(
+        path.clone(),
+        "Push-ups".into(),
+        false,
+        vec![],
+        Some("reps".into()),
+    )
+    .unwrap();
+    let csv = "activity,when,hours,count,done\n\
+               Push-ups,2024-03-04 07:00,0.25,30,yes\n\
+               Push-ups,2024-03-04 07:10,0.25,30,yes\n\
+               Juggling,2024-03-04 08:00,1,,yes\n\
+               Push-ups,2024-03-04 09:00,0.5,20,no\n";
+    let options = TrackerImportOptions {
+        columns: Some(CsvColumns {
+            goal: vec!["Activity".into()],
+            start: "when".into(),
+            duration: Some("hours".into()),
+            quantity: Some("count".into()),
+            success: Some("done".into()),
+            ..Default::default()
+        }),
+        create_missing_goals: false,
+        dry_run: false,
+        ..Default::default()
+    };
+
+    let report = import_tracker_csv(path, csv.into(), options).unwrap();
+    assert_eq!(report.sessions.len(), 1);
+    assert_eq!(report.sessions[0].quantity, Some(30));
+    assert_eq!(report.sessions[0].end_at - report.sessions[0].start_at, 900);
+    let reasons: Vec<(u32, &str)> = report
+        .skipped
+        .iter()
+        .map(|s| (s.line, s.reason.as_str()))
+        .collect();
+    assert_eq!(reasons.len(), 3);
+    assert_eq!(reasons[0].0, 3);
+    assert!(reasons[0].1.contains("overlaps"), "{reasons:?}");
+    assert_eq!(reasons[1], (4, "no goal named \"Juggling\""));
+    assert_eq!(reasons[2], (5, "marked as unsuccessful"));
+}
+
+#[test]
+fn merged_rows_count_the_same_in_a_dry_run() {
+    let (_temp, path) = temp_archive();
+    add_goal(path.clone(), "Study".into(), false, vec![], None).unwrap();
+    let csv = "activity,when,hours\n\
+               Study,2024-03-04 07:00,1\n\
+               Study,2024-03-04 07:30,1\n\
+               Study,2024-03-04 09:00,0.5\n\
+               Study,2024-03-04 08:45,0.5\n";
+    let options = TrackerImportOptions {
+        columns: Some(CsvColumns {
+            goal: vec!["activity".into()],
+            start: "when".into(),
+            duration: Some("hours".into()),
+            ..Default::default()
+        }),
+        validation: Some(SessionValidation {
+            overlap: OverlapPolicy::Merge,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let preview = import_tracker_csv(path.clone(), csv.into(), options.clone()).unwrap();
+    let report = import_tracker_csv(
+        path.clone(),
+        csv.into(),
+        TrackerImportOptions {
+            dry_run: false,
+            ..options
+        },
+    )
+    .unwrap();
+    let spans = |sessions: &[successlib::Session]| -> Vec<(i64, i64)> {
+        sessions.iter().map(|s| (s.start_at, s.end_at)).collect()
+    };
+    assert_eq!(spans(&preview.sessions), spans(&report.sessions));
+    assert_eq!(
+        all_sessions(&path),
+        vec![
+            (1, local_ts(4, 7, 0), local_ts(4, 8, 30)),
+            (1, local_ts(4, 8, 45), local_ts(4, 9, 30)),
+        ]
+    );
+}
+
+#[test]
+fn a_rejected_row_creates_no_goal() {
+    let (_temp, path) = temp_archive();
+    let csv = "activity,when,hours\n\
+               Juggling,2024-03-04 07:00,0\n\
+               Juggling,2024-03-04 08:00,99999999999999999999\n";
+    let options = TrackerImportOptions {
+        columns: Some(CsvColumns {
+            goal: vec!["activity".into()],
+            start: "when".into(),
+            duration: Some("hours".into()),
+            ..Default::default()
+        }),
+        dry_run: false,
+        ..Default::default()
+    };
+
+    let report = import_tracker_csv(path.clone(), csv.into(), options).unwrap();
+    assert!(report.sessions.is_empty());
+    assert!(report.goals_created.is_empty());
+    assert_eq!(report.skipped.len(), 2);
+    assert!(list_goals(path, None, None).unwrap().is_empty());
+}
+
+#[test]
+fn missing_columns_fail_before_anything_is_read() {
+    let (_temp, path) = temp_archive();
+    let csv = "Tag,Start Time\nStudy,2024-03-04 07:00\n";
+
+    let err = import_tracker_csv(
+        path.clone(),
+        csv.into(),
+        TrackerImportOptions {
+            format: TrackerFormat::Forest,
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+
+    let err = import_tracker_csv(path, csv.into(), TrackerImportOptions::default()).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+}
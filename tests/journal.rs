@@ -0,0 +1,132 @@
+mod common;
+
+use successlib::{
+    add_goal, add_session, edit_note, get_note, history, list_day_sessions, list_goals, redo,
+    set_goal_status, set_goal_trashed, undo, Error, GoalStatus,
+};
+
+use common::{temp_archive, yesterday_at_ten};
+
+#[test]
+fn changes_are_undone_and_redone_in_order() {
+    let (_temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Write".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let date = successlib::timestamp_to_date_iso(start);
+    add_session(
+        path.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        1200,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    set_goal_status(path.clone(), goal.id, GoalStatus::DONE).unwrap();
+    set_goal_trashed(path.clone(), goal.id, true).unwrap();
+
+    let operations: Vec<String> = history(path.clone(), None)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.operation)
+        .collect();
+    assert_eq!(
+        operations,
+        [
+            "set_goal_trashed",
+            "set_goal_status",
+            "add_session",
+            "add_goal"
+        ]
+    );
+
+    let entry = undo(path.clone()).unwrap().unwrap();
+    assert_eq!((entry.operation.as_str(), entry.target), ("undo", Some(4)));
+    assert_eq!(entry.files, ["goals.yaml"]);
+    assert_eq!(list_goals(path.clone(), None, None).unwrap().len(), 0);
+    undo(path.clone()).unwrap().unwrap();
+    let goals = list_goals(path.clone(), None, None).unwrap();
+    assert_eq!(goals[0].status, GoalStatus::DOING);
+
+    // Undoing the session removes its day graph and the status change it made.
+    let entry = undo(path.clone()).unwrap().unwrap();
+    assert_eq!(entry.target, Some(2));
+    assert!(list_day_sessions(path.clone(), date.clone())
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        list_goals(path.clone(), None, None).unwrap()[0].status,
+        GoalStatus::TODO
+    );
+
+    let entry = redo(path.clone()).unwrap().unwrap();
+    assert_eq!((entry.operation.as_str(), entry.target), ("redo", Some(2)));
+    assert_eq!(list_day_sessions(path.clone(), date).unwrap().len(), 1);
+
+    let log = history(path.clone(), Some(4)).unwrap();
+    assert_eq!(log.len(), 4);
+    let undone: Vec<u64> = history(path.clone(), None)
+        .unwrap()
+        .iter()
+        .filter(|e| e.undone)
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(undone, [4, 3]);
+
+    // A new change drops what could be redone.
+    set_goal_status(path.clone(), goal.id, GoalStatus::TODO).unwrap();
+    assert!(redo(path.clone()).unwrap().is_none());
+    assert!(history(path, None).unwrap().iter().all(|e| !e.undone));
+}
+
+#[test]
+fn undo_refuses_to_overwrite_outside_changes() {
+    let (temp, path) = temp_archive();
+    assert!(undo(path.clone()).unwrap().is_none());
+    assert!(history(path.clone(), None).unwrap().is_empty());
+
+    let goal = add_goal(path.clone(), "Walk".into(), false, vec![], None).unwrap();
+    set_goal_trashed(path.clone(), goal.id, true).unwrap();
+    // Setting a goal to the status it has changes nothing and is not logged.
+    set_goal_status(path.clone(), goal.id, GoalStatus::TODO).unwrap();
+    assert_eq!(history(path.clone(), None).unwrap().len(), 2);
+
+    let goals_path = temp.path().join("goals.yaml");
+    let edited = std::fs::read_to_string(&goals_path)
+        .unwrap()
+        .replace("Walk", "Hike");
+    std::fs::write(&goals_path, &edited).unwrap();
+
+    let err = undo(path.clone()).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+    assert_eq!(std::fs::read_to_string(&goals_path).unwrap(), edited);
+    assert_eq!(history(path, None).unwrap().len(), 2);
+}
+
+#[test]
+fn changes_kept_for_undo_are_bounded_by_size() {
+    let (temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Novel".into(), false, vec![], None).unwrap();
+    let draft = "a".repeat(400_000) + "\n";
+    edit_note(path.clone(), goal.id, draft.clone()).unwrap();
+    edit_note(path.clone(), goal.id, "b".repeat(400_000)).unwrap();
+
+    // The last edit holds both drafts, which leaves no room for the others.
+    let journal = temp.path().join("journal");
+    let mut kept: Vec<String> = std::fs::read_dir(&journal)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    kept.sort();
+    assert_eq!(kept, ["3.json", "index.json", "log.jsonl"]);
+
+    undo(path.clone()).unwrap().unwrap();
+    assert!(get_note(path.clone(), goal.id).unwrap() == draft);
+    assert!(undo(path.clone()).unwrap().is_none());
+    let log = std::fs::read_to_string(journal.join("log.jsonl")).unwrap();
+    assert_eq!(log.lines().count(), 4);
+    assert_eq!(history(path, None).unwrap().len(), 4);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use chrono::Local;
+use successlib::{
+    add_goal, list_goals, resolve_launch_commands, set_launch_commands, CommandKind,
+    CommandVariant, Error, LaunchCommand, Platform, ResolvedCommand,
+};
+
+use common::temp_archive;
+
+fn resolved(kind: CommandKind, value: &str, legacy: bool) -> ResolvedCommand {
+    ResolvedCommand {
+        label: None,
+        kind,
+        value: value.into(),
+        legacy,
+    }
+}
+
+#[test]
+fn commands_resolve_per_platform_with_quoted_variables() {
+    let (temp, path) = temp_archive();
+    let goal = add_goal(
+        path.clone(),
+        "Tom's notes".into(),
+        false,
+        vec!["code ~/notes".into(), "https://example.com/board".into()],
+        None,
+    )
+    .unwrap();
+    let commands = vec![
+        LaunchCommand {
+            label: Some("Journal".into()),
+            kind: CommandKind::Shell,
+            value: Some("journal --goal {goal_name} --day {date} ${{HOME}}".into()),
+            variants: vec![CommandVariant {
+                platform: Platform::Android,
+                kind: CommandKind::AppId,
+                value: "org.example.journal".into(),
+            }],
+        },
+        LaunchCommand {
+            label: None,
+            kind: CommandKind::Url,
+            value: Some("https://example.com/search?q={goal_name}&id={goal_id}".into()),
+            variants: vec![],
+        },
+        LaunchCommand {
+            label: None,
+            kind: CommandKind::File,
+            value: None,
+            variants: vec![CommandVariant {
+                platform: Platform::Linux,
+                kind: CommandKind::File,
+                value: "{note_path}".into(),
+            }],
+        },
+    ];
+    let updated = set_launch_commands(path.clone(), goal.id, commands.clone()).unwrap();
+    assert_eq!(updated.launch_commands, commands);
+    assert_eq!(list_goals(path.clone(), None, None).unwrap()[0], updated);
+    assert_eq!(updated.commands.len(), 2);
+
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let note = temp.path().join("notes/goal_1.md");
+    let legacy = [
+        resolved(CommandKind::Shell, "code ~/notes", true),
+        resolved(CommandKind::Url, "https://example.com/board", true),
+    ];
+
+    let linux = resolve_launch_commands(path.clone(), goal.id, Some(Platform::Linux)).unwrap();
+    assert_eq!(
+        linux,
+        [
+            ResolvedCommand {
+                label: Some("Journal".into()),
+                ..resolved(
+                    CommandKind::Shell,
+                    &format!("journal --goal 'Tom'\\''s notes' --day '{today}' ${{HOME}}"),
+                    false,
+                )
+            },
+            resolved(
+                CommandKind::Url,
+                "https://example.com/search?q=Tom%27s%20notes&id=1",
+                false,
+            ),
+            resolved(CommandKind::File, note.to_str().unwrap(), false),
+            legacy[0].clone(),
+            legacy[1].clone(),
+        ]
+    );
+
+    let android = resolve_launch_commands(path.clone(), goal.id, Some(Platform::Android)).unwrap();
+    assert_eq!(android.len(), 4);
+    assert_eq!(android[0].kind, CommandKind::AppId);
+    assert_eq!(android[0].value, "org.example.journal");
+
+    let windows = resolve_launch_commands(path, goal.id, Some(Platform::Windows)).unwrap();
+    assert_eq!(
+        windows[0].value,
+        format!("journal --goal \"Tom's notes\" --day \"{today}\" ${{HOME}}")
+    );
+}
+
+#[test]
+fn commands_without_a_value_are_rejected() {
+    let (_temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Run".into(), false, vec![], None).unwrap();
+    let nowhere = LaunchCommand {
+        label: None,
+        kind: CommandKind::Shell,
+        value: None,
+        variants: vec![],
+    };
+    let err = set_launch_commands(path.clone(), goal.id, vec![nowhere]).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+
+    let blank = LaunchCommand {
+        label: None,
+        kind: CommandKind::Url,
+        value: Some(" ".into()),
+        variants: vec![],
+    };
+    let err = set_launch_commands(path.clone(), goal.id, vec![blank]).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+
+    let err = set_launch_commands(path.clone(), 7, vec![]).unwrap_err();
+    assert!(matches!(err, Error::NotFound { .. }), "{err:?}");
+    assert!(resolve_launch_commands(path, goal.id, None)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use std::fs;
+
+use successlib::{
+    add_goal, edit_note, get_note, get_note_body, get_note_frontmatter, update_note_frontmatter,
+    Error, FrontmatterField, FrontmatterValue,
+};
+
+use common::temp_archive;
+
+fn text(key: &str, value: &str) -> FrontmatterField {
+    FrontmatterField {
+        key: key.into(),
+        value: FrontmatterValue::Text {
+            value: value.into(),
+        },
+    }
+}
+
+fn list(key: &str, items: &[&str]) -> FrontmatterField {
+    FrontmatterField {
+        key: key.into(),
+        value: FrontmatterValue::List {
+            items: items.iter().map(|i| i.to_string()).collect(),
+        },
+    }
+}
+
+#[test]
+fn frontmatter_is_read_and_updated_around_the_body() {
+    let (_temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Spanish".into(), false, vec![], None).unwrap();
+    edit_note(
+        path.clone(),
+        goal.id,
+        "---\n\
+         motivation: Talk to my neighbours\n\
+         level: 2\n\
+         links:\n  - https://example.com/verbs\n\
+         ---\n\
+         # Spanish\n\nWeek 1: greetings.\n"
+            .into(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        get_note_frontmatter(path.clone(), goal.id).unwrap(),
+        vec![
+            text("motivation", "Talk to my neighbours"),
+            text("level", "2"),
+            list("links", &["https://example.com/verbs"]),
+        ]
+    );
+    let body = "# Spanish\n\nWeek 1: greetings.\n";
+    assert_eq!(get_note_body(path.clone(), goal.id).unwrap(), body);
+
+    let fields = update_note_frontmatter(
+        path.clone(),
+        goal.id,
+        vec![
+            list(
+                "links",
+                &["https://example.com/verbs", "https://example.com/podcast"],
+            ),
+            text("resources", "Duolingo: daily"),
+        ],
+        vec!["level".into()],
+    )
+    .unwrap();
+    let keys: Vec<&str> = fields.iter().map(|f| f.key.as_str()).collect();
+    assert_eq!(keys, vec!["motivation", "links", "resources"]);
+    assert_eq!(get_note_body(path.clone(), goal.id).unwrap(), body);
+    assert_eq!(get_note_frontmatter(path.clone(), goal.id).unwrap(), fields);
+
+    // Without fields the note goes back to being plain text.
+    update_note_frontmatter(
+        path.clone(),
+        goal.id,
+        vec![],
+        keys.iter().map(|k| k.to_string()).collect(),
+    )
+    .unwrap();
+    assert_eq!(get_note(path, goal.id).unwrap(), body);
+}
+
+#[test]
+fn plain_notes_gain_frontmatter_on_first_update() {
+    let (temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Run".into(), false, vec![], None).unwrap();
+    edit_note(
+        path.clone(),
+        goal.id,
+        "---\nnot frontmatter, just a rule".into(),
+    )
+    .unwrap();
+    assert!(get_note_frontmatter(path.clone(), goal.id)
+        .unwrap()
+        .is_empty());
+
+    update_note_frontmatter(path.clone(), goal.id, vec![text("why", "10k")], vec![]).unwrap();
+    let content = fs::read_to_string(temp.path().join("notes/goal_1.md")).unwrap();
+    assert_eq!(
+        content,
+        "---\nwhy: 10k\n---\n---\nnot frontmatter, just a rule\n"
+    );
+    assert_eq!(
+        get_note_body(path, goal.id).unwrap(),
+        "---\nnot frontmatter, just a rule\n"
+    );
+}
+
+#[test]
+fn frontmatter_that_is_not_a_mapping_is_an_error() {
+    let (_temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Run".into(), false, vec![], None).unwrap();
+    let note = "---\n- just\n- a list\n---\nbody\n";
+    edit_note(path.clone(), goal.id, note.into()).unwrap();
+
+    let err = get_note_frontmatter(path.clone(), goal.id).unwrap_err();
+    assert!(matches!(err, Error::Parse { .. }), "{err:?}");
+    let err =
+        update_note_frontmatter(path.clone(), goal.id, vec![text("a", "b")], vec![]).unwrap_err();
+    assert!(matches!(err, Error::Parse { .. }), "{err:?}");
+    assert_eq!(get_note(path, goal.id).unwrap(), note);
+}
+
+#[test]
+fn removing_the_last_field_of_an_empty_note_deletes_it() {
+    let (temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Run".into(), false, vec![], None).unwrap();
+
+    update_note_frontmatter(path.clone(), goal.id, vec![text("why", "10k")], vec![]).unwrap();
+    let file = temp.path().join("notes/goal_1.md");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "---\nwhy: 10k\n---\n");
+
+    update_note_frontmatter(path.clone(), goal.id, vec![], vec!["why".into()]).unwrap();
+    assert!(!file.exists());
+    assert_eq!(get_note(path, goal.id).unwrap(), "");
+}
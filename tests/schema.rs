@@ -0,0 +1,164 @@
+mod common;
+
+use std::fs;
+
+use successlib::{
+    add_goal, archive_schema_version, get_note, list_goals, migrate_archive,
+    supported_schema_version, Archive, Error,
+};
+
+use common::temp_archive_dir;
+
+/// Lay out an archive the way versions without a manifest wrote it.
+fn write_legacy_archive(root: &std::path::Path) {
+    fs::create_dir_all(root.join("graphs")).unwrap();
+    fs::create_dir_all(root.join("notes/sessions/2024-03-01")).unwrap();
+    fs::write(
+        root.join("goals.yaml"),
+        "- id: 1\n  name: Read\n  is_reward: false\n  commands: []\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("graphs/2024-03-01.mmd"),
+        "stateDiagram-v2\n    sess_1 : Read [id 1] [09#colon;00-09#colon;30]\n",
+    )
+    .unwrap();
+    fs::write(root.join("notes/goal_1.md"), "chapter 3\n").unwrap();
+    fs::write(root.join("notes/sessions/2024-03-01/sess_1.md"), "slow\n").unwrap();
+}
+
+#[test]
+fn legacy_archive_is_backed_up_and_migrated() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    write_legacy_archive(temp.path());
+    assert_eq!(archive_schema_version(path.clone()).unwrap(), 0);
+
+    assert_eq!(
+        migrate_archive(path.clone()).unwrap(),
+        supported_schema_version()
+    );
+    assert_eq!(
+        archive_schema_version(path.clone()).unwrap(),
+        supported_schema_version()
+    );
+
+    let backups: Vec<_> = fs::read_dir(temp.path().join("backups"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(backups.len(), 1);
+    let backup = &backups[0];
+    assert!(backup
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("schema-v0-"));
+    for relative in [
+        "goals.yaml",
+        "graphs/2024-03-01.mmd",
+        "notes/goal_1.md",
+        "notes/sessions/2024-03-01/sess_1.md",
+    ] {
+        assert_eq!(
+            fs::read_to_string(backup.join(relative)).unwrap(),
+            fs::read_to_string(temp.path().join(relative)).unwrap(),
+            "{relative}"
+        );
+    }
+
+    // Already current: nothing more to back up.
+    migrate_archive(path.clone()).unwrap();
+    assert_eq!(
+        fs::read_dir(temp.path().join("backups")).unwrap().count(),
+        1
+    );
+    assert_eq!(list_goals(path, None, None).unwrap()[0].name, "Read");
+}
+
+#[test]
+fn new_archives_start_at_the_current_schema_without_a_backup() {
+    let temp = temp_archive_dir();
+    let path = temp.path().join("fresh").to_str().unwrap().to_string();
+    Archive::open(path.clone(), None).unwrap();
+
+    assert_eq!(
+        archive_schema_version(path).unwrap(),
+        supported_schema_version()
+    );
+    assert!(!temp.path().join("fresh/backups").exists());
+}
+
+#[test]
+fn newer_archives_are_refused_and_left_untouched() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    write_legacy_archive(temp.path());
+    let newer = supported_schema_version() + 1;
+    fs::write(
+        temp.path().join("success.yaml"),
+        format!("schema_version: {newer}\nsync_id: abc\n"),
+    )
+    .unwrap();
+    let goals_before = fs::read_to_string(temp.path().join("goals.yaml")).unwrap();
+
+    let expected = |result: Result<_, Error>| match result {
+        Err(Error::UnsupportedSchemaVersion { found, supported }) => {
+            assert_eq!(found, newer);
+            assert_eq!(supported, supported_schema_version());
+        }
+        other => panic!(
+            "expected UnsupportedSchemaVersion, got {:?}",
+            other.map(|_| ())
+        ),
+    };
+    expected(Archive::open(path.clone(), None).map(|_| ()));
+    expected(add_goal(path.clone(), "Write".into(), false, vec![], None).map(|_| ()));
+    expected(migrate_archive(path.clone()).map(|_| ()));
+    // Reads without a handle are refused as well.
+    expected(list_goals(path.clone(), None, None).map(|_| ()));
+    expected(get_note(path.clone(), 1).map(|_| ()));
+    assert_eq!(archive_schema_version(path).unwrap(), newer);
+
+    assert_eq!(
+        fs::read_to_string(temp.path().join("goals.yaml")).unwrap(),
+        goals_before
+    );
+    assert!(!temp.path().join("backups").exists());
+}
+
+#[test]
+fn a_supported_manifest_is_not_read_again_until_it_changes() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    add_goal(path.clone(), "Write".into(), false, vec![], None).unwrap();
+    let manifest = temp.path().join("success.yaml");
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    let set_modified = |time| {
+        fs::File::options()
+            .write(true)
+            .open(&manifest)
+            .unwrap()
+            .set_modified(time)
+            .unwrap()
+    };
+    set_modified(old);
+    list_goals(path.clone(), None, None).unwrap();
+
+    // Same size and time: the earlier check still stands.
+    let current = format!("schema_version: {}", supported_schema_version());
+    let newer = format!("schema_version: {}", supported_schema_version() + 1);
+    assert_eq!(current.len(), newer.len());
+    let content = fs::read_to_string(&manifest).unwrap();
+    fs::write(&manifest, content.replace(&current, &newer)).unwrap();
+    set_modified(old);
+    list_goals(path.clone(), None, None).unwrap();
+
+    set_modified(std::time::SystemTime::now());
+    let err = list_goals(path, None, None).unwrap_err();
+    assert!(
+        matches!(err, Error::UnsupportedSchemaVersion { .. }),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,231 @@
+mod common;
+
+use chrono::{Duration, Local};
+use successlib::{
+    add_goal, add_session, edit_note, search_archive, search_goals, search_goals_ranked,
+    set_goal_trashed, Error, SearchHitKind, SearchRanking, TextRange,
+};
+
+use common::{days_ago_at, temp_archive};
+
+fn highlighted(snippet: &str, ranges: &[TextRange]) -> Vec<String> {
+    let chars: Vec<char> = snippet.chars().collect();
+    ranges
+        .iter()
+        .map(|r| chars[r.start as usize..r.end as usize].iter().collect())
+        .collect()
+}
+
+#[test]
+fn finds_goals_by_note_content_and_session_names() {
+    let (temp, path) = temp_archive();
+    let japanese = add_goal(path.clone(), "Japanese".into(), false, vec![], None).unwrap();
+    let piano = add_goal(path.clone(), "Piano".into(), false, vec![], None).unwrap();
+    edit_note(
+        path.clone(),
+        japanese.id,
+        "# Japanese\n\nReview the Anki deck before breakfast.\n  Új ANKI cards on Sundays.\n"
+            .into(),
+    )
+    .unwrap();
+    for (days, name) in [(2, "Anki review"), (1, "anki review"), (1, "Japanese")] {
+        let hour = if name == "Japanese" { 18 } else { 8 };
+        add_session(
+            path.clone(),
+            japanese.id,
+            name.into(),
+            days_ago_at(days, hour),
+            600,
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+    add_session(
+        path.clone(),
+        piano.id,
+        "Scales".into(),
+        days_ago_at(1, 12),
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let hits = search_archive(path.clone(), "anki".into()).unwrap();
+    let kinds: Vec<(SearchHitKind, u64)> = hits.iter().map(|h| (h.kind, h.goal_id)).collect();
+    assert_eq!(kinds.len(), 2, "{hits:?}");
+    assert!(kinds.contains(&(SearchHitKind::SessionName, japanese.id)));
+    assert!(kinds.contains(&(SearchHitKind::GoalNote, japanese.id)));
+
+    let session = hits
+        .iter()
+        .find(|h| h.kind == SearchHitKind::SessionName)
+        .unwrap();
+    // The most recent spelling wins.
+    assert_eq!(session.snippet, "anki review");
+    assert_eq!(
+        session.date.as_deref(),
+        Some(
+            (Local::now().date_naive() - Duration::days(1))
+                .format("%Y-%m-%d")
+                .to_string()
+                .as_str()
+        )
+    );
+    assert_eq!(highlighted(&session.snippet, &session.highlights), ["anki"]);
+
+    let note = hits
+        .iter()
+        .find(|h| h.kind == SearchHitKind::GoalNote)
+        .unwrap();
+    assert_eq!(note.line, Some(3));
+    assert_eq!(note.snippet, "Review the Anki deck before breakfast.");
+    assert_eq!(highlighted(&note.snippet, &note.highlights), ["Anki"]);
+
+    // Every word must occur on the line; offsets count characters.
+    let hits = search_archive(path.clone(), "anki új".into()).unwrap();
+    let note = hits
+        .iter()
+        .find(|h| h.kind == SearchHitKind::GoalNote)
+        .unwrap();
+    assert_eq!(note.line, Some(4));
+    assert_eq!(highlighted(&note.snippet, &note.highlights), ["Új", "ANKI"]);
+
+    let hits = search_archive(path.clone(), "pno".into()).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kind, SearchHitKind::GoalName);
+    assert_eq!(
+        highlighted(&hits[0].snippet, &hits[0].highlights),
+        ["P", "no"]
+    );
+
+    assert!(search_archive(path.clone(), "  ".into())
+        .unwrap()
+        .is_empty());
+
+    // Session names are read from the index, which follows edits by hand.
+    let day = (Local::now().date_naive() - Duration::days(1)).format("%Y-%m-%d");
+    let graph = temp.path().join(format!("graphs/{day}.mmd"));
+    let content = std::fs::read_to_string(&graph).unwrap();
+    std::fs::write(&graph, content.replace("Scales", "Arpeggios")).unwrap();
+    let hits = search_archive(path, "arpeggio".into()).unwrap();
+    let kinds: Vec<(SearchHitKind, u64)> = hits.iter().map(|h| (h.kind, h.goal_id)).collect();
+    assert_eq!(kinds, [(SearchHitKind::SessionName, piano.id)]);
+}
+
+#[test]
+fn long_note_lines_are_cut_around_the_match_and_trash_is_ignored() {
+    let (_temp, path) = temp_archive();
+    let goal = add_goal(path.clone(), "Reading".into(), false, vec![], None).unwrap();
+    let line = format!(
+        "{} Stoner by John Williams {}",
+        "x".repeat(200),
+        "y".repeat(200)
+    );
+    edit_note(path.clone(), goal.id, line).unwrap();
+
+    let hits = search_archive(path.clone(), "stoner".into()).unwrap();
+    assert_eq!(hits.len(), 1);
+    let snippet = &hits[0].snippet;
+    assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+    assert_eq!(snippet.chars().count(), 122);
+    assert_eq!(highlighted(snippet, &hits[0].highlights), ["Stoner"]);
+    assert_eq!(hits[0].highlights[0].start, 31);
+
+    set_goal_trashed(path.clone(), goal.id, true).unwrap();
+    assert!(search_archive(path, "stoner".into()).unwrap().is_empty());
+}
+
+fn record(path: &str, goal_id: u64, start_at: i64) {
+    add_session(
+        path.into(),
+        goal_id,
+        "session".into(),
+        start_at,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn ranking_weighs_match_quality_against_recent_use() {
+    let (_temp, path) = temp_archive();
+    let gym = add_goal(path.clone(), "Gym".into(), false, vec![], None).unwrap();
+    let lemurs = add_goal(path.clone(), "Angry lemurs".into(), false, vec![], None).unwrap();
+    for days in [30, 31, 32] {
+        record(&path, gym.id, days_ago_at(days, 7));
+    }
+    record(&path, lemurs.id, days_ago_at(1, 20));
+
+    // The default ordering puts last week's activity before match quality.
+    let recent_first = search_goals(path.clone(), "gym".into(), None, None, None).unwrap();
+    assert_eq!(recent_first[0].id, lemurs.id);
+
+    let ranked = search_goals_ranked(path.clone(), "gym".into(), None, None, None).unwrap();
+    let ids: Vec<u64> = ranked.iter().map(|r| r.goal.id).collect();
+    assert_eq!(ids, vec![gym.id, lemurs.id]);
+    assert_eq!(ranked[0].session_count, 3);
+    assert_eq!(ranked[0].last_active_at, Some(days_ago_at(30, 7)));
+    assert_eq!(ranked[0].indices, vec![0, 1, 2]);
+    assert!(ranked[0].rank > ranked[1].rank);
+
+    // Weighting recency alone brings the recently used goal back up.
+    let recency_only = SearchRanking {
+        fuzzy_weight: 0.0,
+        frequency_weight: 0.0,
+        ..Default::default()
+    };
+    let ranked =
+        search_goals_ranked(path.clone(), "gym".into(), None, None, Some(recency_only)).unwrap();
+    assert_eq!(ranked[0].goal.id, lemurs.id);
+}
+
+#[test]
+fn pinned_goals_are_boosted_and_weights_are_validated() {
+    let (_temp, path) = temp_archive();
+    let busy = add_goal(path.clone(), "Email".into(), false, vec![], None).unwrap();
+    let pinned = add_goal(path.clone(), "Meditate".into(), false, vec![], None).unwrap();
+    let idle = add_goal(path.clone(), "Taxes".into(), false, vec![], None).unwrap();
+    record(&path, busy.id, days_ago_at(1, 9));
+
+    let ranked = search_goals_ranked(
+        path.clone(),
+        "".into(),
+        None,
+        None,
+        Some(SearchRanking {
+            pinned_weight: 2.0,
+            pinned_goal_ids: vec![pinned.id],
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    let order: Vec<(u64, bool)> = ranked.iter().map(|r| (r.goal.id, r.pinned)).collect();
+    assert_eq!(
+        order,
+        vec![(pinned.id, true), (busy.id, false), (idle.id, false)]
+    );
+    assert_eq!(ranked[2].rank, 0.0);
+
+    let err = search_goals_ranked(
+        path,
+        "".into(),
+        None,
+        None,
+        Some(SearchRanking {
+            half_life_days: 0.0,
+            ..Default::default()
+        }),
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+}
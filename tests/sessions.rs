@@ -0,0 +1,755 @@
+mod common;
+
+use chrono::Utc;
+use successlib::{
+    add_goal, add_session, get_session_note, list_day_sessions, list_day_totals,
+    list_goal_sessions, query_sessions, update_session, Error, OverlapPolicy, SessionKind,
+    SessionMetadata, SessionValidation,
+};
+
+use common::{days_ago_at, temp_archive_dir, yesterday_at_ten};
+
+fn day_iso(ts: i64) -> String {
+    successlib::timestamp_to_date_iso(ts)
+}
+
+#[test]
+fn overlapping_session_is_rejected_by_default() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+
+    let first = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        1500,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let err = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start + 600,
+        1500,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+
+    match err {
+        Error::SessionOverlap { session_ids } => assert_eq!(session_ids, vec![first.id]),
+        other => panic!("unexpected error: {other}"),
+    }
+    assert_eq!(list_day_sessions(archive, day_iso(start)).unwrap().len(), 1);
+}
+
+#[test]
+fn overlaps_across_midnight_are_rejected() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let record = |start_at: i64, duration_secs: u32| {
+        add_session(
+            archive.clone(),
+            goal.id,
+            goal.name.clone(),
+            start_at,
+            duration_secs,
+            false,
+            None,
+            None,
+            None,
+        )
+    };
+
+    // Recorded in yesterday's file, overlapped by a session from the day
+    // before that runs past midnight.
+    let early = record(days_ago_at(1, 0) + 1800, 1800).unwrap();
+    let err = record(days_ago_at(2, 23), 7200).unwrap_err();
+    match err {
+        Error::SessionOverlap { session_ids } => assert_eq!(session_ids, vec![early.id]),
+        other => panic!("unexpected error: {other}"),
+    }
+
+    // And the other way round.
+    let late = record(days_ago_at(3, 23), 5400).unwrap();
+    let err = record(days_ago_at(2, 0) + 900, 1800).unwrap_err();
+    match err {
+        Error::SessionOverlap { session_ids } => assert_eq!(session_ids, vec![late.id]),
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(list_day_sessions(archive, day_iso(days_ago_at(2, 12)))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn merge_policy_folds_duplicate_logs_of_the_same_goal() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let merge = SessionValidation {
+        overlap: OverlapPolicy::Merge,
+        ..Default::default()
+    };
+
+    let first = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        1500,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let merged = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start + 600,
+        1500,
+        false,
+        None,
+        Some(merge.clone()),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(merged.id, first.id);
+    assert_eq!(merged.start_at, start);
+    assert_eq!(merged.end_at, start + 2100);
+    let sessions = list_day_sessions(archive.clone(), day_iso(start)).unwrap();
+    assert_eq!(sessions.len(), 1);
+
+    let other = add_goal(archive.clone(), "Guitar".into(), false, vec![], None).unwrap();
+    let err = add_session(
+        archive,
+        other.id,
+        other.name,
+        start + 60,
+        60,
+        false,
+        None,
+        Some(merge),
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::SessionOverlap { .. }));
+}
+
+#[test]
+fn allow_policy_keeps_overlapping_sessions_with_unique_ids() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let allow = SessionValidation {
+        overlap: OverlapPolicy::Allow,
+        ..Default::default()
+    };
+
+    let a = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let b = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start + 60,
+        600,
+        false,
+        None,
+        Some(allow),
+        None,
+    )
+    .unwrap();
+
+    assert_ne!(a.id, b.id);
+    assert_eq!(list_day_sessions(archive, day_iso(start)).unwrap().len(), 2);
+}
+
+#[test]
+fn future_and_out_of_bounds_sessions_are_rejected() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+
+    let future = Utc::now().timestamp() + 3600;
+    let err = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        future,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::SessionInFuture { start_at } if start_at == future));
+
+    let err = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        yesterday_at_ten(),
+        0,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidDuration {
+            duration_secs: 0,
+            ..
+        }
+    ));
+
+    let err = add_session(
+        archive,
+        goal.id,
+        goal.name,
+        yesterday_at_ten(),
+        3 * 24 * 60 * 60,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidDuration { .. }));
+}
+
+#[test]
+fn sessions_at_the_duration_bounds_read_back_unchanged() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let record = |start_at: i64, duration_secs: u32| {
+        add_session(
+            archive.clone(),
+            goal.id,
+            goal.name.clone(),
+            start_at,
+            duration_secs,
+            false,
+            None,
+            None,
+            None,
+        )
+    };
+
+    for (start_at, duration_secs) in [
+        (yesterday_at_ten(), 60),
+        (days_ago_at(3, 0), 24 * 60 * 60 - 60),
+    ] {
+        record(start_at, duration_secs).unwrap();
+        let sessions = list_day_sessions(archive.clone(), day_iso(start_at)).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].start_at, start_at);
+        assert_eq!(sessions[0].end_at, start_at + i64::from(duration_secs));
+    }
+
+    for duration_secs in [59, 24 * 60 * 60] {
+        let err = record(days_ago_at(5, 0), duration_secs).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidDuration { duration_secs: d, .. } if d == duration_secs),
+            "{err:?}"
+        );
+    }
+}
+
+#[test]
+fn session_metadata_round_trips_through_the_day_graph() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Anki".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let metadata = SessionMetadata {
+        rating: Some(4),
+        tags: vec!["deep".into(), "vocab".into()],
+        note: Some("Reviewed 200 cards".into()),
+    };
+
+    let session = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        1500,
+        false,
+        None,
+        None,
+        Some(metadata),
+    )
+    .unwrap();
+
+    let sessions = list_day_sessions(archive.clone(), day_iso(start)).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].name, "Anki");
+    assert_eq!(sessions[0].goal_id, goal.id);
+    assert_eq!(sessions[0].rating, Some(4));
+    assert_eq!(sessions[0].tags, vec!["deep", "vocab"]);
+    assert_eq!(sessions[0].note_path, session.note_path);
+    assert_eq!(
+        get_session_note(archive, day_iso(start), session.id).unwrap(),
+        "Reviewed 200 cards\n"
+    );
+}
+
+#[test]
+fn update_and_query_session_metadata() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let date = day_iso(start);
+
+    let a = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start + 3600,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let updated = update_session(
+        archive.clone(),
+        date.clone(),
+        a.id.clone(),
+        SessionMetadata {
+            rating: Some(5),
+            tags: vec!["exam".into()],
+            note: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(updated.rating, Some(5));
+
+    let hits = query_sessions(
+        archive.clone(),
+        Some(date.clone()),
+        Some(date.clone()),
+        Some(vec!["exam".into()]),
+        Some(4),
+    )
+    .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].id, a.id);
+
+    let err = update_session(
+        archive.clone(),
+        date.clone(),
+        a.id.clone(),
+        SessionMetadata {
+            rating: Some(9),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }));
+
+    // Tags cannot break the mermaid label they are written into.
+    for tag in ["a:b", "two\nlines", "a-->b", "#1", "x;y"] {
+        let err = update_session(
+            archive.clone(),
+            date.clone(),
+            a.id.clone(),
+            SessionMetadata {
+                tags: vec![tag.into()],
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{tag:?}");
+    }
+}
+
+#[test]
+fn merged_sessions_hand_their_notes_over() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let date = day_iso(start);
+    let with_note = |note: &str| {
+        Some(SessionMetadata {
+            note: Some(note.into()),
+            ..Default::default()
+        })
+    };
+    for (offset, note) in [(0, "Chapter 1"), (1200, "Chapter 2")] {
+        add_session(
+            archive.clone(),
+            goal.id,
+            goal.name.clone(),
+            start + offset,
+            600,
+            false,
+            None,
+            None,
+            with_note(note),
+        )
+        .unwrap();
+    }
+
+    let merged = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start + 300,
+        1200,
+        false,
+        None,
+        Some(SessionValidation {
+            overlap: OverlapPolicy::Merge,
+            ..Default::default()
+        }),
+        with_note("Exercises"),
+    )
+    .unwrap();
+    assert_eq!(merged.id, "sess_1");
+    assert_eq!(
+        get_session_note(archive.clone(), date.clone(), "sess_1".into()).unwrap(),
+        "Chapter 1\n\nChapter 2\n\nExercises\n"
+    );
+    let notes = temp.path().join("notes/sessions").join(&date);
+    assert!(!notes.join("sess_2.md").exists());
+
+    // The id of the merged session is given out again, without a note.
+    let next = add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start + 3600,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!((next.id.as_str(), next.note_path), ("sess_2", None));
+    assert_eq!(
+        get_session_note(archive.clone(), date.clone(), next.id).unwrap(),
+        ""
+    );
+
+    // An empty note removes the file.
+    update_session(archive, date, merged.id, with_note("").unwrap()).unwrap();
+    assert!(!notes.join("sess_1.md").exists());
+}
+
+#[test]
+fn list_goal_sessions_pages_through_the_whole_history() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let other = add_goal(archive.clone(), "Guitar".into(), false, vec![], None).unwrap();
+    let base = yesterday_at_ten();
+
+    let mut expected = Vec::new();
+    for days_ago in [400, 30, 0] {
+        let start = base - days_ago * 24 * 60 * 60;
+        let s = add_session(
+            archive.clone(),
+            goal.id,
+            goal.name.clone(),
+            start,
+            600,
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        expected.push(s.start_at);
+        add_session(
+            archive.clone(),
+            other.id,
+            other.name.clone(),
+            start + 3600,
+            600,
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+    expected.reverse();
+
+    let first =
+        list_goal_sessions(archive.clone(), goal.id, None, None, None, Some(2), None).unwrap();
+    assert_eq!(first.sessions.len(), 2);
+    assert!(first.next_cursor.is_some());
+
+    let second = list_goal_sessions(
+        archive.clone(),
+        goal.id,
+        None,
+        None,
+        None,
+        Some(2),
+        first.next_cursor,
+    )
+    .unwrap();
+    assert_eq!(second.sessions.len(), 1);
+    assert!(second.next_cursor.is_none());
+
+    let starts: Vec<i64> = first
+        .sessions
+        .iter()
+        .chain(&second.sessions)
+        .map(|s| s.start_at)
+        .collect();
+    assert_eq!(starts, expected);
+    assert!(first
+        .sessions
+        .iter()
+        .chain(&second.sessions)
+        .all(|s| s.goal_id == goal.id));
+}
+
+#[test]
+fn goal_session_index_is_disposable() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let page = list_goal_sessions(archive.clone(), goal.id, None, None, None, None, None).unwrap();
+    assert_eq!(page.sessions.len(), 1);
+
+    std::fs::remove_dir_all(temp.path().join("cache")).unwrap();
+    let page = list_goal_sessions(
+        archive.clone(),
+        goal.id,
+        Some(day_iso(start)),
+        Some(day_iso(start)),
+        Some(SessionKind::Goal),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(page.sessions.len(), 1);
+
+    let err = list_goal_sessions(
+        archive,
+        goal.id,
+        None,
+        None,
+        None,
+        None,
+        Some("bogus".into()),
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }));
+}
+
+#[test]
+fn day_totals_follow_external_edits_to_graph_files() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let reward = add_goal(archive.clone(), "Games".into(), true, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let date = day_iso(start);
+
+    add_session(
+        archive.clone(),
+        goal.id,
+        goal.name.clone(),
+        start,
+        1500,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    add_session(
+        archive.clone(),
+        reward.id,
+        reward.name.clone(),
+        start + 3600,
+        600,
+        true,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let totals = list_day_totals(archive.clone(), Some(date.clone()), Some(date.clone())).unwrap();
+    assert_eq!(totals.len(), 1);
+    assert_eq!(totals[0].goal_secs, 1500);
+    assert_eq!(totals[0].reward_secs, 600);
+    assert_eq!(totals[0].session_count, 2);
+
+    // Another app rewrites the day with only the goal session.
+    let graph = temp.path().join("graphs").join(format!("{date}.mmd"));
+    let content = std::fs::read_to_string(&graph).unwrap();
+    let kept: Vec<&str> = content.lines().filter(|l| !l.contains("rew_")).collect();
+    std::fs::write(&graph, kept.join("\n")).unwrap();
+
+    let totals = list_day_totals(archive.clone(), Some(date.clone()), Some(date.clone())).unwrap();
+    assert_eq!(totals[0].reward_secs, 0);
+    assert_eq!(totals[0].session_count, 1);
+    assert_eq!(
+        successlib::list_sessions_between_dates(archive, Some(date.clone()), Some(date))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn days_written_by_add_session_are_not_parsed_again() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let start = yesterday_at_ten();
+    let date = day_iso(start);
+    let record = |start_at: i64| {
+        add_session(
+            archive.clone(),
+            goal.id,
+            goal.name.clone(),
+            start_at,
+            600,
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    };
+
+    record(start);
+    // Builds the index.
+    list_day_totals(archive.clone(), Some(date.clone()), Some(date.clone())).unwrap();
+    record(start + 3600);
+
+    // Blank the graph without changing its size or modification time: only
+    // a day that is parsed again loses its sessions.
+    let mut graph = std::fs::File::options()
+        .write(true)
+        .open(temp.path().join("graphs").join(format!("{date}.mmd")))
+        .unwrap();
+    let meta = graph.metadata().unwrap();
+    std::io::Write::write_all(&mut graph, &vec![b' '; meta.len() as usize]).unwrap();
+    graph.set_modified(meta.modified().unwrap()).unwrap();
+
+    let totals = list_day_totals(archive, Some(date.clone()), Some(date)).unwrap();
+    assert_eq!(totals[0].session_count, 2);
+    assert_eq!(totals[0].goal_secs, 1200);
+}
+
+#[test]
+fn recorded_days_span_the_whole_history() {
+    let temp = temp_archive_dir();
+    let archive = temp.path().to_str().unwrap().to_string();
+    assert_eq!(
+        successlib::first_recorded_day(archive.clone()).unwrap(),
+        None
+    );
+
+    let goal = add_goal(archive.clone(), "Study".into(), false, vec![], None).unwrap();
+    let yesterday = yesterday_at_ten();
+    for start in [
+        yesterday - 3 * 365 * 86400,
+        yesterday - 30 * 86400,
+        yesterday,
+    ] {
+        add_session(
+            archive.clone(),
+            goal.id,
+            goal.name.clone(),
+            start,
+            600,
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+    // Empty graphs and files that are not day graphs do not count.
+    let graphs = temp.path().join("graphs");
+    std::fs::write(graphs.join("2001-01-01.mmd"), "stateDiagram-v2\n").unwrap();
+    std::fs::write(graphs.join("README.txt"), "hand-written notes").unwrap();
+
+    let days = successlib::list_recorded_days(archive.clone()).unwrap();
+    assert_eq!(
+        days,
+        vec![
+            day_iso(yesterday - 3 * 365 * 86400),
+            day_iso(yesterday - 30 * 86400),
+            day_iso(yesterday),
+        ]
+    );
+    assert_eq!(
+        successlib::first_recorded_day(archive.clone()).unwrap(),
+        Some(days[0].clone())
+    );
+    assert_eq!(
+        successlib::last_recorded_day(archive).unwrap(),
+        Some(days[2].clone())
+    );
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use successlib::{
+    add_goal, add_session, create_snapshot, edit_note, get_note, list_goals, list_snapshots,
+    prune_snapshots, restore_snapshot, Archive, ArchiveOptions, Error, RetentionPolicy,
+};
+
+use common::{temp_archive_dir, yesterday_at_ten};
+
+#[test]
+fn restore_brings_back_the_captured_state() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    let read = add_goal(path.clone(), "Read".into(), false, vec![], None).unwrap();
+    edit_note(path.clone(), read.id, "chapter 1".into()).unwrap();
+    let snapshot = create_snapshot(path.clone(), Some("before the week".into())).unwrap();
+    assert_eq!(snapshot.label.as_deref(), Some("before the week"));
+    assert!(snapshot.file_count >= 3);
+
+    let write = add_goal(path.clone(), "Write".into(), false, vec![], None).unwrap();
+    edit_note(path.clone(), read.id, "chapter 2".into()).unwrap();
+    edit_note(path.clone(), write.id, "outline".into()).unwrap();
+    add_session(
+        path.clone(),
+        write.id,
+        write.name.clone(),
+        yesterday_at_ten(),
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let safety = restore_snapshot(path.clone(), snapshot.id.clone()).unwrap();
+    let names: Vec<String> = list_goals(path.clone(), None, None)
+        .unwrap()
+        .into_iter()
+        .map(|g| g.name)
+        .collect();
+    assert_eq!(names, vec!["Read"]);
+    assert_eq!(get_note(path.clone(), read.id).unwrap(), "chapter 1\n");
+    assert!(!temp
+        .path()
+        .join(format!("notes/goal_{}.md", write.id))
+        .exists());
+    assert!(
+        successlib::list_sessions_between_dates(path.clone(), None, None)
+            .unwrap()
+            .is_empty()
+    );
+
+    // The automatic snapshot undoes the restore.
+    let ids: Vec<String> = list_snapshots(path.clone())
+        .unwrap()
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    assert_eq!(ids, vec![safety.id.clone(), snapshot.id]);
+    restore_snapshot(path.clone(), safety.id).unwrap();
+    assert_eq!(list_goals(path.clone(), None, None).unwrap().len(), 2);
+    assert_eq!(
+        successlib::list_sessions_between_dates(path, None, None)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn unknown_snapshot_ids_are_not_found() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    add_goal(path.clone(), "Read".into(), false, vec![], None).unwrap();
+
+    for id in ["20990101-000000-000", "../goals", ""] {
+        let err = restore_snapshot(path.clone(), id.into()).unwrap_err();
+        assert!(matches!(err, Error::NotFound { .. }), "{id}: {err:?}");
+    }
+}
+
+#[test]
+fn retention_keeps_the_newest_snapshots() {
+    let temp = temp_archive_dir();
+    let path = temp.path().to_str().unwrap().to_string();
+    add_goal(path.clone(), "Read".into(), false, vec![], None).unwrap();
+    for i in 0..4 {
+        create_snapshot(path.clone(), Some(format!("#{i}"))).unwrap();
+    }
+
+    let removed = prune_snapshots(
+        path.clone(),
+        RetentionPolicy {
+            keep_last: Some(2),
+            keep_days: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(removed.len(), 2);
+    let labels: Vec<Option<String>> = list_snapshots(path.clone())
+        .unwrap()
+        .into_iter()
+        .map(|s| s.label)
+        .collect();
+    assert_eq!(labels, vec![Some("#3".into()), Some("#2".into())]);
+
+    // Everything here is younger than a day.
+    let policy = RetentionPolicy {
+        keep_last: Some(0),
+        keep_days: Some(1),
+    };
+    assert!(prune_snapshots(path.clone(), policy).unwrap().is_empty());
+
+    // A handle prunes on its own after each snapshot.
+    let options = ArchiveOptions {
+        snapshot_retention: Some(RetentionPolicy {
+            keep_last: Some(1),
+            keep_days: None,
+        }),
+        ..Default::default()
+    };
+    let archive = Archive::open(path.clone(), Some(options)).unwrap();
+    let latest = archive.create_snapshot(None).unwrap();
+    let ids: Vec<String> = list_snapshots(path)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    assert_eq!(ids, vec![latest.id]);
+}
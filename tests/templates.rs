@@ -0,0 +1,106 @@
+mod common;
+
+use successlib::{
+    add_goal, apply_template, delete_template, get_note, import_archive, list_goals,
+    list_templates, parse_template, save_template, Error, ImportMode,
+};
+
+use common::temp_archive;
+
+const LANGUAGE: &str = r#"
+id: language
+name: Learn a language
+description: Daily practice in three parts.
+goals:
+  - name: Vocabulary
+    pinned: true
+    note: |
+      ---
+      target: 20 words a day
+      ---
+      Use the Anki deck.
+    launch_commands:
+      - kind: Url
+        value: https://example.com/deck?goal={goal_id}
+  - name: Listening
+    quantity_name: minutes
+  - name: Episode of a show
+    is_reward: true
+"#;
+
+#[test]
+fn templates_are_stored_and_applied_in_one_go() {
+    let (_temp, path) = temp_archive();
+    let existing = add_goal(path.clone(), "Run".into(), false, vec![], None).unwrap();
+
+    let template = parse_template(LANGUAGE.into()).unwrap();
+    assert_eq!(template.goals.len(), 3);
+    save_template(path.clone(), template.clone()).unwrap();
+    assert_eq!(
+        list_templates(path.clone()).unwrap(),
+        vec![template.clone()]
+    );
+
+    let ids = apply_template(path.clone(), template).unwrap();
+    assert_eq!(ids, vec![existing.id + 1, existing.id + 2, existing.id + 3]);
+    let goals = list_goals(path.clone(), None, None).unwrap();
+    let names: Vec<&str> = goals.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["Run", "Vocabulary", "Listening", "Episode of a show"]
+    );
+    assert!(goals[1].pinned && goals[1].launch_commands.len() == 1);
+    assert_eq!(goals[2].quantity_name.as_deref(), Some("minutes"));
+    assert!(goals[3].is_reward);
+    // Each goal gets its own place in the manual order.
+    assert!(goals.windows(2).all(|w| w[0].sort_order < w[1].sort_order));
+
+    assert!(get_note(path.clone(), ids[0])
+        .unwrap()
+        .contains("target: 20 words a day"));
+    assert_eq!(get_note(path.clone(), ids[1]).unwrap(), "");
+
+    // Templates travel with the archive.
+    let (_copy, copy) = temp_archive();
+    let bundle = successlib::export_archive(path.clone()).unwrap();
+    import_archive(copy.clone(), bundle, ImportMode::Replace).unwrap();
+    assert_eq!(list_templates(copy).unwrap()[0].id, "language");
+
+    delete_template(path.clone(), "language".into()).unwrap();
+    assert!(list_templates(path).unwrap().is_empty());
+}
+
+#[test]
+fn invalid_templates_create_nothing() {
+    let (_temp, path) = temp_archive();
+    let err = parse_template("name: Empty\ngoals: []\n".into()).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+
+    let mut template = parse_template(LANGUAGE.into()).unwrap();
+    template.goals[2].name = "  ".into();
+    let err = apply_template(path.clone(), template.clone()).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+    assert!(list_goals(path.clone(), None, None).unwrap().is_empty());
+
+    template.goals[2].name = "Episode".into();
+    template.id = "../escape".into();
+    let err = save_template(path.clone(), template).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }), "{err:?}");
+
+    let err = delete_template(path, "missing".into()).unwrap_err();
+    assert!(matches!(err, Error::NotFound { .. }), "{err:?}");
+}
+
+#[test]
+fn a_damaged_template_does_not_hide_the_others() {
+    let (temp, path) = temp_archive();
+    save_template(path.clone(), parse_template(LANGUAGE.into()).unwrap()).unwrap();
+    std::fs::write(temp.path().join("templates/broken.yaml"), "goals: [").unwrap();
+
+    let ids: Vec<String> = list_templates(path)
+        .unwrap()
+        .into_iter()
+        .map(|t| t.id)
+        .collect();
+    assert_eq!(ids, vec!["language"]);
+}
@@ -0,0 +1,216 @@
+mod common;
+
+use successlib::{
+    add_goal, add_session, edit_note, export_archive, get_note, get_session_note, import_archive,
+    list_goals, list_sessions_between_dates, restore_snapshot, timestamp_to_date_iso, Error,
+    GoalStatus, ImportMode, SessionMetadata,
+};
+use tempfile::TempDir;
+
+use common::{temp_archive, yesterday_at};
+
+/// An archive with "Read" (1) and "Write" (2), a session of each and notes.
+fn source_archive() -> (TempDir, String) {
+    let (temp, path) = temp_archive();
+    let read = add_goal(path.clone(), "Read".into(), false, vec![], None).unwrap();
+    let write = add_goal(path.clone(), "Write".into(), false, vec![], None).unwrap();
+    edit_note(path.clone(), read.id, "reading list".into()).unwrap();
+    let metadata = SessionMetadata {
+        note: Some("chapter 3".into()),
+        ..Default::default()
+    };
+    add_session(
+        path.clone(),
+        read.id,
+        read.name,
+        yesterday_at(9),
+        1800,
+        false,
+        None,
+        None,
+        Some(metadata),
+    )
+    .unwrap();
+    add_session(
+        path.clone(),
+        write.id,
+        write.name,
+        yesterday_at(11),
+        1800,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    (temp, path)
+}
+
+fn goal_names(path: &str) -> Vec<(u64, String)> {
+    list_goals(path.to_string(), None, None)
+        .unwrap()
+        .into_iter()
+        .map(|g| (g.id, g.name))
+        .collect()
+}
+
+#[test]
+fn merge_remaps_goal_and_session_ids() {
+    let (_source, source) = source_archive();
+    let bundle = export_archive(source).unwrap();
+
+    let (_target, target) = temp_archive();
+    let write = add_goal(target.clone(), "Write".into(), false, vec![], None).unwrap();
+    add_goal(target.clone(), "Run".into(), false, vec![], None).unwrap();
+    add_session(
+        target.clone(),
+        write.id,
+        write.name,
+        yesterday_at(7),
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let summary = import_archive(target.clone(), bundle.clone(), ImportMode::Merge).unwrap();
+    assert_eq!((summary.goals_added, summary.goals_matched), (1, 1));
+    assert_eq!((summary.sessions_added, summary.sessions_skipped), (2, 0));
+    assert_eq!(summary.notes_written, 2);
+    assert_eq!(
+        goal_names(&target),
+        vec![(1, "Write".into()), (2, "Run".into()), (3, "Read".into())]
+    );
+    assert_eq!(get_note(target.clone(), 3).unwrap(), "reading list\n");
+
+    let sessions = list_sessions_between_dates(target.clone(), None, None).unwrap();
+    let placed: Vec<(&str, u64)> = sessions
+        .iter()
+        .map(|s| (s.id.as_str(), s.goal_id))
+        .collect();
+    assert_eq!(placed, vec![("sess_1", 1), ("sess_2", 3), ("sess_3", 1)]);
+    let date = timestamp_to_date_iso(sessions[1].start_at);
+    assert_eq!(
+        get_session_note(target.clone(), date, "sess_2".into()).unwrap(),
+        "chapter 3\n"
+    );
+
+    // Importing the same bundle again changes nothing.
+    let again = import_archive(target.clone(), bundle, ImportMode::Merge).unwrap();
+    assert_eq!((again.goals_added, again.goals_matched), (0, 2));
+    assert_eq!((again.sessions_added, again.sessions_skipped), (0, 2));
+    assert_eq!(again.notes_written, 0);
+    assert_eq!(
+        list_sessions_between_dates(target, None, None)
+            .unwrap()
+            .len(),
+        3
+    );
+}
+
+#[test]
+fn merged_sessions_start_their_goals() {
+    let (_source, source) = source_archive();
+    let bundle = export_archive(source).unwrap();
+    let (_target, target) = temp_archive();
+    add_goal(target.clone(), "Read".into(), false, vec![], None).unwrap();
+
+    import_archive(target.clone(), bundle, ImportMode::Merge).unwrap();
+    let statuses: Vec<(String, GoalStatus)> = list_goals(target, None, None)
+        .unwrap()
+        .into_iter()
+        .map(|g| (g.name, g.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("Read".into(), GoalStatus::DOING),
+            ("Write".into(), GoalStatus::DOING)
+        ]
+    );
+}
+
+#[test]
+fn replace_copies_the_bundle_and_can_be_undone() {
+    let (_source, source) = source_archive();
+    let bundle = export_archive(source).unwrap();
+
+    let (target_dir, target) = temp_archive();
+    let run = add_goal(target.clone(), "Run".into(), false, vec![], None).unwrap();
+    edit_note(target.clone(), run.id, "5k plan".into()).unwrap();
+
+    let summary = import_archive(target.clone(), bundle, ImportMode::Replace).unwrap();
+    assert_eq!((summary.goals_added, summary.sessions_added), (2, 2));
+    assert_eq!(
+        goal_names(&target),
+        vec![(1, "Read".into()), (2, "Write".into())]
+    );
+    assert_eq!(get_note(target.clone(), 1).unwrap(), "reading list\n");
+    assert!(target_dir.path().join("success.yaml").exists());
+
+    restore_snapshot(target.clone(), summary.snapshot_id).unwrap();
+    assert_eq!(goal_names(&target), vec![(1, "Run".into())]);
+    assert_eq!(get_note(target, 1).unwrap(), "5k plan\n");
+}
+
+#[test]
+fn bundles_with_paths_outside_the_archive_are_rejected() {
+    let (target_dir, target) = temp_archive();
+    add_goal(target.clone(), "Run".into(), false, vec![], None).unwrap();
+    let bundle = r#"{"format":1,"created_at":0,"files":{"../escape.md":"x"}}"#;
+
+    let err = import_archive(target.clone(), bundle.into(), ImportMode::Replace).unwrap_err();
+    assert!(matches!(err, Error::Parse { .. }), "{err:?}");
+    assert_eq!(goal_names(&target), vec![(1, "Run".into())]);
+    assert!(!target_dir.path().join("snapshots").exists());
+}
+
+#[test]
+fn merged_sessions_go_through_the_overlap_check() {
+    let (_source, source) = source_archive();
+    let bundle = export_archive(source).unwrap();
+
+    let (_target, target) = temp_archive();
+    let run = add_goal(target.clone(), "Run".into(), false, vec![], None).unwrap();
+    // Overlaps the session of "Read" in the bundle.
+    add_session(
+        target.clone(),
+        run.id,
+        run.name,
+        yesterday_at(9) + 600,
+        600,
+        false,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let summary = import_archive(target.clone(), bundle, ImportMode::Merge).unwrap();
+    assert_eq!((summary.sessions_added, summary.sessions_skipped), (1, 1));
+    // Only the goal note was written; the skipped session's note was not.
+    assert_eq!(summary.notes_written, 1);
+    let goals: Vec<u64> = list_sessions_between_dates(target, None, None)
+        .unwrap()
+        .iter()
+        .map(|s| s.goal_id)
+        .collect();
+    assert_eq!(goals, [run.id, 3]);
+}
+
+#[test]
+fn replacing_with_a_bundle_without_manifest_keeps_the_target_one() {
+    let (target_dir, target) = temp_archive();
+    add_goal(target.clone(), "Run".into(), false, vec![], None).unwrap();
+    let manifest = target_dir.path().join("success.yaml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str("device: laptop\n");
+    std::fs::write(&manifest, &content).unwrap();
+
+    let bundle = r#"{"format":1,"created_at":0,"files":{"goals.yaml":"- id: 1\n  name: Read\n"}}"#;
+    import_archive(target.clone(), bundle.into(), ImportMode::Replace).unwrap();
+    assert_eq!(goal_names(&target), vec![(1, "Read".into())]);
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), content);
+}
@@ -0,0 +1,220 @@
+//! The TypeScript declarations shipped with the web build are written by
+//! hand, so check them against the Rust types they describe: every
+//! declared interface must list the fields of the struct of the same name,
+//! and every declared union the variants (and variant fields) of the enum.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+const TYPINGS: &str = include_str!("../src/wasm.d.ts");
+
+/// A type as it reaches JavaScript: the field names of a struct, or the
+/// variants of an enum with the field names of each. Errors only declare
+/// their variant names, as their fields are typed `unknown`.
+#[derive(Debug, PartialEq)]
+enum Shape {
+    Fields(BTreeSet<String>),
+    Variants(BTreeMap<String, BTreeSet<String>>),
+    Kinds(BTreeSet<String>),
+}
+
+fn leading_ident(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+fn quoted(text: &str) -> Vec<String> {
+    text.split('"')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect()
+}
+
+/// The `pub struct` and `pub enum` items of the crate sources, assuming
+/// rustfmt layout.
+fn rust_shapes() -> BTreeMap<String, Shape> {
+    let mut shapes = BTreeMap::new();
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    for entry in fs::read_dir(src).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+        let mut lines = source.lines();
+        while let Some(line) = lines.next() {
+            let (is_enum, rest) = if let Some(rest) = line.strip_prefix("pub struct ") {
+                (false, rest)
+            } else if let Some(rest) = line.strip_prefix("pub enum ") {
+                (true, rest)
+            } else {
+                continue;
+            };
+            if !line.ends_with('{') {
+                continue;
+            }
+            let name = leading_ident(rest).to_string();
+            let body: Vec<&str> = lines.by_ref().take_while(|l| *l != "}").collect();
+            let shape = if is_enum {
+                Shape::Variants(rust_variants(&body))
+            } else {
+                Shape::Fields(
+                    body.iter()
+                        .filter_map(|l| l.strip_prefix("    pub "))
+                        .map(|l| leading_ident(l).to_string())
+                        .collect(),
+                )
+            };
+            shapes.insert(name, shape);
+        }
+    }
+    shapes
+}
+
+fn rust_variants(body: &[&str]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut variants = BTreeMap::new();
+    let mut current: Option<String> = None;
+    for line in body {
+        if let Some(field) = line.strip_prefix("        ") {
+            let field = field.trim_start();
+            if let (Some(name), false) = (&current, field.starts_with(['#', '/'])) {
+                let entry: &mut BTreeSet<String> = variants.get_mut(name).unwrap();
+                entry.insert(leading_ident(field).to_string());
+            }
+            continue;
+        }
+        let Some(item) = line.strip_prefix("    ") else {
+            continue;
+        };
+        if item.starts_with(['#', '/', '}']) || item.is_empty() {
+            continue;
+        }
+        let name = leading_ident(item).to_string();
+        let mut fields = BTreeSet::new();
+        if let Some(inline) = item
+            .split_once('{')
+            .and_then(|(_, rest)| rest.split_once('}'))
+        {
+            for field in inline.0.split(',') {
+                if let Some((field, _)) = field.split_once(':') {
+                    fields.insert(field.trim().to_string());
+                }
+            }
+        }
+        variants.insert(name.clone(), fields);
+        current = Some(name);
+    }
+    variants
+}
+
+/// The `export interface` and `export type` declarations of the typings.
+fn declared_shapes() -> BTreeMap<String, Shape> {
+    let mut shapes = BTreeMap::new();
+    let mut lines = TYPINGS.lines();
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("export interface ") {
+            let name = leading_ident(rest).to_string();
+            let body: Vec<&str> = lines.by_ref().take_while(|l| *l != "}").collect();
+            let shape = match body.iter().position(|l| l.trim() == "kind:") {
+                // An error interface: `kind` names the variant.
+                Some(at) => Shape::Kinds(
+                    body[at + 1..]
+                        .iter()
+                        .take_while(|l| l.trim_start().starts_with('|'))
+                        .flat_map(|l| quoted(l))
+                        .collect(),
+                ),
+                None => Shape::Fields(
+                    body.iter()
+                        .filter_map(|l| l.strip_prefix("    "))
+                        .filter(|l| !l.starts_with([' ', '[']))
+                        .map(|l| leading_ident(l).to_string())
+                        .collect(),
+                ),
+            };
+            shapes.insert(name, shape);
+        } else if let Some(rest) = line.strip_prefix("export type ") {
+            let name = leading_ident(rest).to_string();
+            let mut text = rest.split_once('=').unwrap().1.to_string();
+            while !text.trim_end().ends_with(';')
+                || text.matches('{').count() > text.matches('}').count()
+            {
+                text.push('\n');
+                text.push_str(lines.next().unwrap());
+            }
+            shapes.insert(name, Shape::Variants(declared_variants(&text)));
+        }
+    }
+    shapes
+}
+
+fn declared_variants(union: &str) -> BTreeMap<String, BTreeSet<String>> {
+    if !union.contains('{') {
+        return quoted(union)
+            .into_iter()
+            .map(|variant| (variant, BTreeSet::new()))
+            .collect();
+    }
+    let mut variants = BTreeMap::new();
+    for object in union.split('{').skip(1) {
+        let object = object.split('}').next().unwrap();
+        let mut tag = None;
+        let mut fields = BTreeSet::new();
+        for member in object.split(';') {
+            let Some((key, value)) = member.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().trim_end_matches('?');
+            if key == "type" {
+                tag = quoted(value).pop();
+            } else {
+                fields.insert(key.to_string());
+            }
+        }
+        variants.insert(tag.expect("tagged union member without a type"), fields);
+    }
+    variants
+}
+
+#[test]
+fn typings_match_the_rust_types() {
+    let rust = rust_shapes();
+    let declared = declared_shapes();
+    assert!(
+        declared.len() > 40,
+        "parsed only {} declarations",
+        declared.len()
+    );
+    for (name, shape) in &declared {
+        let actual = match (shape, &rust.get(name)) {
+            (Shape::Kinds(_), Some(Shape::Variants(variants))) => {
+                &Shape::Kinds(variants.keys().cloned().collect())
+            }
+            (_, Some(actual)) => *actual,
+            (_, None) => panic!("{name} is declared but no Rust type has that name"),
+        };
+        assert_eq!(
+            shape, actual,
+            "the declaration of {name} drifted from the Rust type"
+        );
+    }
+}
+
+#[test]
+fn declarations_are_read_field_by_field() {
+    let declared = declared_shapes();
+    let Some(Shape::Fields(fields)) = declared.get("Goal") else {
+        panic!("Goal is not declared as an interface");
+    };
+    assert!(fields.contains("quantity_name"));
+    let Some(Shape::Variants(events)) = declared.get("ArchiveEvent") else {
+        panic!("ArchiveEvent is not declared as a union");
+    };
+    assert_eq!(
+        events["GoalTrashed"],
+        BTreeSet::from(["goal_id".to_string(), "trashed".to_string()])
+    );
+}